@@ -0,0 +1,78 @@
+use std::{str::FromStr, collections::BTreeMap};
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use primitive_types::{U256, H160};
+use evm::Config;
+use evm::executor::{StackExecutor, MemoryStackState, StackSubstateMetadata};
+use evm::backend::{MemoryAccount, MemoryVicinity, MemoryBackend, CountingBackend, AccessStats};
+use evm::generator::Generator;
+
+// Increments storage slots `i & 3` for `i` in `0..64`, so that most storage
+// accesses are warm.
+const STORAGE_LOOP: &str = "60005b80600316805460010190556001018060401160025700";
+
+fn vicinity() -> MemoryVicinity {
+	MemoryVicinity {
+		gas_price: U256::zero(),
+		origin: H160::default(),
+		block_hashes: Vec::new(),
+		max_block_hashes: 256,
+		block_number: Default::default(),
+		block_coinbase: Default::default(),
+		block_timestamp: Default::default(),
+		block_difficulty: Default::default(),
+		block_gas_limit: Default::default(),
+		chain_id: U256::one(),
+	}
+}
+
+fn run_storage_loop(
+	vicinity: &MemoryVicinity,
+	accounts: &BTreeMap<H160, MemoryAccount>,
+) -> AccessStats {
+	let config = Config::istanbul();
+	let caller = H160::from_str("0xf000000000000000000000000000000000000000").unwrap();
+	let contract = H160::from_str("0x1000000000000000000000000000000000000000").unwrap();
+
+	let mut state = accounts.clone();
+	state.insert(contract, MemoryAccount {
+		nonce: U256::one(),
+		balance: U256::zero(),
+		storage: BTreeMap::new(),
+		code: hex::decode(STORAGE_LOOP).unwrap(),
+	});
+	state.insert(caller, MemoryAccount {
+		nonce: U256::one(),
+		balance: U256::from(10000000),
+		storage: BTreeMap::new(),
+		code: Vec::new(),
+	});
+
+	let mut backend = CountingBackend::new(MemoryBackend::new(vicinity, state));
+	{
+		let metadata = StackSubstateMetadata::new(u64::MAX, &config);
+		let state = MemoryStackState::new(metadata, &backend);
+		let mut executor = StackExecutor::new(state, &config);
+		let _reason = executor.transact_call(caller, contract, U256::zero(), Vec::new(), u64::MAX);
+	}
+	backend.take_stats()
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+	let vicinity = vicinity();
+	// Unrelated accounts, so that lookups go through a realistically sized
+	// state.
+	let accounts = Generator::new(0).state(1000, 16, 64);
+	// Report throughput in backend accesses, so that the access count of the
+	// loop shows up in criterion's output.
+	let accesses = run_storage_loop(&vicinity, &accounts).total();
+	let mut group = c.benchmark_group("backend access");
+	group.throughput(Throughput::Elements(accesses));
+	group.bench_function(
+		"storage loop with counting backend",
+		|b| b.iter(|| run_storage_loop(&vicinity, &accounts)),
+	);
+	group.finish();
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
@@ -12,6 +12,7 @@ fn run_loop_contract() {
 		gas_price: U256::zero(),
 		origin: H160::default(),
 		block_hashes: Vec::new(),
+		max_block_hashes: 256,
 		block_number: Default::default(),
 		block_coinbase: Default::default(),
 		block_timestamp: Default::default(),
@@ -0,0 +1,60 @@
+use core::cmp::min;
+use core::ops::Deref;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+
+/// Code or input data of a machine, either shared or borrowed from the
+/// caller.
+#[derive(Clone, Debug)]
+pub enum Bytes<'a> {
+	/// Reference counted bytes.
+	Shared(Rc<Vec<u8>>),
+	/// Bytes borrowed from the caller, avoiding any copy or allocation.
+	Borrowed(&'a [u8]),
+}
+
+impl<'a> Deref for Bytes<'a> {
+	type Target = [u8];
+
+	fn deref(&self) -> &[u8] {
+		match self {
+			Self::Shared(bytes) => &bytes[..],
+			Self::Borrowed(bytes) => bytes,
+		}
+	}
+}
+
+impl<'a> From<Rc<Vec<u8>>> for Bytes<'a> {
+	fn from(bytes: Rc<Vec<u8>>) -> Self {
+		Self::Shared(bytes)
+	}
+}
+
+impl<'a> From<&'a [u8]> for Bytes<'a> {
+	fn from(bytes: &'a [u8]) -> Self {
+		Self::Borrowed(bytes)
+	}
+}
+
+/// Copy `len` bytes of `code` starting at `offset`, truncated at the end of
+/// the code.
+pub fn code_chunk(code: &[u8], offset: usize, len: usize) -> Vec<u8> {
+	let start = min(offset, code.len());
+	let end = min(offset.saturating_add(len), code.len());
+	code[start..end].to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::code_chunk;
+
+	#[test]
+	fn chunks_are_truncated() {
+		let code = [1, 2, 3, 4];
+		assert_eq!(code_chunk(&code, 1, 2), [2, 3]);
+		assert_eq!(code_chunk(&code, 2, 10), [3, 4]);
+		assert_eq!(code_chunk(&code, 4, 1), []);
+		assert_eq!(code_chunk(&code, 10, 1), []);
+		assert_eq!(code_chunk(&code, 1, usize::MAX), [2, 3, 4]);
+	}
+}
@@ -1,8 +1,15 @@
 use alloc::borrow::Cow;
 use crate::Opcode;
 
-/// Trap which indicates that an `ExternalOpcode` has to be handled.
-pub type Trap = Opcode;
+/// Trap which indicates that the machine has to be handled externally.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Trap {
+	/// An `ExternalOpcode` has to be handled.
+	External(Opcode),
+	/// A breakpoint was hit at the given position. The instruction at that
+	/// position has not been executed yet.
+	Breakpoint(usize),
+}
 
 /// Capture represents the result of execution.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -62,6 +69,30 @@ impl ExitReason {
 			_ => false,
 		}
 	}
+
+	/// Stable numeric code of the exit reason. The high byte is the kind
+	/// (`0` succeed, `1` revert, `2` error, `3` fatal) and the low byte is
+	/// the code of the inner reason. Codes are never reused or changed
+	/// across versions, so they can be stored in receipts.
+	pub fn code(&self) -> u16 {
+		match self {
+			Self::Succeed(s) => s.code() as u16,
+			Self::Revert(r) => 0x0100 | r.code() as u16,
+			Self::Error(e) => 0x0200 | e.code() as u16,
+			Self::Fatal(f) => 0x0300 | f.code() as u16,
+		}
+	}
+
+	/// Stable short identifier of the exit reason, the one of the inner
+	/// reason. Identifiers are never changed across versions.
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			Self::Succeed(s) => s.as_str(),
+			Self::Revert(r) => r.as_str(),
+			Self::Error(e) => e.as_str(),
+			Self::Fatal(f) => f.as_str(),
+		}
+	}
 }
 
 /// Exit succeed reason.
@@ -77,6 +108,26 @@ pub enum ExitSucceed {
 	Suicided,
 }
 
+impl ExitSucceed {
+	/// Stable numeric code, see `ExitReason::code`.
+	pub fn code(&self) -> u8 {
+		match self {
+			Self::Stopped => 0x00,
+			Self::Returned => 0x01,
+			Self::Suicided => 0x02,
+		}
+	}
+
+	/// Stable short identifier, see `ExitReason::as_str`.
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			Self::Stopped => "stopped",
+			Self::Returned => "returned",
+			Self::Suicided => "suicided",
+		}
+	}
+}
+
 impl From<ExitSucceed> for ExitReason {
 	fn from(s: ExitSucceed) -> Self {
 		Self::Succeed(s)
@@ -92,6 +143,22 @@ pub enum ExitRevert {
 	Reverted,
 }
 
+impl ExitRevert {
+	/// Stable numeric code, see `ExitReason::code`.
+	pub fn code(&self) -> u8 {
+		match self {
+			Self::Reverted => 0x00,
+		}
+	}
+
+	/// Stable short identifier, see `ExitReason::as_str`.
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			Self::Reverted => "reverted",
+		}
+	}
+}
+
 impl From<ExitRevert> for ExitReason {
 	fn from(s: ExitRevert) -> Self {
 		Self::Revert(s)
@@ -107,6 +174,9 @@ pub enum ExitError {
 	StackUnderflow,
 	/// Trying to push into a stack over stack limit.
 	StackOverflow,
+	/// The stack holds fewer items than the opcode pops, found by strict
+	/// stack validation before its gas is computed.
+	OpcodeStackUnderflow(Opcode),
 	/// Jump destination is invalid.
 	InvalidJump,
 	/// An opcode accesses memory region, but the region is invalid.
@@ -119,6 +189,8 @@ pub enum ExitError {
 	CreateCollision,
 	/// Create init code exceeds limit (runtime).
 	CreateContractLimit,
+	/// Created contract code starts with the reserved 0xEF byte (runtime).
+	InvalidCodePrefix,
 
 	///	An opcode accesses external information, but the request is off offset
 	///	limit (runtime).
@@ -137,6 +209,53 @@ pub enum ExitError {
 	Other(Cow<'static, str>),
 }
 
+impl ExitError {
+	/// Stable numeric code, see `ExitReason::code`. All `Other` errors share
+	/// the same code.
+	pub fn code(&self) -> u8 {
+		match self {
+			Self::StackUnderflow => 0x00,
+			Self::StackOverflow => 0x01,
+			Self::InvalidJump => 0x02,
+			Self::InvalidRange => 0x03,
+			Self::DesignatedInvalid => 0x04,
+			Self::CallTooDeep => 0x05,
+			Self::CreateCollision => 0x06,
+			Self::CreateContractLimit => 0x07,
+			Self::OutOfOffset => 0x08,
+			Self::OutOfGas => 0x09,
+			Self::OutOfFund => 0x0a,
+			Self::PCUnderflow => 0x0b,
+			Self::CreateEmpty => 0x0c,
+			Self::InvalidCodePrefix => 0x0d,
+			Self::OpcodeStackUnderflow(_) => 0x0e,
+			Self::Other(_) => 0xff,
+		}
+	}
+
+	/// Stable short identifier, see `ExitReason::as_str`.
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			Self::StackUnderflow => "stack_underflow",
+			Self::StackOverflow => "stack_overflow",
+			Self::InvalidJump => "invalid_jump",
+			Self::InvalidRange => "invalid_range",
+			Self::DesignatedInvalid => "designated_invalid",
+			Self::CallTooDeep => "call_too_deep",
+			Self::CreateCollision => "create_collision",
+			Self::CreateContractLimit => "create_contract_limit",
+			Self::OutOfOffset => "out_of_offset",
+			Self::OutOfGas => "out_of_gas",
+			Self::OutOfFund => "out_of_fund",
+			Self::PCUnderflow => "pc_underflow",
+			Self::CreateEmpty => "create_empty",
+			Self::InvalidCodePrefix => "invalid_code_prefix",
+			Self::OpcodeStackUnderflow(_) => "opcode_stack_underflow",
+			Self::Other(_) => "other",
+		}
+	}
+}
+
 impl From<ExitError> for ExitReason {
 	fn from(s: ExitError) -> Self {
 		Self::Error(s)
@@ -154,13 +273,61 @@ pub enum ExitFatal {
 	UnhandledInterrupt,
 	/// The environment explictly set call errors as fatal error.
 	CallErrorAsFatal(ExitError),
+	/// The instruction limit set by the environment was exceeded.
+	ExecutionTimeout,
 
 	/// Other fatal errors.
 	Other(Cow<'static, str>),
 }
 
+impl ExitFatal {
+	/// Stable numeric code, see `ExitReason::code`. The wrapped error of
+	/// `CallErrorAsFatal` is not part of the code.
+	pub fn code(&self) -> u8 {
+		match self {
+			Self::NotSupported => 0x00,
+			Self::UnhandledInterrupt => 0x01,
+			Self::CallErrorAsFatal(_) => 0x02,
+			Self::ExecutionTimeout => 0x03,
+			Self::Other(_) => 0xff,
+		}
+	}
+
+	/// Stable short identifier, see `ExitReason::as_str`.
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			Self::NotSupported => "not_supported",
+			Self::UnhandledInterrupt => "unhandled_interrupt",
+			Self::CallErrorAsFatal(_) => "call_error_as_fatal",
+			Self::ExecutionTimeout => "execution_timeout",
+			Self::Other(_) => "other",
+		}
+	}
+}
+
 impl From<ExitFatal> for ExitReason {
 	fn from(s: ExitFatal) -> Self {
 		Self::Fatal(s)
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use crate::{ExitReason, ExitSucceed, ExitRevert, ExitError, ExitFatal, Opcode};
+
+	#[test]
+	fn stable_codes() {
+		// These values are part of the compatibility guarantee and must never
+		// change.
+		assert_eq!(ExitReason::from(ExitSucceed::Returned).code(), 0x0001);
+		assert_eq!(ExitReason::from(ExitRevert::Reverted).code(), 0x0100);
+		assert_eq!(ExitReason::from(ExitError::OutOfGas).code(), 0x0209);
+		assert_eq!(ExitReason::from(ExitError::Other("x".into())).code(), 0x02ff);
+		assert_eq!(ExitReason::from(ExitError::OpcodeStackUnderflow(Opcode::ADD)).code(), 0x020e);
+		assert_eq!(ExitReason::from(ExitFatal::NotSupported).code(), 0x0300);
+
+		assert_eq!(ExitReason::from(ExitError::OutOfGas).as_str(), "out_of_gas");
+		assert_eq!(ExitReason::from(ExitFatal::CallErrorAsFatal(ExitError::OutOfGas)).as_str(),
+			"call_error_as_fatal");
+	}
+}
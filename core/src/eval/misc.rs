@@ -1,4 +1,3 @@
-use core::cmp::min;
 use primitive_types::{H256, U256};
 use super::Control;
 use crate::{Machine, ExitError, ExitSucceed, ExitFatal, ExitRevert};
@@ -146,8 +145,10 @@ pub fn msize(state: &mut Machine) -> Control {
 
 #[inline]
 pub fn push(state: &mut Machine, n: usize, position: usize) -> Control {
-	let end = min(position + 1 + n, state.code.len());
-	let slice = &state.code[(position + 1)..end];
+	// Immediate bytes past the end of the code are zeros. Slicing through
+	// `get` keeps this free of panicking bounds checks.
+	let code = state.code.get((position + 1)..).unwrap_or(&[]);
+	let slice = code.get(..n).unwrap_or(code);
 	let mut val = [0u8; 32];
 	val[(32 - slice.len())..32].copy_from_slice(slice);
 
@@ -10,6 +10,7 @@ extern crate alloc;
 
 mod memory;
 mod stack;
+mod bytes;
 mod valids;
 mod opcode;
 mod error;
@@ -18,6 +19,7 @@ mod utils;
 
 pub use crate::memory::Memory;
 pub use crate::stack::Stack;
+pub use crate::bytes::{Bytes, code_chunk};
 pub use crate::valids::Valids;
 pub use crate::opcode::Opcode;
 pub use crate::error::{Trap, Capture, ExitReason, ExitSucceed, ExitError, ExitRevert, ExitFatal};
@@ -25,15 +27,16 @@ pub use crate::error::{Trap, Capture, ExitReason, ExitSucceed, ExitError, ExitRe
 use core::ops::Range;
 use alloc::vec::Vec;
 use alloc::rc::Rc;
+use alloc::collections::BTreeSet;
 use primitive_types::U256;
 use crate::eval::{eval, Control};
 
 /// Core execution layer for EVM.
-pub struct Machine {
+pub struct Machine<'a> {
 	/// Program data.
-	data: Rc<Vec<u8>>,
+	data: Bytes<'a>,
 	/// Program code.
-	code: Rc<Vec<u8>>,
+	code: Bytes<'a>,
 	/// Program counter.
 	position: Result<usize, ExitReason>,
 	/// Return value.
@@ -44,9 +47,13 @@ pub struct Machine {
 	memory: Memory,
 	/// Stack.
 	stack: Stack,
+	/// Positions at which the machine stops before executing.
+	breakpoints: BTreeSet<usize>,
+	/// Whether the breakpoint at the current position was already hit.
+	breakpoint_hit: bool,
 }
 
-impl Machine {
+impl<'a> Machine<'a> {
 	/// Reference of machine stack.
 	pub fn stack(&self) -> &Stack { &self.stack }
 	/// Mutable reference of machine stack.
@@ -57,6 +64,8 @@ impl Machine {
 	pub fn memory_mut(&mut self) -> &mut Memory { &mut self.memory }
 	/// Return a reference of the program counter.
 	pub fn position(&self) -> &Result<usize, ExitReason> { &self.position }
+	/// Reference of the program code.
+	pub fn code(&self) -> &Bytes<'a> { &self.code }
 
 	/// Create a new machine with given code and data.
 	pub fn new(
@@ -64,6 +73,28 @@ impl Machine {
 		data: Rc<Vec<u8>>,
 		stack_limit: usize,
 		memory_limit: usize
+	) -> Self {
+		Self::with_bytes(code.into(), data.into(), stack_limit, memory_limit)
+	}
+
+	/// Create a new machine borrowing the given code and data, without copying
+	/// them.
+	pub fn new_borrowed(
+		code: &'a [u8],
+		data: &'a [u8],
+		stack_limit: usize,
+		memory_limit: usize
+	) -> Self {
+		Self::with_bytes(code.into(), data.into(), stack_limit, memory_limit)
+	}
+
+	/// Create a new machine with given code and data, either shared or
+	/// borrowed.
+	pub fn with_bytes(
+		code: Bytes<'a>,
+		data: Bytes<'a>,
+		stack_limit: usize,
+		memory_limit: usize
 	) -> Self {
 		let valids = Valids::new(&code[..]);
 
@@ -75,9 +106,32 @@ impl Machine {
 			valids,
 			memory: Memory::new(memory_limit),
 			stack: Stack::new(stack_limit),
+			breakpoints: BTreeSet::new(),
+			breakpoint_hit: false,
 		}
 	}
 
+	/// Add a breakpoint. The machine traps with `Trap::Breakpoint` before
+	/// executing the instruction at `position`. Stepping again resumes.
+	pub fn add_breakpoint(&mut self, position: usize) {
+		self.breakpoints.insert(position);
+	}
+
+	/// Remove a breakpoint. Returns whether it was set.
+	pub fn remove_breakpoint(&mut self, position: usize) -> bool {
+		self.breakpoints.remove(&position)
+	}
+
+	/// Remove all breakpoints.
+	pub fn clear_breakpoints(&mut self) {
+		self.breakpoints.clear();
+	}
+
+	/// Currently set breakpoints.
+	pub fn breakpoints(&self) -> &BTreeSet<usize> {
+		&self.breakpoints
+	}
+
 	/// Explict exit of the machine. Further step will return error.
 	pub fn exit(&mut self, reason: ExitReason) {
 		self.position = Err(reason);
@@ -130,6 +184,13 @@ impl Machine {
 	pub fn step(&mut self) -> Result<(), Capture<ExitReason, Trap>> {
 		let position = *self.position.as_ref().map_err(|reason| Capture::Exit(reason.clone()))?;
 
+		if self.breakpoint_hit {
+			self.breakpoint_hit = false;
+		} else if self.breakpoints.contains(&position) {
+			self.breakpoint_hit = true;
+			return Err(Capture::Trap(Trap::Breakpoint(position)))
+		}
+
 		match self.code.get(position).map(|v| Opcode(*v)) {
 			Some(opcode) => {
 				match eval(self, opcode, position) {
@@ -147,7 +208,7 @@ impl Machine {
 					},
 					Control::Trap(opcode) => {
 						self.position = Ok(position + 1);
-						Err(Capture::Trap(opcode))
+						Err(Capture::Trap(Trap::External(opcode)))
 					},
 				}
 			},
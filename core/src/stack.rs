@@ -66,6 +66,23 @@ impl Stack {
 		}
 	}
 
+	#[inline]
+	/// Peek the `N` values at the top of the stack, with the top of the
+	/// stack at index `0`. If the stack holds less than `N` values,
+	/// `StackError::Underflow` is returned.
+	pub fn peek_many<const N: usize>(&self) -> Result<[H256; N], ExitError> {
+		let len = self.data.len();
+		if len < N {
+			return Err(ExitError::StackUnderflow)
+		}
+
+		let mut values = [H256::default(); N];
+		for (value, item) in values.iter_mut().zip(self.data[len - N..].iter().rev()) {
+			*value = *item;
+		}
+		Ok(values)
+	}
+
 	#[inline]
 	/// Set a value at given index for the stack, where the top of the
 	/// stack is at index `0`. If the index is too large,
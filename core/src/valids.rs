@@ -1,4 +1,4 @@
-use alloc::vec::Vec;
+use alloc::{vec, vec::Vec};
 use crate::Opcode;
 
 /// Mapping of valid jump destination from code.
@@ -8,19 +8,18 @@ pub struct Valids(Vec<bool>);
 impl Valids {
 	/// Create a new valid mapping from given code bytes.
 	pub fn new(code: &[u8]) -> Self {
-		let mut valids: Vec<bool> = Vec::with_capacity(code.len());
-		valids.resize(code.len(), false);
-
-		let mut i = 0;
-		while i < code.len() {
-			let opcode = Opcode(code[i]);
+		let mut valids = vec![false; code.len()];
+
+		// Iterating over the code and the mapping together avoids bounds
+		// checks on every byte. Push data is skipped by advancing the iterator
+		// past it.
+		let mut entries = code.iter().zip(valids.iter_mut());
+		while let Some((byte, valid)) = entries.next() {
+			let opcode = Opcode(*byte);
 			if opcode == Opcode::JUMPDEST {
-				valids[i] = true;
-				i += 1;
+				*valid = true;
 			} else if let Some(v) = opcode.is_push() {
-				i += v as usize + 1;
-			} else {
-				i += 1;
+				entries.nth(v as usize - 1);
 			}
 		}
 
@@ -43,14 +42,19 @@ impl Valids {
 	/// Returns `true` if the position is a valid jump destination. If
 	/// not, returns `false`.
 	pub fn is_valid(&self, position: usize) -> bool {
-		if position >= self.0.len() {
-			return false;
-		}
+		self.0.get(position).copied().unwrap_or(false)
+	}
+}
 
-		if !self.0[position] {
-			return false;
-		}
+#[cfg(test)]
+mod tests {
+	use super::Valids;
 
-		true
+	#[test]
+	fn push_data_is_skipped() {
+		// JUMPDEST; PUSH2 JUMPDEST JUMPDEST; JUMPDEST; PUSH3 JUMPDEST, truncated.
+		let valids = Valids::new(&[0x5b, 0x61, 0x5b, 0x5b, 0x5b, 0x62, 0x5b]);
+		let positions = (0..8).filter(|position| valids.is_valid(*position)).collect::<Vec<_>>();
+		assert_eq!(positions, [0, 4]);
 	}
 }
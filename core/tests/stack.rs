@@ -0,0 +1,26 @@
+use std::rc::Rc;
+use evm_core::{Machine, Capture, ExitError, ExitSucceed};
+
+#[test]
+fn configured_stack_limit() {
+	// PUSH1 1; DUP1; DUP1; STOP
+	let code = hex::decode("6001808000").unwrap();
+
+	let mut vm = Machine::new(Rc::new(code.clone()), Rc::new(Vec::new()), 3, 10000);
+	assert_eq!(vm.run(), Capture::Exit(ExitSucceed::Stopped.into()));
+
+	let mut vm = Machine::new(Rc::new(code), Rc::new(Vec::new()), 2, 10000);
+	assert_eq!(vm.run(), Capture::Exit(ExitError::StackOverflow.into()));
+	assert_eq!(vm.stack().len(), 2);
+
+	// 1025 times PUSH1 1, over the mainnet limit.
+	let mut code = hex::decode("6001").unwrap().repeat(1025);
+	code.push(0x00);
+
+	let mut vm = Machine::new(Rc::new(code.clone()), Rc::new(Vec::new()), 1024, 10000);
+	assert_eq!(vm.run(), Capture::Exit(ExitError::StackOverflow.into()));
+
+	let mut vm = Machine::new(Rc::new(code), Rc::new(Vec::new()), 2048, 10000);
+	assert_eq!(vm.run(), Capture::Exit(ExitSucceed::Stopped.into()));
+	assert_eq!(vm.stack().len(), 1025);
+}
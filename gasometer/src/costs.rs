@@ -1,8 +1,17 @@
+//! Gas cost formulas of individual opcodes, as used by the `Gasometer`.
+//!
+//! Costs depending on the fork take the `Config`. Fallible costs fail with
+//! `OutOfGas` when they do not fit in a `u64`.
+
 use primitive_types::{H256, U256};
 use evm_core::ExitError;
 use crate::Config;
 use crate::consts::*;
 
+/// Check, for `CALL`, `CALLCODE`, `DELEGATECALL` and `STATICCALL`, that the
+/// requested `gas` is not more than the gas left `after_gas` once the call
+/// cost is paid. This is only enforced before EIP-150, with
+/// `err_on_call_with_more_gas`.
 pub fn call_extra_check(gas: U256, after_gas: u64, config: &Config) -> Result<(), ExitError> {
 	if config.err_on_call_with_more_gas && U256::from(after_gas) < gas {
 		Err(ExitError::OutOfGas)
@@ -11,51 +20,18 @@ pub fn call_extra_check(gas: U256, after_gas: u64, config: &Config) -> Result<()
 	}
 }
 
-pub fn suicide_refund(already_removed: bool) -> i64 {
+/// Refund of `SUICIDE`, `refund_suicide`, granted only the first time the
+/// account is removed in the transaction.
+pub fn suicide_refund(already_removed: bool, config: &Config) -> i64 {
 	if already_removed {
 		0
 	} else {
-		R_SUICIDE
-	}
-}
-
-pub fn sstore_refund(original: H256, current: H256, new: H256, config: &Config) -> i64 {
-	if config.sstore_gas_metering {
-		if current == new {
-			0
-		} else {
-			if original == current && new == H256::default() {
-				config.refund_sstore_clears
-			} else {
-				let mut refund = 0;
-				if original != H256::default() {
-					if current == H256::default() {
-						refund -= config.refund_sstore_clears;
-					} else if new == H256::default() {
-						refund += config.refund_sstore_clears;
-					}
-				}
-
-				if original == new {
-					if original == H256::default() {
-						refund += (config.gas_sstore_set - config.gas_sload) as i64;
-					} else {
-						refund += (config.gas_sstore_reset - config.gas_sload) as i64;
-					}
-				}
-
-				refund
-			}
-		}
-	} else {
-		if current != H256::default() && new == H256::default() {
-			config.refund_sstore_clears
-		} else {
-			0
-		}
+		config.refund_suicide
 	}
 }
 
+/// Cost of `CREATE2`, that is `G_CREATE + G_SHA3WORD * ceil(len / 32)` for
+/// hashing the init code of length `len`.
 pub fn create2_cost(len: U256) -> Result<u64, ExitError> {
 	let base = U256::from(G_CREATE);
 	// ceil(len / 32.0)
@@ -72,6 +48,8 @@ pub fn create2_cost(len: U256) -> Result<u64, ExitError> {
 	Ok(gas.as_u64())
 }
 
+/// Cost of `EXP`. It is at most `G_EXP + 32 * gas_expbyte`, so it only fails
+/// if `gas_expbyte` is configured absurdly high.
 pub fn exp_cost(power: U256, config: &Config) -> Result<u64, ExitError> {
 	if power == U256::zero() {
 		Ok(G_EXP)
@@ -92,6 +70,14 @@ pub fn exp_cost(power: U256, config: &Config) -> Result<u64, ExitError> {
 	}
 }
 
+/// Saturating variant of `exp_cost`, returning `u64::MAX` where it fails.
+pub fn exp_cost_saturating(power: U256, config: &Config) -> u64 {
+	exp_cost(power, config).unwrap_or(u64::MAX)
+}
+
+/// Cost of `CALLDATACOPY`, `CODECOPY` and `RETURNDATACOPY`, that is
+/// `G_VERYLOW + G_COPY * ceil(len / 32)`. Fails with `OutOfGas` if it does not
+/// fit in a `u64`, which is the case from `len > 32 * ((u64::MAX - 3) / 3)`.
 pub fn verylowcopy_cost(len: U256) -> Result<u64, ExitError> {
 	let wordd = len / U256::from(32);
 	let wordr = len % U256::from(32);
@@ -113,6 +99,13 @@ pub fn verylowcopy_cost(len: U256) -> Result<u64, ExitError> {
 	Ok(gas.as_u64())
 }
 
+/// Saturating variant of `verylowcopy_cost`, returning `u64::MAX` where it
+/// fails.
+pub fn verylowcopy_cost_saturating(len: U256) -> u64 {
+	verylowcopy_cost(len).unwrap_or(u64::MAX)
+}
+
+/// Cost of `EXTCODECOPY`, that is `gas_ext_code + G_COPY * ceil(len / 32)`.
 pub fn extcodecopy_cost(len: U256, config: &Config) -> Result<u64, ExitError> {
 	let wordd = len / U256::from(32);
 	let wordr = len % U256::from(32);
@@ -134,6 +127,9 @@ pub fn extcodecopy_cost(len: U256, config: &Config) -> Result<u64, ExitError> {
 	Ok(gas.as_u64())
 }
 
+/// Cost of `LOGn`, that is `G_LOG + G_LOGDATA * len + G_LOGTOPIC * n`. Fails
+/// with `OutOfGas` if it does not fit in a `u64`, which is the case from
+/// `len > (u64::MAX - 375 - 375 * n) / 8`.
 pub fn log_cost(n: u8, len: U256) -> Result<u64, ExitError> {
 	let gas = U256::from(G_LOG)
 		.checked_add(U256::from(G_LOGDATA).checked_mul(len).ok_or(ExitError::OutOfGas)?)
@@ -148,6 +144,14 @@ pub fn log_cost(n: u8, len: U256) -> Result<u64, ExitError> {
 	Ok(gas.as_u64())
 }
 
+/// Saturating variant of `log_cost`, returning `u64::MAX` where it fails.
+pub fn log_cost_saturating(n: u8, len: U256) -> u64 {
+	log_cost(n, len).unwrap_or(u64::MAX)
+}
+
+/// Cost of `SHA3`, that is `G_SHA3 + G_SHA3WORD * ceil(len / 32)`. Fails with
+/// `OutOfGas` if it does not fit in a `u64`, which is the case from
+/// `len > 32 * ((u64::MAX - 30) / 6)`.
 pub fn sha3_cost(len: U256) -> Result<u64, ExitError> {
 	let wordd = len / U256::from(32);
 	let wordr = len % U256::from(32);
@@ -169,39 +173,164 @@ pub fn sha3_cost(len: U256) -> Result<u64, ExitError> {
 	Ok(gas.as_u64())
 }
 
-pub fn sstore_cost(original: H256, current: H256, new: H256, gas: u64, config: &Config) -> Result<u64, ExitError> {
-	if config.sstore_gas_metering {
-		if config.sstore_revert_under_stipend {
-			if gas < config.call_stipend {
-				return Err(ExitError::OutOfGas)
-			}
+/// Saturating variant of `sha3_cost`, returning `u64::MAX` where it fails.
+pub fn sha3_cost_saturating(len: U256) -> u64 {
+	sha3_cost(len).unwrap_or(u64::MAX)
+}
+
+/// Gas parameter an SSTORE transition is charged or refunded with.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum SstoreGas {
+	Zero,
+	Sload,
+	Set,
+	Reset,
+}
+
+impl SstoreGas {
+	const fn value(self, config: &Config) -> u64 {
+		match self {
+			Self::Zero => 0,
+			Self::Sload => config.gas_sload,
+			Self::Set => config.gas_sstore_set,
+			Self::Reset => config.gas_sstore_reset,
 		}
+	}
+}
 
-		Ok(if new == current {
-			config.gas_sload
-		} else {
-			if original == current {
-				if original == H256::zero() {
-					config.gas_sstore_set
-				} else {
-					config.gas_sstore_reset
-				}
+/// Row of the EIP-2200 table: the gas charged, the multiple of the clear
+/// refund that is added (or removed), and the gas whose difference with
+/// `SLOAD` is refunded when the slot is restored to its original value.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct SstoreRule {
+	cost: SstoreGas,
+	clears: i64,
+	restored: SstoreGas,
+}
+
+/// Classification of an SSTORE with net gas metering (EIP-2200), from the
+/// original value of the slot in the transaction, its current value and the
+/// new value.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum SstoreTransition {
+	/// `current == new`.
+	NoOp,
+	/// `original == current == 0`, `new != 0`.
+	FreshSet,
+	/// `original == current != 0`, `new != 0`.
+	FreshReset,
+	/// `original == current != 0`, `new == 0`.
+	FreshClear,
+	/// `original == 0`, `current != 0`, `new != 0`.
+	DirtyFromZero,
+	/// `original == 0`, `current != 0`, `new == 0`.
+	DirtyRestoreZero,
+	/// `original != 0`, `current == 0`, `new != original`.
+	DirtyUnclear,
+	/// `original != 0`, `current == 0`, `new == original`.
+	DirtyUnclearRestore,
+	/// `original != 0`, `current != 0`, `new == 0`.
+	DirtyClear,
+	/// `original != 0`, `current != 0`, `new != 0`, `new != original`.
+	DirtyUpdate,
+	/// `original != 0`, `current != 0`, `new == original`.
+	DirtyRestore,
+}
+
+impl SstoreTransition {
+	fn classify(original: H256, current: H256, new: H256) -> Self {
+		let zero = H256::default();
+
+		if current == new {
+			Self::NoOp
+		} else if original == current {
+			match (original == zero, new == zero) {
+				(true, _) => Self::FreshSet,
+				(false, false) => Self::FreshReset,
+				(false, true) => Self::FreshClear,
+			}
+		} else if original == zero {
+			if new == zero {
+				Self::DirtyRestoreZero
 			} else {
-				config.gas_sload
+				Self::DirtyFromZero
 			}
-		})
-	} else {
-		Ok(if current == H256::zero() && new != H256::zero() {
-			config.gas_sstore_set
+		} else if current == zero {
+			if new == original {
+				Self::DirtyUnclearRestore
+			} else {
+				Self::DirtyUnclear
+			}
+		} else if new == zero {
+			Self::DirtyClear
+		} else if new == original {
+			Self::DirtyRestore
 		} else {
-			config.gas_sstore_reset
-		})
+			Self::DirtyUpdate
+		}
+	}
+
+	const fn rule(self) -> SstoreRule {
+		use SstoreGas::*;
+
+		let (cost, clears, restored) = match self {
+			Self::NoOp => (Sload, 0, Zero),
+			Self::FreshSet => (Set, 0, Zero),
+			Self::FreshReset => (Reset, 0, Zero),
+			Self::FreshClear => (Reset, 1, Zero),
+			Self::DirtyFromZero => (Sload, 0, Zero),
+			Self::DirtyRestoreZero => (Sload, 0, Set),
+			Self::DirtyUnclear => (Sload, -1, Zero),
+			Self::DirtyUnclearRestore => (Sload, -1, Reset),
+			Self::DirtyClear => (Sload, 1, Zero),
+			Self::DirtyUpdate => (Sload, 0, Zero),
+			Self::DirtyRestore => (Sload, 0, Reset),
+		};
+
+		SstoreRule { cost, clears, restored }
+	}
+}
+
+/// Refund of an `SSTORE` from the `original` value of the slot in the
+/// transaction, its `current` value and the `new` value. It is negative when
+/// a refund granted earlier in the transaction is taken back (EIP-2200).
+pub fn sstore_refund(original: H256, current: H256, new: H256, config: &Config) -> i64 {
+	if config.sstore_gas_metering {
+		let rule = SstoreTransition::classify(original, current, new).rule();
+		let restored = match rule.restored {
+			SstoreGas::Zero => 0,
+			gas => (gas.value(config) - config.gas_sload) as i64,
+		};
+
+		rule.clears * config.refund_sstore_clears + restored
+	} else if current != H256::default() && new == H256::default() {
+		config.refund_sstore_clears
+	} else {
+		0
 	}
 }
 
+/// Cost of an `SSTORE` from the `original` value of the slot in the
+/// transaction, its `current` value and the `new` value. With EIP-2200, it
+/// fails if the gas left `gas` is not more than the call stipend.
+pub fn sstore_cost(original: H256, current: H256, new: H256, gas: u64, config: &Config) -> Result<u64, ExitError> {
+	if config.sstore_gas_metering {
+		if config.sstore_revert_under_stipend && gas <= config.call_stipend {
+			return Err(ExitError::OutOfGas)
+		}
+
+		Ok(SstoreTransition::classify(original, current, new).rule().cost.value(config))
+	} else if current == H256::zero() && new != H256::zero() {
+		Ok(config.gas_sstore_set)
+	} else {
+		Ok(config.gas_sstore_reset)
+	}
+}
+
+/// Cost of `SUICIDE` sending `value` to a beneficiary, including the new
+/// account cost if the beneficiary does not exist.
 pub fn suicide_cost(value: U256, target_exists: bool, config: &Config) -> u64 {
-	let eip161 = !config.empty_considered_exists;
-	let should_charge_topup = if eip161 {
+	let should_charge_topup = if config.new_account_charged_only_on_value {
 		value != U256::zero() && !target_exists
 	} else {
 		!target_exists
@@ -216,6 +345,14 @@ pub fn suicide_cost(value: U256, target_exists: bool, config: &Config) -> u64 {
 	config.gas_suicide + suicide_gas_topup
 }
 
+/// Cost of a call opcode, excluding the gas passed to the callee and memory
+/// expansion:
+///
+/// * `value`: value transferred, only charged for `CALL` and `CALLCODE`.
+/// * `is_call_or_callcode`: whether the opcode is `CALL` or `CALLCODE`.
+/// * `is_call_or_staticcall`: whether the opcode is `CALL` or `STATICCALL`,
+///   the ones that may create the target account.
+/// * `new_account`: whether the target account does not exist.
 pub fn call_cost(
 	value: U256,
 	is_call_or_callcode: bool,
@@ -225,16 +362,17 @@ pub fn call_cost(
 ) -> u64 {
 	let transfers_value = value != U256::default();
 	config.gas_call +
-		xfer_cost(is_call_or_callcode, transfers_value) +
+		xfer_cost(is_call_or_callcode, transfers_value, config) +
 		new_cost(is_call_or_staticcall, new_account, transfers_value, config)
 }
 
 fn xfer_cost(
 	is_call_or_callcode: bool,
-	transfers_value: bool
+	transfers_value: bool,
+	config: &Config,
 ) -> u64 {
 	if is_call_or_callcode && transfers_value {
-		G_CALLVALUE
+		config.gas_call_value
 	} else {
 		0
 	}
@@ -246,16 +384,15 @@ fn new_cost(
 	transfers_value: bool,
 	config: &Config,
 ) -> u64 {
-	let eip161 = !config.empty_considered_exists;
 	if is_call_or_staticcall {
-		if eip161 {
+		if config.new_account_charged_only_on_value {
 			if transfers_value && new_account {
-				G_NEWACCOUNT
+				config.gas_new_account
 			} else {
 				0
 			}
 		} else if new_account {
-			G_NEWACCOUNT
+			config.gas_new_account
 		} else {
 			0
 		}
@@ -263,3 +400,110 @@ fn new_cost(
 		0
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn boundaries(cutoff: U256) -> [U256; 4] {
+		[cutoff - U256::one(), cutoff, cutoff + U256::one(), U256::max_value()]
+	}
+
+	#[test]
+	fn copy_costs_overflow_at_cutoff() {
+		let sha3_cutoff = U256::from(32) * U256::from((u64::MAX - G_SHA3) / G_SHA3WORD);
+		let copy_cutoff = U256::from(32) * U256::from((u64::MAX - G_VERYLOW) / G_COPY);
+
+		let [below, at, above, max] = boundaries(sha3_cutoff);
+		assert!(sha3_cost(below).is_ok());
+		assert!(sha3_cost(at).is_ok());
+		assert_eq!(sha3_cost(above), Err(ExitError::OutOfGas));
+		assert_eq!(sha3_cost(max), Err(ExitError::OutOfGas));
+		assert_eq!(sha3_cost_saturating(above), u64::MAX);
+		assert_eq!(sha3_cost_saturating(at), sha3_cost(at).unwrap());
+
+		let [below, at, above, max] = boundaries(copy_cutoff);
+		assert!(verylowcopy_cost(below).is_ok());
+		assert!(verylowcopy_cost(at).is_ok());
+		assert_eq!(verylowcopy_cost(above), Err(ExitError::OutOfGas));
+		assert_eq!(verylowcopy_cost(max), Err(ExitError::OutOfGas));
+		assert_eq!(verylowcopy_cost_saturating(above), u64::MAX);
+		assert_eq!(verylowcopy_cost_saturating(at), verylowcopy_cost(at).unwrap());
+	}
+
+	#[test]
+	fn log_cost_overflows_at_cutoff() {
+		for n in 0..5u8 {
+			let cutoff = U256::from((u64::MAX - G_LOG - G_LOGTOPIC * n as u64) / G_LOGDATA);
+
+			let [below, at, above, max] = boundaries(cutoff);
+			assert!(log_cost(n, below).is_ok());
+			assert!(log_cost(n, at).is_ok());
+			assert_eq!(log_cost(n, above), Err(ExitError::OutOfGas));
+			assert_eq!(log_cost(n, max), Err(ExitError::OutOfGas));
+			assert_eq!(log_cost_saturating(n, above), u64::MAX);
+		}
+	}
+
+	#[test]
+	fn exp_cost_never_overflows() {
+		let config = Config::istanbul();
+		let boundary = U256::from(u64::MAX);
+
+		assert_eq!(exp_cost(U256::zero(), &config), Ok(G_EXP));
+		assert_eq!(exp_cost(boundary, &config), Ok(G_EXP + 8 * config.gas_expbyte));
+		assert_eq!(exp_cost(boundary + U256::one(), &config), Ok(G_EXP + 9 * config.gas_expbyte));
+		assert_eq!(exp_cost(U256::max_value(), &config), Ok(G_EXP + 32 * config.gas_expbyte));
+		assert_eq!(exp_cost_saturating(U256::max_value(), &config), G_EXP + 32 * config.gas_expbyte);
+	}
+
+	#[test]
+	fn sstore_transitions() {
+		let (zero, a, b) = (H256::zero(), H256::from_low_u64_be(1), H256::from_low_u64_be(2));
+		let (frontier, istanbul) = (Config::frontier(), Config::istanbul());
+
+		// Original, current and new values, then the cost and refund under
+		// frontier and under istanbul, as given by EIP-2200.
+		let transitions = [
+			(zero, zero, zero,  5000,     0,   800,      0),
+			(zero, zero, a,    20000,     0, 20000,      0),
+			(zero, zero, b,    20000,     0, 20000,      0),
+			(zero, a,    zero,  5000, 15000,   800,  19200),
+			(zero, a,    a,     5000,     0,   800,      0),
+			(zero, a,    b,     5000,     0,   800,      0),
+			(zero, b,    zero,  5000, 15000,   800,  19200),
+			(zero, b,    a,     5000,     0,   800,      0),
+			(zero, b,    b,     5000,     0,   800,      0),
+			(a,    zero, zero,  5000,     0,   800,      0),
+			(a,    zero, a,    20000,     0,   800, -10800),
+			(a,    zero, b,    20000,     0,   800, -15000),
+			(a,    a,    zero,  5000, 15000,  5000,  15000),
+			(a,    a,    a,     5000,     0,   800,      0),
+			(a,    a,    b,     5000,     0,  5000,      0),
+			(a,    b,    zero,  5000, 15000,   800,  15000),
+			(a,    b,    a,     5000,     0,   800,   4200),
+			(a,    b,    b,     5000,     0,   800,      0),
+			(b,    zero, zero,  5000,     0,   800,      0),
+			(b,    zero, a,    20000,     0,   800, -15000),
+			(b,    zero, b,    20000,     0,   800, -10800),
+			(b,    a,    zero,  5000, 15000,   800,  15000),
+			(b,    a,    a,     5000,     0,   800,      0),
+			(b,    a,    b,     5000,     0,   800,   4200),
+			(b,    b,    zero,  5000, 15000,  5000,  15000),
+			(b,    b,    a,     5000,     0,  5000,      0),
+			(b,    b,    b,     5000,     0,   800,      0),
+		];
+
+		for (original, current, new, frontier_cost, frontier_refund, istanbul_cost, istanbul_refund) in transitions {
+			let case = (original, current, new);
+			assert_eq!(sstore_cost(original, current, new, u64::MAX, &frontier), Ok(frontier_cost), "{:?}", case);
+			assert_eq!(sstore_refund(original, current, new, &frontier), frontier_refund, "{:?}", case);
+			assert_eq!(sstore_cost(original, current, new, u64::MAX, &istanbul), Ok(istanbul_cost), "{:?}", case);
+			assert_eq!(sstore_refund(original, current, new, &istanbul), istanbul_refund, "{:?}", case);
+		}
+
+		// Not more than the call stipend left.
+		assert_eq!(sstore_cost(zero, zero, a, istanbul.call_stipend, &istanbul), Err(ExitError::OutOfGas));
+		assert_eq!(sstore_cost(zero, zero, a, istanbul.call_stipend, &frontier), Ok(20000));
+	}
+}
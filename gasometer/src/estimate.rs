@@ -0,0 +1,99 @@
+//! Opcode cost estimation without a handler.
+//!
+//! Tooling that prices opcodes offline, such as static analyzers or gas
+//! profilers, supplies the account and storage facts a cost depends on
+//! through `CostState`, typically with `CostInputs`. Nothing is read from or
+//! written to a state.
+
+use primitive_types::{H160, H256, U256};
+use evm_core::{Opcode, ExitError, Stack};
+use evm_runtime::{Handler, Config, CustomOpcode};
+use crate::{Gasometer, MemoryCost, static_opcode_cost, dynamic_opcode_cost};
+
+/// Account and storage state read when pricing an opcode.
+pub trait CostState {
+	/// Whether the account exists.
+	fn exists(&self, address: H160) -> bool;
+	/// Balance of the account.
+	fn balance(&self, address: H160) -> U256;
+	/// Current storage value.
+	fn storage(&self, address: H160, index: H256) -> H256;
+	/// Storage value at the start of the transaction.
+	fn original_storage(&self, address: H160, index: H256) -> H256;
+	/// Whether the account has already suicided.
+	fn deleted(&self, address: H160) -> bool;
+	/// Signature of a custom opcode.
+	fn custom_opcode(&self, opcode: Opcode) -> Option<CustomOpcode>;
+}
+
+impl<H: Handler> CostState for H {
+	fn exists(&self, address: H160) -> bool {
+		Handler::exists(self, address)
+	}
+
+	fn balance(&self, address: H160) -> U256 {
+		Handler::balance(self, address)
+	}
+
+	fn storage(&self, address: H160, index: H256) -> H256 {
+		Handler::storage(self, address, index)
+	}
+
+	fn original_storage(&self, address: H160, index: H256) -> H256 {
+		Handler::original_storage(self, address, index)
+	}
+
+	fn deleted(&self, address: H160) -> bool {
+		Handler::deleted(self, address)
+	}
+
+	fn custom_opcode(&self, opcode: Opcode) -> Option<CustomOpcode> {
+		Handler::custom_opcode(self, opcode)
+	}
+}
+
+/// Fixed answers to the state queries of a single opcode.
+#[derive(Clone, Debug, Default)]
+pub struct CostInputs {
+	/// Whether the target of a call or suicide exists.
+	pub target_exists: bool,
+	/// Balance of the executing contract.
+	pub balance: U256,
+	/// Current value of the stored slot.
+	pub current_storage: H256,
+	/// Value of the stored slot at the start of the transaction.
+	pub original_storage: H256,
+	/// Whether the executing contract has already suicided.
+	pub deleted: bool,
+	/// Signature of the opcode, if it is a custom one.
+	pub custom_opcode: Option<CustomOpcode>,
+}
+
+impl CostState for CostInputs {
+	fn exists(&self, _address: H160) -> bool { self.target_exists }
+	fn balance(&self, _address: H160) -> U256 { self.balance }
+	fn storage(&self, _address: H160, _index: H256) -> H256 { self.current_storage }
+	fn original_storage(&self, _address: H160, _index: H256) -> H256 { self.original_storage }
+	fn deleted(&self, _address: H160) -> bool { self.deleted }
+	fn custom_opcode(&self, _opcode: Opcode) -> Option<CustomOpcode> { self.custom_opcode }
+}
+
+/// Estimate the cost of executing `opcode` with `gas` left. Returns the gas
+/// cost, excluding memory expansion, and the memory range accessed.
+pub fn estimate_opcode_cost<S: CostState>(
+	address: H160,
+	opcode: Opcode,
+	stack: &Stack,
+	is_static: bool,
+	gas: u64,
+	config: &Config,
+	state: &S,
+) -> Result<(u64, Option<MemoryCost>), ExitError> {
+	if let Some(cost) = static_opcode_cost(opcode) {
+		return Ok((cost, None))
+	}
+
+	let (cost, memory) = dynamic_opcode_cost(address, opcode, stack, is_static, config, state)?;
+	let cost = Gasometer::new(gas, config).gas_cost(cost, gas)?;
+	Ok((cost, memory))
+}
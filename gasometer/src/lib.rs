@@ -7,6 +7,8 @@
 
 #[cfg(feature = "tracing")]
 pub mod tracing;
+#[cfg(feature = "tracing")]
+pub mod oracle;
 
 #[cfg(feature = "tracing")]
 macro_rules! event {
@@ -22,14 +24,22 @@ macro_rules! event {
 }
 
 mod consts;
-mod costs;
+pub mod costs;
 mod memory;
 mod utils;
+mod estimate;
+
+pub use crate::costs::{
+	exp_cost_saturating, sha3_cost_saturating, log_cost_saturating, verylowcopy_cost_saturating,
+};
+pub use crate::estimate::{CostState, CostInputs, estimate_opcode_cost};
+#[allow(deprecated)]
+pub use crate::consts::R_SUICIDE;
 
 use core::cmp::max;
 use primitive_types::{H160, H256, U256};
 use evm_core::{Opcode, ExitError, Stack};
-use evm_runtime::{Handler, Config};
+use evm_runtime::{Config, Frame};
 
 macro_rules! try_or_fail {
 	( $inner:expr, $e:expr ) => (
@@ -43,12 +53,40 @@ macro_rules! try_or_fail {
 	)
 }
 
+/// State of a gasometer. `used_gas` is the sum of `intrinsic_gas` and
+/// `execution_gas`, and memory gas is accounted separately.
 #[derive(Debug, Copy, Clone)]
 pub struct Snapshot {
 	pub gas_limit: u64,
 	pub memory_gas: u64,
 	pub used_gas: u64,
 	pub refunded_gas: i64,
+	/// Part of `used_gas` charged by the external cost oracle.
+	pub surcharge_gas: u64,
+	/// Part of `used_gas` charged as the transaction intrinsic cost.
+	pub intrinsic_gas: u64,
+	/// Part of `used_gas` charged during execution, excluding memory gas.
+	pub execution_gas: u64,
+	/// Call frame the gasometer meters.
+	pub frame: Frame,
+}
+
+/// Costs of the operation that ran out of gas.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct OutOfGasCost {
+	/// Gas cost of the operation, excluding memory expansion.
+	pub gas_cost: u64,
+	/// Gas cost of the memory expansion.
+	pub memory_cost: u64,
+	/// Gas left before the operation.
+	pub gas_left: u64,
+}
+
+/// Host-defined surcharge added on top of opcode costs, such as a storage
+/// rent reflecting the trie depth of a cold read.
+pub trait ExternalCostOracle {
+	/// Extra gas charged for an opcode of the given cost category.
+	fn surcharge(&self, cost: &GasCost) -> u64;
 }
 
 /// EVM gasometer.
@@ -56,6 +94,9 @@ pub struct Snapshot {
 pub struct Gasometer<'config> {
 	gas_limit: u64,
 	config: &'config Config,
+	external_cost_oracle: Option<&'config dyn ExternalCostOracle>,
+	out_of_gas: Option<OutOfGasCost>,
+	frame: Frame,
 	inner: Result<Inner<'config>, ExitError>
 }
 
@@ -65,15 +106,35 @@ impl<'config> Gasometer<'config> {
 		Self {
 			gas_limit,
 			config,
+			external_cost_oracle: None,
+			out_of_gas: None,
+			frame: Frame::default(),
 			inner: Ok(Inner {
 				memory_gas: 0,
 				used_gas: 0,
 				refunded_gas: 0,
+				surcharge_gas: 0,
+				intrinsic_gas: 0,
 				config,
 			}),
 		}
 	}
 
+	/// Set the oracle consulted for surcharges in `record_dynamic_cost`.
+	pub fn set_external_cost_oracle(&mut self, oracle: Option<&'config dyn ExternalCostOracle>) {
+		self.external_cost_oracle = oracle;
+	}
+
+	/// Set the call frame the gasometer meters, reported in snapshots.
+	pub fn set_frame(&mut self, frame: Frame) {
+		self.frame = frame;
+	}
+
+	/// The external cost oracle, if any.
+	pub fn external_cost_oracle(&self) -> Option<&'config dyn ExternalCostOracle> {
+		self.external_cost_oracle
+	}
+
 	#[inline]
 	/// Returns the numerical gas cost value.
 	pub fn gas_cost(
@@ -100,6 +161,12 @@ impl<'config> Gasometer<'config> {
 		self.config
 	}
 
+	#[inline]
+	/// Gas limit.
+	pub fn gas_limit(&self) -> u64 {
+		self.gas_limit
+	}
+
 	#[inline]
 	/// Remaining gas.
 	pub fn gas(&self) -> u64 {
@@ -118,6 +185,15 @@ impl<'config> Gasometer<'config> {
 		}
 	}
 
+	#[inline]
+	/// Gas charged as the transaction intrinsic cost.
+	pub fn intrinsic_gas(&self) -> u64 {
+		match self.inner.as_ref() {
+			Ok(inner) => inner.intrinsic_gas,
+			Err(_) => 0,
+		}
+	}
+
 	#[inline]
 	/// Refunded gas.
 	pub fn refunded_gas(&self) -> i64 {
@@ -127,6 +203,12 @@ impl<'config> Gasometer<'config> {
 		}
 	}
 
+	/// Costs of the operation that exhausted the gas, if the gasometer failed
+	/// because its cost exceeded the gas left.
+	pub fn out_of_gas(&self) -> Option<OutOfGasCost> {
+		self.out_of_gas
+	}
+
 	/// Explictly fail the gasometer with out of gas. Return `OutOfGas` error.
 	pub fn fail(&mut self) -> ExitError {
 		self.inner = Err(ExitError::OutOfGas);
@@ -146,6 +228,11 @@ impl<'config> Gasometer<'config> {
 
 		let all_gas_cost = self.total_used_gas() + cost;
 		if self.gas_limit < all_gas_cost {
+			self.out_of_gas = Some(OutOfGasCost {
+				gas_cost: cost,
+				memory_cost: 0,
+				gas_left: self.gas(),
+			});
 			self.inner = Err(ExitError::OutOfGas);
 			return Err(ExitError::OutOfGas)
 		}
@@ -160,6 +247,10 @@ impl<'config> Gasometer<'config> {
 		&mut self,
 		refund: i64,
 	) -> Result<(), ExitError> {
+		if self.config.disable_refunds {
+			return Ok(())
+		}
+
 		event!(RecordRefund {
 			refund,
 			snapshot: self.snapshot()?,
@@ -175,8 +266,7 @@ impl<'config> Gasometer<'config> {
 		&mut self,
 		len: usize,
 	) -> Result<(), ExitError> {
-		let cost = len as u64 * consts::G_CODEDEPOSIT;
-		self.record_cost(cost)
+		self.record_cost(code_deposit_cost(len))
 	}
 
 	/// Record opcode gas cost.
@@ -191,7 +281,9 @@ impl<'config> Gasometer<'config> {
 			Some(memory) => try_or_fail!(self.inner, self.inner_mut()?.memory_gas(memory)),
 			None => self.inner_mut()?.memory_gas,
 		};
+		let surcharge = self.external_cost_oracle.map(|oracle| oracle.surcharge(&cost)).unwrap_or(0);
 		let gas_cost = try_or_fail!(self.inner, self.inner_mut()?.gas_cost(cost, gas));
+		let gas_cost = try_or_fail!(self.inner, gas_cost.checked_add(surcharge).ok_or(ExitError::OutOfGas));
 		let gas_refund = self.inner_mut()?.gas_refund(cost);
 		let used_gas = self.inner_mut()?.used_gas;
 
@@ -204,6 +296,11 @@ impl<'config> Gasometer<'config> {
 
 		let all_gas_cost = memory_gas + used_gas + gas_cost;
 		if self.gas_limit < all_gas_cost {
+			self.out_of_gas = Some(OutOfGasCost {
+				gas_cost,
+				memory_cost: memory_gas - self.inner_mut()?.memory_gas,
+				gas_left: gas,
+			});
 			self.inner = Err(ExitError::OutOfGas);
 			return Err(ExitError::OutOfGas)
 		}
@@ -212,6 +309,7 @@ impl<'config> Gasometer<'config> {
 		try_or_fail!(self.inner, self.inner_mut()?.extra_check(cost, after_gas));
 
 		self.inner_mut()?.used_gas += gas_cost;
+		self.inner_mut()?.surcharge_gas += surcharge;
 		self.inner_mut()?.memory_gas = memory_gas;
 		self.inner_mut()?.refunded_gas += gas_refund;
 
@@ -238,21 +336,32 @@ impl<'config> Gasometer<'config> {
 		&mut self,
 		cost: TransactionCost,
 	) -> Result<(), ExitError> {
-		let gas_cost = match cost {
-			TransactionCost::Call { zero_data_len, non_zero_data_len } => {
-				self.config.gas_transaction_call +
-					zero_data_len as u64 * self.config.gas_transaction_zero_data +
-					non_zero_data_len as u64 * self.config.gas_transaction_non_zero_data
-			},
-			TransactionCost::Create { zero_data_len, non_zero_data_len } => {
-				self.config.gas_transaction_create +
+		let (is_create, zero_data_len, non_zero_data_len) = match cost {
+			TransactionCost::Call { zero_data_len, non_zero_data_len } =>
+				(false, zero_data_len, non_zero_data_len),
+			TransactionCost::Create { zero_data_len, non_zero_data_len } =>
+				(true, zero_data_len, non_zero_data_len),
+		};
+		let (base_cost, data_cost) = match self.config.intrinsic_gas {
+			Some(intrinsic_gas) =>
+				intrinsic_gas(self.config, is_create, zero_data_len, non_zero_data_len),
+			None => {
+				let base_cost = if is_create {
+					self.config.gas_transaction_create
+				} else {
+					self.config.gas_transaction_call
+				};
+				let data_cost =
 					zero_data_len as u64 * self.config.gas_transaction_zero_data +
-					non_zero_data_len as u64 * self.config.gas_transaction_non_zero_data
+					non_zero_data_len as u64 * self.config.gas_transaction_non_zero_data;
+				(base_cost, data_cost)
 			},
 		};
+		let gas_cost = base_cost + data_cost;
 
 		event!(RecordTransaction {
 			cost: gas_cost,
+			data_cost,
 			snapshot: self.snapshot()?,
 		});
 
@@ -262,9 +371,11 @@ impl<'config> Gasometer<'config> {
 		}
 
 		self.inner_mut()?.used_gas += gas_cost;
+		self.inner_mut()?.intrinsic_gas += gas_cost;
 		Ok(())
 	}
 
+	/// Current state of the gasometer. Fails if the gasometer has failed.
 	pub fn snapshot(&self) -> Result<Snapshot, ExitError> {
 		let inner = self.inner.as_ref().map_err(|e| e.clone())?;
 		Ok(Snapshot {
@@ -272,6 +383,10 @@ impl<'config> Gasometer<'config> {
 			memory_gas: inner.memory_gas,
 			used_gas: inner.used_gas,
 			refunded_gas: inner.refunded_gas,
+			surcharge_gas: inner.surcharge_gas,
+			intrinsic_gas: inner.intrinsic_gas,
+			execution_gas: inner.used_gas - inner.intrinsic_gas,
+			frame: self.frame,
 		})
 	}
 }
@@ -286,6 +401,17 @@ pub fn call_transaction_cost(
 	TransactionCost::Call { zero_data_len, non_zero_data_len }
 }
 
+/// Gas paid at the end of a create to deposit `len` bytes of code.
+pub fn code_deposit_cost(len: usize) -> u64 {
+	(len as u64).saturating_mul(consts::G_CODEDEPOSIT)
+}
+
+/// Gas paid to deposit the largest code allowed by `create_contract_limit`,
+/// or `None` if code size is not limited.
+pub fn max_code_deposit_cost(config: &Config) -> Option<u64> {
+	config.create_contract_limit.map(code_deposit_cost)
+}
+
 /// Calculate the create transaction cost.
 pub fn create_transaction_cost(
 	data: &[u8]
@@ -421,8 +547,79 @@ pub fn static_opcode_cost(
 	TABLE[opcode.as_usize()]
 }
 
-/// Calculate the opcode cost.
-pub fn dynamic_opcode_cost<H: Handler>(
+/// How the cost of an opcode is computed under a config.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum OpcodeCost {
+	/// Cost only depending on the config, charged without reading the stack.
+	Static(u64),
+	/// Cost computed by `dynamic_opcode_cost`, without memory expansion.
+	Dynamic,
+	/// Cost computed by `dynamic_opcode_cost`, including the expansion of
+	/// memory ranges read from the stack.
+	DynamicMemory,
+}
+
+/// Table of opcode costs. It only depends on the config, so it can be
+/// computed once and shared by all executors using the same config.
+#[derive(Debug, Clone)]
+pub struct OpcodeCostTable([OpcodeCost; 256]);
+
+impl OpcodeCostTable {
+	/// Compute the table of `config`.
+	pub fn new(config: &Config) -> Self {
+		let mut table = [OpcodeCost::Dynamic; 256];
+		for (index, cost) in table.iter_mut().enumerate() {
+			if let Some(static_cost) = static_opcode_cost(Opcode(index as u8)) {
+				*cost = OpcodeCost::Static(static_cost);
+			}
+		}
+
+		if config.has_chain_id {
+			table[Opcode::CHAINID.as_usize()] = OpcodeCost::Static(consts::G_BASE);
+		}
+		if config.has_bitwise_shifting {
+			table[Opcode::SHL.as_usize()] = OpcodeCost::Static(consts::G_VERYLOW);
+			table[Opcode::SHR.as_usize()] = OpcodeCost::Static(consts::G_VERYLOW);
+			table[Opcode::SAR.as_usize()] = OpcodeCost::Static(consts::G_VERYLOW);
+		}
+		if config.has_self_balance {
+			table[Opcode::SELFBALANCE.as_usize()] = OpcodeCost::Static(consts::G_LOW);
+		}
+		if config.has_return_data {
+			table[Opcode::RETURNDATASIZE.as_usize()] = OpcodeCost::Static(consts::G_BASE);
+		}
+
+		// Memory is expanded whether the opcode is enabled or not, as the
+		// range is read before the cost is known to be invalid.
+		for opcode in &[
+			Opcode::RETURN, Opcode::REVERT, Opcode::MLOAD, Opcode::MSTORE, Opcode::MSTORE8,
+			Opcode::SHA3, Opcode::EXTCODECOPY, Opcode::CALLDATACOPY, Opcode::CODECOPY,
+			Opcode::RETURNDATACOPY, Opcode::LOG0, Opcode::LOG1, Opcode::LOG2, Opcode::LOG3,
+			Opcode::LOG4, Opcode::CREATE, Opcode::CREATE2, Opcode::CALL, Opcode::CALLCODE,
+			Opcode::DELEGATECALL, Opcode::STATICCALL,
+		] {
+			table[opcode.as_usize()] = OpcodeCost::DynamicMemory;
+		}
+
+		Self(table)
+	}
+
+	/// Cost of `opcode`.
+	pub fn get(&self, opcode: Opcode) -> OpcodeCost {
+		self.0[opcode.as_usize()]
+	}
+}
+
+fn word(value: H256) -> U256 {
+	U256::from_big_endian(&value[..])
+}
+
+fn memory_cost(offset: H256, len: H256) -> MemoryCost {
+	MemoryCost { offset: word(offset), len: word(len) }
+}
+
+/// Calculate the opcode cost. Each stack slot involved is read once.
+pub fn dynamic_opcode_cost<H: CostState>(
 	address: H160,
 	opcode: Opcode,
 	stack: &Stack,
@@ -430,172 +627,164 @@ pub fn dynamic_opcode_cost<H: Handler>(
 	config: &Config,
 	handler: &H
 ) -> Result<(GasCost, Option<MemoryCost>), ExitError> {
-	let gas_cost = match opcode {
-		Opcode::RETURN => GasCost::Zero,
+	Ok(match opcode {
+		Opcode::RETURN => {
+			let [offset, len] = stack.peek_many()?;
+			(GasCost::Zero, Some(memory_cost(offset, len)))
+		},
 
-		Opcode::MLOAD | Opcode::MSTORE | Opcode::MSTORE8 => GasCost::VeryLow,
+		Opcode::MLOAD | Opcode::MSTORE => {
+			let [offset] = stack.peek_many()?;
+			(GasCost::VeryLow, Some(MemoryCost { offset: word(offset), len: U256::from(32) }))
+		},
+		Opcode::MSTORE8 => {
+			let [offset] = stack.peek_many()?;
+			(GasCost::VeryLow, Some(MemoryCost { offset: word(offset), len: U256::from(1) }))
+		},
 
-		Opcode::REVERT if config.has_revert => GasCost::Zero,
-		Opcode::REVERT => GasCost::Invalid,
+		Opcode::REVERT => {
+			let [offset, len] = stack.peek_many()?;
+			let gas_cost = if config.has_revert { GasCost::Zero } else { GasCost::Invalid };
+			(gas_cost, Some(memory_cost(offset, len)))
+		},
 
-		Opcode::CHAINID if config.has_chain_id => GasCost::Base,
-		Opcode::CHAINID => GasCost::Invalid,
+		Opcode::CHAINID if config.has_chain_id => (GasCost::Base, None),
+		Opcode::CHAINID => (GasCost::Invalid, None),
 
 		Opcode::SHL | Opcode::SHR | Opcode::SAR if config.has_bitwise_shifting =>
-			GasCost::VeryLow,
-		Opcode::SHL | Opcode::SHR | Opcode::SAR => GasCost::Invalid,
-
-		Opcode::SELFBALANCE if config.has_self_balance => GasCost::Low,
-		Opcode::SELFBALANCE => GasCost::Invalid,
-
-		Opcode::EXTCODESIZE => GasCost::ExtCodeSize,
-		Opcode::BALANCE => GasCost::Balance,
-		Opcode::BLOCKHASH => GasCost::BlockHash,
-
-		Opcode::EXTCODEHASH if config.has_ext_code_hash => GasCost::ExtCodeHash,
-		Opcode::EXTCODEHASH => GasCost::Invalid,
-
-		Opcode::CALLCODE => GasCost::CallCode {
-			value: U256::from_big_endian(&stack.peek(2)?[..]),
-			gas: U256::from_big_endian(&stack.peek(0)?[..]),
-			target_exists: handler.exists(stack.peek(1)?.into()),
+			(GasCost::VeryLow, None),
+		Opcode::SHL | Opcode::SHR | Opcode::SAR => (GasCost::Invalid, None),
+
+		Opcode::SELFBALANCE if config.has_self_balance => (GasCost::Low, None),
+		Opcode::SELFBALANCE => (GasCost::Invalid, None),
+
+		Opcode::EXTCODESIZE => (GasCost::ExtCodeSize, None),
+		Opcode::BALANCE => (GasCost::Balance, None),
+		Opcode::BLOCKHASH => (GasCost::BlockHash, None),
+
+		Opcode::EXTCODEHASH if config.has_ext_code_hash => (GasCost::ExtCodeHash, None),
+		Opcode::EXTCODEHASH => (GasCost::Invalid, None),
+
+		Opcode::CALLCODE => {
+			let [gas, target, value, in_offset, in_len, out_offset, out_len] = stack.peek_many()?;
+			(GasCost::CallCode {
+				value: word(value),
+				gas: word(gas),
+				target_exists: handler.exists(target.into()),
+			}, Some(memory_cost(in_offset, in_len).join(memory_cost(out_offset, out_len))))
 		},
-		Opcode::STATICCALL => GasCost::StaticCall {
-			gas: U256::from_big_endian(&stack.peek(0)?[..]),
-			target_exists: handler.exists(stack.peek(1)?.into()),
+		Opcode::STATICCALL => {
+			let [gas, target, in_offset, in_len, out_offset, out_len] = stack.peek_many()?;
+			(GasCost::StaticCall {
+				gas: word(gas),
+				target_exists: handler.exists(target.into()),
+			}, Some(memory_cost(in_offset, in_len).join(memory_cost(out_offset, out_len))))
 		},
-		Opcode::SHA3 => GasCost::Sha3 {
-			len: U256::from_big_endian(&stack.peek(1)?[..]),
+		Opcode::SHA3 => {
+			let [offset, len] = stack.peek_many()?;
+			(GasCost::Sha3 { len: word(len) }, Some(memory_cost(offset, len)))
 		},
-		Opcode::EXTCODECOPY => GasCost::ExtCodeCopy {
-			len: U256::from_big_endian(&stack.peek(3)?[..]),
+		Opcode::EXTCODECOPY => {
+			let [_, offset, _, len] = stack.peek_many()?;
+			(GasCost::ExtCodeCopy { len: word(len) }, Some(memory_cost(offset, len)))
 		},
-		Opcode::CALLDATACOPY | Opcode::CODECOPY => GasCost::VeryLowCopy {
-			len: U256::from_big_endian(&stack.peek(2)?[..]),
+		Opcode::CALLDATACOPY | Opcode::CODECOPY => {
+			let [offset, _, len] = stack.peek_many()?;
+			(GasCost::VeryLowCopy { len: word(len) }, Some(memory_cost(offset, len)))
 		},
-		Opcode::EXP => GasCost::Exp {
-			power: U256::from_big_endian(&stack.peek(1)?[..]),
+		Opcode::EXP => {
+			let [_, power] = stack.peek_many()?;
+			(GasCost::Exp { power: word(power) }, None)
 		},
-		Opcode::SLOAD => GasCost::SLoad,
-
-		Opcode::DELEGATECALL if config.has_delegate_call => GasCost::DelegateCall {
-			gas: U256::from_big_endian(&stack.peek(0)?[..]),
-			target_exists: handler.exists(stack.peek(1)?.into()),
+		Opcode::SLOAD => (GasCost::SLoad, None),
+
+		Opcode::DELEGATECALL => {
+			let [gas, target, in_offset, in_len, out_offset, out_len] = stack.peek_many()?;
+			let gas_cost = if config.has_delegate_call {
+				GasCost::DelegateCall {
+					gas: word(gas),
+					target_exists: handler.exists(target.into()),
+				}
+			} else {
+				GasCost::Invalid
+			};
+			(gas_cost, Some(memory_cost(in_offset, in_len).join(memory_cost(out_offset, out_len))))
 		},
-		Opcode::DELEGATECALL => GasCost::Invalid,
 
-		Opcode::RETURNDATASIZE if config.has_return_data => GasCost::Base,
-		Opcode::RETURNDATACOPY if config.has_return_data => GasCost::VeryLowCopy {
-			len: U256::from_big_endian(&stack.peek(2)?[..]),
+		Opcode::RETURNDATASIZE if config.has_return_data => (GasCost::Base, None),
+		Opcode::RETURNDATASIZE => (GasCost::Invalid, None),
+		Opcode::RETURNDATACOPY => {
+			let [offset, _, len] = stack.peek_many()?;
+			let gas_cost = if config.has_return_data {
+				GasCost::VeryLowCopy { len: word(len) }
+			} else {
+				GasCost::Invalid
+			};
+			(gas_cost, Some(memory_cost(offset, len)))
 		},
-		Opcode::RETURNDATASIZE | Opcode::RETURNDATACOPY => GasCost::Invalid,
 
 		Opcode::SSTORE if !is_static => {
-			let index = stack.peek(0)?;
-			let value = stack.peek(1)?;
+			let [index, value] = stack.peek_many()?;
 
-			GasCost::SStore {
+			(GasCost::SStore {
 				original: handler.original_storage(address, index),
 				current: handler.storage(address, index),
 				new: value,
-			}
-		},
-		Opcode::LOG0 if !is_static => GasCost::Log {
-			n: 0,
-			len: U256::from_big_endian(&stack.peek(1)?[..]),
+			}, None)
 		},
-		Opcode::LOG1 if !is_static => GasCost::Log {
-			n: 1,
-			len: U256::from_big_endian(&stack.peek(1)?[..]),
+		Opcode::LOG0 | Opcode::LOG1 | Opcode::LOG2 | Opcode::LOG3 | Opcode::LOG4 => {
+			let [offset, len] = stack.peek_many()?;
+			let gas_cost = if is_static {
+				GasCost::Invalid
+			} else {
+				GasCost::Log { n: opcode.0 - Opcode::LOG0.0, len: word(len) }
+			};
+			(gas_cost, Some(memory_cost(offset, len)))
 		},
-		Opcode::LOG2 if !is_static => GasCost::Log {
-			n: 2,
-			len: U256::from_big_endian(&stack.peek(1)?[..]),
+		Opcode::CREATE => {
+			let [_, offset, len] = stack.peek_many()?;
+			let gas_cost = if is_static { GasCost::Invalid } else { GasCost::Create };
+			(gas_cost, Some(memory_cost(offset, len)))
 		},
-		Opcode::LOG3 if !is_static => GasCost::Log {
-			n: 3,
-			len: U256::from_big_endian(&stack.peek(1)?[..]),
+		Opcode::CREATE2 => {
+			let [_, offset, len] = stack.peek_many()?;
+			let gas_cost = if !is_static && config.has_create2 {
+				GasCost::Create2 { len: word(len) }
+			} else {
+				GasCost::Invalid
+			};
+			(gas_cost, Some(memory_cost(offset, len)))
 		},
-		Opcode::LOG4 if !is_static => GasCost::Log {
-			n: 4,
-			len: U256::from_big_endian(&stack.peek(1)?[..]),
+		Opcode::SUICIDE if !is_static => {
+			let [target] = stack.peek_many()?;
+			(GasCost::Suicide {
+				value: handler.balance(address),
+				target_exists: handler.exists(target.into()),
+				already_removed: handler.deleted(address),
+			}, None)
 		},
-		Opcode::CREATE if !is_static => GasCost::Create,
-		Opcode::CREATE2 if !is_static && config.has_create2 => GasCost::Create2 {
-			len: U256::from_big_endian(&stack.peek(2)?[..]),
+		Opcode::CALL => {
+			let [gas, target, value, in_offset, in_len, out_offset, out_len] = stack.peek_many()?;
+			let value = word(value);
+			let gas_cost = if !is_static || value == U256::zero() {
+				GasCost::Call {
+					value,
+					gas: word(gas),
+					target_exists: handler.exists(target.into()),
+				}
+			} else {
+				GasCost::Invalid
+			};
+			(gas_cost, Some(memory_cost(in_offset, in_len).join(memory_cost(out_offset, out_len))))
 		},
-		Opcode::SUICIDE if !is_static => GasCost::Suicide {
-			value: handler.balance(address),
-			target_exists: handler.exists(stack.peek(0)?.into()),
-			already_removed: handler.deleted(address),
-		},
-		Opcode::CALL
-			if !is_static ||
-			(is_static && U256::from_big_endian(&stack.peek(2)?[..]) == U256::zero()) =>
-			GasCost::Call {
-				value: U256::from_big_endian(&stack.peek(2)?[..]),
-				gas: U256::from_big_endian(&stack.peek(0)?[..]),
-				target_exists: handler.exists(stack.peek(1)?.into()),
-			},
 
-		_ => GasCost::Invalid,
-	};
-
-	let memory_cost = match opcode {
-		Opcode::SHA3 | Opcode::RETURN | Opcode::REVERT |
-		Opcode::LOG0 | Opcode::LOG1 | Opcode::LOG2 |
-		Opcode::LOG3 | Opcode::LOG4 => Some(MemoryCost {
-			offset: U256::from_big_endian(&stack.peek(0)?[..]),
-			len: U256::from_big_endian(&stack.peek(1)?[..]),
-		}),
-
-		Opcode::CODECOPY | Opcode::CALLDATACOPY |
-		Opcode::RETURNDATACOPY => Some(MemoryCost {
-			offset: U256::from_big_endian(&stack.peek(0)?[..]),
-			len: U256::from_big_endian(&stack.peek(2)?[..]),
-		}),
-
-		Opcode::EXTCODECOPY => Some(MemoryCost {
-			offset: U256::from_big_endian(&stack.peek(1)?[..]),
-			len: U256::from_big_endian(&stack.peek(3)?[..]),
-		}),
-
-		Opcode::MLOAD | Opcode::MSTORE => Some(MemoryCost {
-			offset: U256::from_big_endian(&stack.peek(0)?[..]),
-			len: U256::from(32),
-		}),
-
-		Opcode::MSTORE8 => Some(MemoryCost {
-			offset: U256::from_big_endian(&stack.peek(0)?[..]),
-			len: U256::from(1),
-		}),
-
-		Opcode::CREATE | Opcode::CREATE2 => Some(MemoryCost {
-			offset: U256::from_big_endian(&stack.peek(1)?[..]),
-			len: U256::from_big_endian(&stack.peek(2)?[..]),
-		}),
-
-		Opcode::CALL | Opcode::CALLCODE => Some(MemoryCost {
-			offset: U256::from_big_endian(&stack.peek(3)?[..]),
-			len: U256::from_big_endian(&stack.peek(4)?[..]),
-		}.join(MemoryCost {
-			offset: U256::from_big_endian(&stack.peek(5)?[..]),
-			len: U256::from_big_endian(&stack.peek(6)?[..]),
-		})),
-
-		Opcode::DELEGATECALL |
-		Opcode::STATICCALL => Some(MemoryCost {
-			offset: U256::from_big_endian(&stack.peek(2)?[..]),
-			len: U256::from_big_endian(&stack.peek(3)?[..]),
-		}.join(MemoryCost {
-			offset: U256::from_big_endian(&stack.peek(4)?[..]),
-			len: U256::from_big_endian(&stack.peek(5)?[..]),
-		})),
-
-		_ => None,
-	};
-
-	Ok((gas_cost, memory_cost))
+		// Host signatures are only honoured for opcodes with no meaning in
+		// the EVM, so that they cannot lift the static restrictions above.
+		_ => match handler.custom_opcode(opcode) {
+			Some(custom) if opcode.name().is_none() => (GasCost::Custom { gas: custom.gas }, None),
+			_ => (GasCost::Invalid, None),
+		},
+	})
 }
 
 /// Holds the gas consumption for a Gasometer instance.
@@ -604,6 +793,8 @@ struct Inner<'config> {
 	memory_gas: u64,
 	used_gas: u64,
 	refunded_gas: i64,
+	surcharge_gas: u64,
+	intrinsic_gas: u64,
 	config: &'config Config,
 }
 
@@ -690,6 +881,7 @@ impl<'config> Inner<'config> {
 			GasCost::Balance => self.config.gas_balance,
 			GasCost::BlockHash => consts::G_BLOCKHASH,
 			GasCost::ExtCodeHash => self.config.gas_ext_code_hash,
+			GasCost::Custom { gas } => gas,
 		})
 	}
 
@@ -698,12 +890,12 @@ impl<'config> Inner<'config> {
 		cost: GasCost
 	) -> i64 {
 		match cost {
-			_ if self.config.estimate => 0,
+			_ if self.config.estimate || self.config.disable_refunds => 0,
 
 			GasCost::SStore { original, current, new } =>
 				costs::sstore_refund(original, current, new, self.config),
 			GasCost::Suicide { already_removed, .. } =>
-				costs::suicide_refund(already_removed),
+				costs::suicide_refund(already_removed, self.config),
 			_ => 0,
 		}
 	}
@@ -731,6 +923,11 @@ pub enum GasCost {
 	BlockHash,
 	/// Gas cost for `EXTBLOCKHASH`.
 	ExtCodeHash,
+	/// Gas cost of a host-defined opcode.
+	Custom {
+		/// Gas declared in the opcode signature.
+		gas: u64,
+	},
 
 	/// Gas cost for `CALL`.
 	Call {
@@ -0,0 +1,163 @@
+//! Post-execution gas estimates built from gasometer events.
+//!
+//! Install a `GasOracle` with `tracing::using`, run the transaction, and then
+//! ask the oracle for an itemized `GasEstimate`.
+
+use core::cmp::min;
+use crate::tracing::{Event, EventListener};
+
+/// Itemized gas usage of a transaction.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub struct GasEstimate {
+	/// Intrinsic cost of the transaction, excluding calldata.
+	pub base_cost: u64,
+	/// Cost of the transaction calldata.
+	pub calldata_cost: u64,
+	/// Gas used by execution, including memory expansion.
+	pub execution_cost: u64,
+	/// Refund accumulated during execution, before the cap is applied.
+	pub refund_requested: i64,
+	/// Refund actually applied to the used gas.
+	pub refund_applied: u64,
+}
+
+impl GasEstimate {
+	/// Gas used before refunds.
+	pub fn total_cost(&self) -> u64 {
+		self.base_cost + self.calldata_cost + self.execution_cost
+	}
+
+	/// Gas used after refunds. This is what the sender pays for.
+	pub fn used_gas(&self) -> u64 {
+		self.total_cost() - self.refund_applied
+	}
+}
+
+/// Listener of gasometer events producing a gas estimate after execution.
+pub trait GasOracle: EventListener {
+	/// Estimate of the transaction observed so far.
+	fn estimate(&self) -> GasEstimate;
+}
+
+/// Default gas oracle.
+///
+/// Only the events of the transaction gasometer are accounted for. Gas spent
+/// in sub-calls is already part of the cost recorded for the call in their
+/// parent, minus the stipend returned when the sub-call finishes. The
+/// transaction gasometer is recognized by its frame, which is outside of any
+/// call and so has no depth.
+#[derive(Debug, Default, Clone)]
+pub struct StandardGasOracle {
+	gas_limit: Option<u64>,
+	base_cost: u64,
+	calldata_cost: u64,
+	used_gas: u64,
+	memory_gas: u64,
+	refunded_gas: i64,
+	failed: bool,
+}
+
+impl StandardGasOracle {
+	/// Create a new oracle. It must observe a single transaction.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	fn record(&mut self, cost: u64, gas: u64) -> bool {
+		if gas < cost {
+			self.failed = true;
+			false
+		} else {
+			true
+		}
+	}
+}
+
+impl EventListener for StandardGasOracle {
+	fn event(&mut self, event: Event) {
+		let snapshot = match event {
+			Event::RecordCost { snapshot, .. } |
+			Event::RecordRefund { snapshot, .. } |
+			Event::RecordStipend { snapshot, .. } |
+			Event::RecordDynamicCost { snapshot, .. } |
+			Event::RecordTransaction { snapshot, .. } => snapshot,
+		};
+		if snapshot.frame.depth.is_some() {
+			return
+		}
+
+		if let Event::RecordTransaction { cost, data_cost, snapshot } = event {
+			if self.gas_limit.is_none() {
+				self.gas_limit = Some(snapshot.gas_limit);
+				self.base_cost = cost - data_cost;
+				self.calldata_cost = data_cost;
+				if self.record(cost, snapshot.gas()) {
+					self.used_gas += cost;
+				}
+			}
+			return
+		}
+
+		if self.failed || self.gas_limit.is_none() {
+			return
+		}
+
+		match event {
+			Event::RecordCost { cost, snapshot } => {
+				if self.record(cost, snapshot.gas()) {
+					self.used_gas += cost;
+				}
+			},
+			Event::RecordRefund { refund, .. } => {
+				self.refunded_gas += refund;
+			},
+			Event::RecordStipend { stipend, .. } => {
+				self.used_gas -= stipend;
+			},
+			Event::RecordDynamicCost { gas_cost, memory_gas, gas_refund, snapshot } => {
+				let available = snapshot.gas_limit - snapshot.used_gas;
+				if self.record(memory_gas + gas_cost, available) {
+					self.used_gas += gas_cost;
+					self.memory_gas = memory_gas;
+					self.refunded_gas += gas_refund;
+				}
+			},
+			Event::RecordTransaction { .. } => (),
+		}
+	}
+}
+
+impl GasOracle for StandardGasOracle {
+	fn estimate(&self) -> GasEstimate {
+		let gas_limit = match self.gas_limit {
+			Some(gas_limit) => gas_limit,
+			None => return GasEstimate::default(),
+		};
+		let intrinsic = self.base_cost + self.calldata_cost;
+
+		if self.failed {
+			return GasEstimate {
+				base_cost: self.base_cost,
+				calldata_cost: self.calldata_cost,
+				execution_cost: gas_limit.saturating_sub(intrinsic),
+				refund_requested: 0,
+				refund_applied: 0,
+			}
+		}
+
+		let total = self.used_gas + self.memory_gas;
+		let refund_applied = if self.refunded_gas > 0 {
+			min(total / 2, self.refunded_gas as u64)
+		} else {
+			0
+		};
+
+		GasEstimate {
+			base_cost: self.base_cost,
+			calldata_cost: self.calldata_cost,
+			execution_cost: total - intrinsic,
+			refund_requested: self.refunded_gas,
+			refund_applied,
+		}
+	}
+}
@@ -39,6 +39,7 @@ pub enum Event {
     },
     RecordTransaction {
         cost: u64,
+        data_cost: u64,
         snapshot: Snapshot,
     },
 }
@@ -0,0 +1,94 @@
+//! Opcode costs estimated with `estimate_opcode_cost` from fixed inputs.
+
+use primitive_types::{H160, H256, U256};
+use evm_core::{ExitError, Opcode, Stack};
+use evm_runtime::{Config, CustomOpcode};
+use evm_gasometer::{CostInputs, MemoryCost, estimate_opcode_cost};
+
+const GAS: u64 = 100_000;
+
+fn stack(words: &[u64]) -> Stack {
+	let mut stack = Stack::new(1024);
+	// The last word is the top of the stack.
+	for word in words {
+		stack.push(H256::from_low_u64_be(*word)).unwrap();
+	}
+	stack
+}
+
+/// Estimate under Istanbul, returning the memory range as offset and length.
+fn estimate(
+	opcode: Opcode,
+	stack: &Stack,
+	is_static: bool,
+	gas: u64,
+	inputs: &CostInputs,
+) -> Result<(u64, Option<(U256, U256)>), ExitError> {
+	let config = Config::istanbul();
+	let (cost, memory) = estimate_opcode_cost(H160::default(), opcode, stack, is_static, gas, &config, inputs)?;
+	Ok((cost, memory.map(|MemoryCost { offset, len }| (offset, len))))
+}
+
+#[test]
+fn static_and_memory_costs() {
+	let inputs = CostInputs::default();
+	assert_eq!(estimate(Opcode::ADD, &stack(&[1, 2]), false, GAS, &inputs), Ok((3, None)));
+	assert_eq!(estimate(Opcode::SLOAD, &stack(&[0]), false, GAS, &inputs), Ok((800, None)));
+
+	// MSTORE at offset 64, reported as a memory range but not priced.
+	assert_eq!(
+		estimate(Opcode::MSTORE, &stack(&[0, 64]), false, GAS, &inputs),
+		Ok((3, Some((U256::from(64), U256::from(32))))),
+	);
+}
+
+#[test]
+fn storage_inputs() {
+	// SSTORE of 1 at slot 0.
+	let set = stack(&[1, 0]);
+	assert_eq!(estimate(Opcode::SSTORE, &set, false, GAS, &CostInputs::default()), Ok((20000, None)));
+
+	let inputs = CostInputs {
+		current_storage: H256::from_low_u64_be(2),
+		original_storage: H256::from_low_u64_be(2),
+		..Default::default()
+	};
+	assert_eq!(estimate(Opcode::SSTORE, &set, false, GAS, &inputs), Ok((5000, None)));
+
+	// Slot already dirty.
+	let inputs = CostInputs { current_storage: H256::from_low_u64_be(2), ..Default::default() };
+	assert_eq!(estimate(Opcode::SSTORE, &set, false, GAS, &inputs), Ok((800, None)));
+
+	// Not allowed with a stipend left, nor in a static context.
+	assert_eq!(estimate(Opcode::SSTORE, &set, false, 2300, &inputs), Err(ExitError::OutOfGas));
+	assert!(estimate(Opcode::SSTORE, &set, true, GAS, &inputs).is_err());
+}
+
+#[test]
+fn account_inputs() {
+	// CALL with value 1 to address 0xff and no memory.
+	let call = stack(&[0, 0, 0, 0, 1, 0xff, 1000]);
+	let (cost, memory) = estimate(Opcode::CALL, &call, false, GAS, &CostInputs::default()).unwrap();
+	assert_eq!(cost, 700 + 9000 + 25000);
+	assert_eq!(memory, Some((U256::zero(), U256::zero())));
+
+	let inputs = CostInputs { target_exists: true, ..Default::default() };
+	let (cost, _) = estimate(Opcode::CALL, &call, false, GAS, &inputs).unwrap();
+	assert_eq!(cost, 700 + 9000);
+
+	// SUICIDE to a new account, with and without a balance to send.
+	let suicide = stack(&[0xff]);
+	let inputs = CostInputs { balance: U256::one(), ..Default::default() };
+	assert_eq!(estimate(Opcode::SUICIDE, &suicide, false, GAS, &inputs), Ok((5000 + 25000, None)));
+	assert_eq!(estimate(Opcode::SUICIDE, &suicide, false, GAS, &CostInputs::default()), Ok((5000, None)));
+}
+
+#[test]
+fn custom_opcodes() {
+	let inputs = CostInputs {
+		custom_opcode: Some(CustomOpcode { inputs: 0, outputs: 1, gas: 42 }),
+		..Default::default()
+	};
+	assert_eq!(estimate(Opcode(0xc0), &stack(&[]), false, GAS, &inputs), Ok((42, None)));
+	assert!(estimate(Opcode(0xc0), &stack(&[]), false, GAS, &CostInputs::default()).is_err());
+}
@@ -0,0 +1,53 @@
+//! Intrinsic, execution, memory and refunded gas reported apart in
+//! snapshots.
+
+use primitive_types::U256;
+use evm_core::ExitError;
+use evm_runtime::Config;
+use evm_gasometer::{Gasometer, GasCost, MemoryCost, TransactionCost};
+
+#[test]
+fn snapshot_domains() {
+	let config = Config::istanbul();
+	let mut gasometer = Gasometer::new(100_000, &config);
+
+	// 2 zero and 3 non-zero bytes of data.
+	gasometer.record_transaction(TransactionCost::Call { zero_data_len: 2, non_zero_data_len: 3 }).unwrap();
+	let intrinsic = 21000 + 2 * 4 + 3 * 16;
+	assert_eq!(gasometer.intrinsic_gas(), intrinsic);
+
+	gasometer.record_cost(3).unwrap();
+	gasometer.record_dynamic_cost(GasCost::SLoad, None).unwrap();
+	// MSTORE of a word at offset 0, expanding the memory by one word.
+	gasometer.record_dynamic_cost(
+		GasCost::VeryLow,
+		Some(MemoryCost { offset: U256::zero(), len: U256::from(32) }),
+	).unwrap();
+	gasometer.record_refund(4800).unwrap();
+
+	let snapshot = gasometer.snapshot().unwrap();
+	assert_eq!(snapshot.intrinsic_gas, intrinsic);
+	assert_eq!(snapshot.execution_gas, 3 + 800 + 3);
+	assert_eq!(snapshot.used_gas, snapshot.intrinsic_gas + snapshot.execution_gas);
+	assert_eq!(snapshot.memory_gas, 3);
+	assert_eq!(snapshot.refunded_gas, 4800);
+	assert_eq!(snapshot.surcharge_gas, 0);
+	assert_eq!(gasometer.total_used_gas(), snapshot.used_gas + snapshot.memory_gas);
+	assert_eq!(gasometer.intrinsic_gas(), intrinsic);
+}
+
+#[test]
+fn child_gasometers_have_no_intrinsic_gas() {
+	let config = Config::istanbul();
+	let mut gasometer = Gasometer::new(100, &config);
+	gasometer.record_cost(10).unwrap();
+
+	let snapshot = gasometer.snapshot().unwrap();
+	assert_eq!(snapshot.intrinsic_gas, 0);
+	assert_eq!(snapshot.execution_gas, 10);
+
+	// Failed gasometers have no snapshot.
+	assert_eq!(gasometer.record_cost(1000), Err(ExitError::OutOfGas));
+	assert!(gasometer.snapshot().is_err());
+	assert_eq!(gasometer.intrinsic_gas(), 0);
+}
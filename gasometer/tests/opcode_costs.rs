@@ -0,0 +1,66 @@
+//! Opcode cost tables checked against `static_opcode_cost` and
+//! `dynamic_opcode_cost` for every opcode.
+
+use primitive_types::{H160, H256};
+use evm_core::{Opcode, Stack};
+use evm_runtime::Config;
+use evm_gasometer::{
+	Gasometer, OpcodeCost, OpcodeCostTable, CostInputs, static_opcode_cost, dynamic_opcode_cost,
+};
+
+fn check(config: &Config) {
+	let table = OpcodeCostTable::new(config);
+	let mut stack = Stack::new(1024);
+	for _ in 0..7 {
+		stack.push(H256::default()).unwrap();
+	}
+
+	for opcode in (0..=255).map(Opcode) {
+		let dynamic = dynamic_opcode_cost(
+			H160::default(), opcode, &stack, false, config, &CostInputs::default(),
+		);
+
+		match (table.get(opcode), static_opcode_cost(opcode)) {
+			(OpcodeCost::Static(cost), Some(static_cost)) =>
+				assert_eq!(cost, static_cost, "{:?}", opcode),
+			(OpcodeCost::Static(cost), None) => {
+				let (gas_cost, memory) = dynamic.unwrap();
+				assert!(memory.is_none(), "{:?}", opcode);
+				let gasometer = Gasometer::new(u64::MAX, config);
+				assert_eq!(gasometer.gas_cost(gas_cost, u64::MAX), Ok(cost), "{:?}", opcode);
+			},
+			(OpcodeCost::Dynamic, None) =>
+				assert!(dynamic.unwrap().1.is_none(), "{:?}", opcode),
+			(OpcodeCost::DynamicMemory, None) =>
+				assert!(dynamic.unwrap().1.is_some(), "{:?}", opcode),
+			(cost, static_cost) =>
+				panic!("{:?} is {:?} but has a static cost {:?}", opcode, cost, static_cost),
+		}
+	}
+}
+
+#[test]
+fn frontier() {
+	let config = Config::frontier();
+	check(&config);
+
+	let table = OpcodeCostTable::new(&config);
+	assert_eq!(table.get(Opcode::CHAINID), OpcodeCost::Dynamic);
+	assert_eq!(table.get(Opcode::SHL), OpcodeCost::Dynamic);
+	assert_eq!(table.get(Opcode::RETURNDATASIZE), OpcodeCost::Dynamic);
+	assert_eq!(table.get(Opcode::REVERT), OpcodeCost::DynamicMemory);
+}
+
+#[test]
+fn istanbul() {
+	let config = Config::istanbul();
+	check(&config);
+
+	let table = OpcodeCostTable::new(&config);
+	assert_eq!(table.get(Opcode::ADD), OpcodeCost::Static(3));
+	assert_eq!(table.get(Opcode::CHAINID), OpcodeCost::Static(2));
+	assert_eq!(table.get(Opcode::SHL), OpcodeCost::Static(3));
+	assert_eq!(table.get(Opcode::SELFBALANCE), OpcodeCost::Static(5));
+	assert_eq!(table.get(Opcode::SLOAD), OpcodeCost::Dynamic);
+	assert_eq!(table.get(Opcode::CALL), OpcodeCost::DynamicMemory);
+}
@@ -0,0 +1,101 @@
+//! Gas cost reference vectors.
+//!
+//! Every vector in `tests/reference_vectors.txt` is evaluated with
+//! `Gasometer::gas_cost` under the given fork, and compared against the cost
+//! worked out by hand from the yellow paper and the EIPs.
+
+use primitive_types::{H256, U256};
+use evm_runtime::Config;
+use evm_gasometer::{Gasometer, GasCost};
+
+fn u256(value: &str) -> U256 {
+	if value == "max" {
+		U256::MAX
+	} else {
+		U256::from_dec_str(value).unwrap()
+	}
+}
+
+fn h256(value: &str) -> H256 {
+	let mut h = H256::default();
+	u256(value).to_big_endian(&mut h[..]);
+	h
+}
+
+fn flag(value: &str) -> bool {
+	match value {
+		"0" => false,
+		"1" => true,
+		_ => panic!("invalid boolean {}", value),
+	}
+}
+
+/// Parse an operation and its arguments, returning the cost and the gas left
+/// it is evaluated with.
+fn parse(op: &str, args: &[&str]) -> (GasCost, u64) {
+	let gas = 1_000_000;
+	match (op, args) {
+		("sstore", &[original, current, new, gas]) => (GasCost::SStore {
+			original: h256(original),
+			current: h256(current),
+			new: h256(new),
+		}, gas.parse().unwrap()),
+		("call", &[value, target_exists]) => (GasCost::Call {
+			value: u256(value),
+			gas: U256::zero(),
+			target_exists: flag(target_exists),
+		}, gas),
+		("callcode", &[value, target_exists]) => (GasCost::CallCode {
+			value: u256(value),
+			gas: U256::zero(),
+			target_exists: flag(target_exists),
+		}, gas),
+		("delegatecall", &[target_exists]) => (GasCost::DelegateCall {
+			gas: U256::zero(),
+			target_exists: flag(target_exists),
+		}, gas),
+		("staticcall", &[target_exists]) => (GasCost::StaticCall {
+			gas: U256::zero(),
+			target_exists: flag(target_exists),
+		}, gas),
+		("exp", &[power]) => (GasCost::Exp { power: u256(power) }, gas),
+		("verylowcopy", &[len]) => (GasCost::VeryLowCopy { len: u256(len) }, gas),
+		("extcodecopy", &[len]) => (GasCost::ExtCodeCopy { len: u256(len) }, gas),
+		("sha3", &[len]) => (GasCost::Sha3 { len: u256(len) }, gas),
+		("log", &[n, len]) => (GasCost::Log { n: n.parse().unwrap(), len: u256(len) }, gas),
+		_ => panic!("invalid vector {} {:?}", op, args),
+	}
+}
+
+#[test]
+fn reference_vectors() {
+	let frontier = Config::frontier();
+	let istanbul = Config::istanbul();
+
+	let mut failures = Vec::new();
+	for line in include_str!("reference_vectors.txt").lines() {
+		let line = line.trim();
+		if line.is_empty() || line.starts_with('#') {
+			continue
+		}
+
+		let words = line.split_whitespace().collect::<Vec<_>>();
+		let config = match words[0] {
+			"frontier" => &frontier,
+			"istanbul" => &istanbul,
+			fork => panic!("unknown fork {}", fork),
+		};
+		let (cost, gas) = parse(words[1], &words[2..words.len() - 1]);
+		let expected = words[words.len() - 1];
+
+		let actual = match Gasometer::new(u64::MAX, config).gas_cost(cost, gas) {
+			Ok(cost) => cost.to_string(),
+			Err(_) => "error".to_string(),
+		};
+		if actual != expected {
+			failures.push(format!("{}: got {}", line, actual));
+		}
+	}
+
+	assert!(failures.is_empty(), "gas cost mismatches:\n{}", failures.join("\n"));
+}
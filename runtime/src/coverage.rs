@@ -0,0 +1,178 @@
+//! Bytecode coverage collected from runtime tracing events.
+//!
+//! Install a `CoverageListener` with `tracing::using`, run the executor, and
+//! then inspect the executed positions per code hash, or render them as an
+//! LCOV-like report whose line numbers refer to the rows of `disassemble`.
+
+use core::fmt::Write;
+use core::ops::Range;
+use alloc::{rc::Rc, vec::Vec, string::String, collections::BTreeMap};
+use primitive_types::H256;
+use sha3::{Keccak256, Digest};
+use crate::{Opcode, Bytes};
+use crate::tracing::{Event, EventListener};
+
+/// Coverage of a single code.
+#[derive(Clone, Debug, Default)]
+pub struct CodeCoverage {
+	code: Vec<u8>,
+	hits: BTreeMap<usize, u64>,
+}
+
+impl CodeCoverage {
+	/// The covered code.
+	pub fn code(&self) -> &[u8] {
+		&self.code
+	}
+
+	/// Number of times each executed position was hit.
+	pub fn hits(&self) -> &BTreeMap<usize, u64> {
+		&self.hits
+	}
+
+	/// Executed positions merged into ranges of consecutive instructions.
+	pub fn executed_ranges(&self) -> Vec<Range<usize>> {
+		let mut ranges: Vec<Range<usize>> = Vec::new();
+
+		for (position, opcode) in disassemble(&self.code) {
+			let next = position + 1 + opcode.is_push().unwrap_or(0) as usize;
+
+			if !self.hits.contains_key(&position) {
+				continue
+			}
+
+			match ranges.last_mut() {
+				Some(range) if range.end == position => range.end = next,
+				_ => ranges.push(position..next),
+			}
+		}
+
+		ranges
+	}
+
+	/// Disassembly annotated with hit counts, one instruction per line, in the
+	/// same order as the lines of the LCOV report.
+	pub fn annotated_disassembly(&self) -> String {
+		let mut out = String::new();
+
+		for (position, opcode) in disassemble(&self.code) {
+			let hits = self.hits.get(&position).cloned().unwrap_or(0);
+			let _ = write!(out, "{:>8} {:04x} ", hits, position);
+			match opcode.name() {
+				Some(name) => { let _ = write!(out, "{}", name); },
+				None => { let _ = write!(out, "0x{:02x}", opcode.as_u8()); },
+			}
+			if let Some(n) = opcode.is_push() {
+				let end = core::cmp::min(position + 1 + n as usize, self.code.len());
+				let _ = write!(out, " 0x");
+				for byte in &self.code[(position + 1)..end] {
+					let _ = write!(out, "{:02x}", byte);
+				}
+			}
+			let _ = writeln!(out);
+		}
+
+		out
+	}
+}
+
+/// Listener recording executed positions per code hash.
+#[derive(Default)]
+pub struct CoverageListener {
+	/// Hashes of shared codes, keyed by pointer. Keeping the `Rc` alive
+	/// guarantees that its pointer is not reused by another code.
+	shared: BTreeMap<usize, (Rc<Vec<u8>>, H256)>,
+	/// Hashes of borrowed codes, which can be freed once their runtime is
+	/// gone, keyed by content.
+	borrowed: BTreeMap<Vec<u8>, H256>,
+	coverage: BTreeMap<H256, CodeCoverage>,
+}
+
+impl CoverageListener {
+	/// Create a new empty coverage listener.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Collected coverage, keyed by code hash.
+	pub fn coverage(&self) -> &BTreeMap<H256, CodeCoverage> {
+		&self.coverage
+	}
+
+	/// Render the coverage as an LCOV-like report. Each code hash is a source
+	/// file and each instruction is a line, numbered as in `disassemble`.
+	pub fn lcov(&self) -> String {
+		let mut out = String::new();
+
+		for (hash, coverage) in &self.coverage {
+			let instructions = disassemble(&coverage.code);
+			let mut hit_lines = 0;
+
+			let _ = writeln!(out, "TN:");
+			let _ = writeln!(out, "SF:{:x}", hash);
+			for (line, (position, _)) in instructions.iter().enumerate() {
+				let hits = coverage.hits.get(position).cloned().unwrap_or(0);
+				if hits > 0 {
+					hit_lines += 1;
+				}
+				let _ = writeln!(out, "DA:{},{}", line + 1, hits);
+			}
+			let _ = writeln!(out, "LF:{}", instructions.len());
+			let _ = writeln!(out, "LH:{}", hit_lines);
+			let _ = writeln!(out, "end_of_record");
+		}
+
+		out
+	}
+
+	fn code_hash(&mut self, code: &Bytes) -> H256 {
+		match code {
+			Bytes::Shared(code) => {
+				let pointer = Rc::as_ptr(code) as usize;
+				if let Some((_, hash)) = self.shared.get(&pointer) {
+					return *hash
+				}
+
+				let hash = H256::from_slice(Keccak256::digest(&code[..]).as_slice());
+				self.shared.insert(pointer, (code.clone(), hash));
+				hash
+			},
+			Bytes::Borrowed(code) => {
+				if let Some(hash) = self.borrowed.get(*code) {
+					return *hash
+				}
+
+				let hash = H256::from_slice(Keccak256::digest(code).as_slice());
+				self.borrowed.insert(code.to_vec(), hash);
+				hash
+			},
+		}
+	}
+}
+
+impl EventListener for CoverageListener {
+	fn event(&mut self, event: Event) {
+		if let Event::Step { code, position: Ok(position), .. } = event {
+			let hash = self.code_hash(code);
+			let coverage = self.coverage.entry(hash).or_insert_with(|| CodeCoverage {
+				code: code.to_vec(),
+				hits: BTreeMap::new(),
+			});
+			*coverage.hits.entry(*position).or_insert(0) += 1;
+		}
+	}
+}
+
+/// Split code into instructions, returning the position of each of them.
+pub fn disassemble(code: &[u8]) -> Vec<(usize, Opcode)> {
+	let mut instructions = Vec::new();
+
+	let mut i = 0;
+	while i < code.len() {
+		let opcode = Opcode(code[i]);
+		instructions.push((i, opcode));
+		i += 1 + opcode.is_push().unwrap_or(0) as usize;
+	}
+
+	instructions
+}
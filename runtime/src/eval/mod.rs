@@ -2,7 +2,8 @@
 mod macros;
 mod system;
 
-use crate::{Handler, Runtime, ExitReason, CallScheme, Opcode};
+use alloc::vec::Vec;
+use crate::{Handler, Runtime, ExitReason, ExitError, CallScheme, Opcode};
 
 pub enum Control<H: Handler> {
 	Continue,
@@ -12,11 +13,37 @@ pub enum Control<H: Handler> {
 }
 
 fn handle_other<H: Handler>(state: &mut Runtime, opcode: Opcode, handler: &mut H) -> Control<H> {
-	match handler.other(
-		opcode,
-		&mut state.machine
-	) {
-		Ok(()) => Control::Continue,
+	// Opcodes with a meaning in the EVM are never host-defined.
+	let signature = match handler.custom_opcode(opcode).filter(|_| opcode.name().is_none()) {
+		Some(signature) => signature,
+		None => return match handler.other(opcode, &mut state.machine) {
+			Ok(()) => Control::Continue,
+			Err(e) => Control::Exit(e.into()),
+		},
+	};
+
+	let stack = state.machine.stack();
+	if stack.len() < signature.inputs {
+		return Control::Exit(ExitError::StackUnderflow.into())
+	}
+	if stack.len() - signature.inputs + signature.outputs > stack.limit() {
+		return Control::Exit(ExitError::StackOverflow.into())
+	}
+
+	let mut inputs = Vec::with_capacity(signature.inputs);
+	for _ in 0..signature.inputs {
+		pop!(state, input);
+		inputs.push(input);
+	}
+
+	match handler.custom(opcode, &state.context, inputs) {
+		Ok(outputs) if outputs.len() == signature.outputs => {
+			for output in outputs {
+				push!(state, output);
+			}
+			Control::Continue
+		},
+		Ok(_) => Control::Exit(ExitError::Other("custom opcode output count mismatch".into()).into()),
 		Err(e) => Control::Exit(e.into()),
 	}
 }
@@ -1,7 +1,6 @@
 use core::cmp::min;
 use alloc::vec::Vec;
 use primitive_types::{H256, U256};
-use sha3::{Keccak256, Digest};
 use crate::{Runtime, ExitError, Handler, Capture, Transfer, ExitReason,
 			CreateScheme, CallScheme, Context, ExitSucceed, ExitFatal};
 use super::Control;
@@ -19,8 +18,8 @@ pub fn sha3<H: Handler>(runtime: &mut Runtime) -> Control<H> {
 		runtime.machine.memory_mut().get(from, len)
 	};
 
-	let ret = Keccak256::digest(data.as_slice());
-	push!(runtime, H256::from_slice(ret.as_slice()));
+	let ret = runtime.config.keccak256(data.as_slice());
+	push!(runtime, ret);
 
 	Control::Continue
 }
@@ -100,11 +99,17 @@ pub fn extcodecopy<H: Handler>(runtime: &mut Runtime, handler: &H) -> Control<H>
 	pop_u256!(runtime, memory_offset, code_offset, len);
 
 	try_or_fail!(runtime.machine.memory_mut().resize_offset(memory_offset, len));
+	let usize_max = U256::from(usize::MAX);
+	let chunk = if len == U256::zero() || code_offset > usize_max || len > usize_max {
+		Vec::new()
+	} else {
+		handler.code_chunk(address.into(), code_offset.as_usize(), len.as_usize())
+	};
 	match runtime.machine.memory_mut().copy_large(
 		memory_offset,
-		code_offset,
+		U256::zero(),
 		len,
-		&handler.code(address.into())
+		&chunk
 	) {
 		Ok(()) => (),
 		Err(e) => return Control::Exit(e.into()),
@@ -175,6 +180,7 @@ pub fn sload<H: Handler>(runtime: &mut Runtime, handler: &H) -> Control<H> {
 	push!(runtime, value);
 
 	event!(SLoad {
+		frame: handler.frame(),
 		address: runtime.context.address,
 		index,
 		value
@@ -187,6 +193,7 @@ pub fn sstore<H: Handler>(runtime: &mut Runtime, handler: &mut H) -> Control<H>
 	pop!(runtime, index, value);
 
 	event!(SStore {
+		frame: handler.frame(),
 		address: runtime.context.address,
 		index,
 		value
@@ -263,7 +270,7 @@ pub fn create<H: Handler>(
 
 	let scheme = if is_create2 {
 		pop!(runtime, salt);
-		let code_hash = H256::from_slice(Keccak256::digest(&code).as_slice());
+		let code_hash = runtime.config.keccak256(&code);
 		CreateScheme::Create2 {
 			caller: runtime.context.address,
 			salt,
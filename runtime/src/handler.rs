@@ -1,6 +1,6 @@
 use alloc::vec::Vec;
 use primitive_types::{H160, H256, U256};
-use crate::{Capture, Stack, ExitError, Opcode,
+use crate::{Capture, Stack, ExitError, Opcode, code_chunk,
 			CreateScheme, Context, Machine, ExitReason};
 
 /// Transfer from source to target, with given value.
@@ -14,6 +14,26 @@ pub struct Transfer {
 	pub value: U256,
 }
 
+/// Signature of a host-defined opcode.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct CustomOpcode {
+	/// Number of stack items popped and passed to the handler.
+	pub inputs: usize,
+	/// Number of stack items returned by the handler and pushed.
+	pub outputs: usize,
+	/// Gas cost of the opcode.
+	pub gas: u64,
+}
+
+/// Call frame code is executed in, used to attribute tracing events.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Frame {
+	/// Depth of the frame, `None` outside of any frame.
+	pub depth: Option<usize>,
+	/// Whether state changes are forbidden in the frame.
+	pub is_static: bool,
+}
+
 /// EVM context handler.
 pub trait Handler {
 	/// Type of `CREATE` interrupt.
@@ -33,6 +53,12 @@ pub trait Handler {
 	fn code_hash(&self, address: H160) -> H256;
 	/// Get code of address.
 	fn code(&self, address: H160) -> Vec<u8>;
+	/// Get `len` bytes of the code of address starting at `offset`. The chunk
+	/// is truncated at the end of the code. Handlers with chunked code storage
+	/// can override it to avoid loading the whole code.
+	fn code_chunk(&self, address: H160, offset: usize, len: usize) -> Vec<u8> {
+		code_chunk(&self.code(address), offset, len)
+	}
 	/// Get storage value of address at index.
 	fn storage(&self, address: H160, index: H256) -> H256;
 	/// Get original storage value of address at index.
@@ -111,11 +137,35 @@ pub trait Handler {
 		opcode: Opcode,
 		stack: &Stack
 	) -> Result<(), ExitError>;
-	/// Handle other unknown external opcodes.
+	/// Frame currently executing.
+	fn frame(&self) -> Frame {
+		Frame::default()
+	}
+	/// Signature of a host-defined opcode. It is only consulted for opcodes
+	/// unknown to the EVM, and those without a signature are passed to
+	/// `other`.
+	fn custom_opcode(&self, _opcode: Opcode) -> Option<CustomOpcode> {
+		None
+	}
+	/// Execute a host-defined opcode. `inputs` are popped from the stack, top
+	/// first. The returned values are pushed in order, so the last one ends up
+	/// on top. Only called for opcodes with a signature, after the stack has
+	/// been checked against it.
+	fn custom(
+		&mut self,
+		_opcode: Opcode,
+		_context: &Context,
+		_inputs: Vec<H256>,
+	) -> Result<Vec<H256>, ExitError> {
+		Err(ExitError::OutOfGas)
+	}
+	/// Handle other unknown opcodes, without a signature. The handler gets
+	/// the whole machine and is left to meter the opcode, so prefer
+	/// `custom_opcode` and `custom`.
 	fn other(
 		&mut self,
 		_opcode: Opcode,
-		_stack: &mut Machine
+		_machine: &mut Machine
 	) -> Result<(), ExitError> {
 		Err(ExitError::OutOfGas)
 	}
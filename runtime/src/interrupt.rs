@@ -1,25 +1,25 @@
 use crate::{Runtime, Handler, ExitFatal};
 
 /// Interrupt resolution.
-pub enum Resolve<'a, 'config, H: Handler> {
+pub enum Resolve<'a, 'code, 'config, H: Handler> {
 	/// Create interrupt resolution.
-	Create(H::CreateInterrupt, ResolveCreate<'a, 'config>),
+	Create(H::CreateInterrupt, ResolveCreate<'a, 'code, 'config>),
 	/// Call interrupt resolution.
-	Call(H::CallInterrupt, ResolveCall<'a, 'config>),
+	Call(H::CallInterrupt, ResolveCall<'a, 'code, 'config>),
 }
 
 /// Create interrupt resolution.
-pub struct ResolveCreate<'a, 'config> {
-	runtime: &'a mut Runtime<'config>,
+pub struct ResolveCreate<'a, 'code, 'config> {
+	runtime: &'a mut Runtime<'code, 'config>,
 }
 
-impl<'a, 'config> ResolveCreate<'a, 'config> {
-	pub(crate) fn new(runtime: &'a mut Runtime<'config>) -> Self {
+impl<'a, 'code, 'config> ResolveCreate<'a, 'code, 'config> {
+	pub(crate) fn new(runtime: &'a mut Runtime<'code, 'config>) -> Self {
 		Self { runtime }
 	}
 }
 
-impl<'a, 'config> Drop for ResolveCreate<'a, 'config> {
+impl<'a, 'code, 'config> Drop for ResolveCreate<'a, 'code, 'config> {
 	fn drop(&mut self) {
 		self.runtime.status = Err(ExitFatal::UnhandledInterrupt.into());
 		self.runtime.machine.exit(ExitFatal::UnhandledInterrupt.into());
@@ -27,17 +27,17 @@ impl<'a, 'config> Drop for ResolveCreate<'a, 'config> {
 }
 
 /// Call interrupt resolution.
-pub struct ResolveCall<'a, 'config> {
-	runtime: &'a mut Runtime<'config>,
+pub struct ResolveCall<'a, 'code, 'config> {
+	runtime: &'a mut Runtime<'code, 'config>,
 }
 
-impl<'a, 'config> ResolveCall<'a, 'config> {
-	pub(crate) fn new(runtime: &'a mut Runtime<'config>) -> Self {
+impl<'a, 'code, 'config> ResolveCall<'a, 'code, 'config> {
+	pub(crate) fn new(runtime: &'a mut Runtime<'code, 'config>) -> Self {
 		Self { runtime }
 	}
 }
 
-impl<'a, 'config> Drop for ResolveCall<'a, 'config> {
+impl<'a, 'code, 'config> Drop for ResolveCall<'a, 'code, 'config> {
 	fn drop(&mut self) {
 		self.runtime.status = Err(ExitFatal::UnhandledInterrupt.into());
 		self.runtime.machine.exit(ExitFatal::UnhandledInterrupt.into());
@@ -9,6 +9,10 @@ extern crate alloc;
 
 #[cfg(feature = "tracing")]
 pub mod tracing;
+#[cfg(feature = "tracing")]
+pub mod coverage;
+#[cfg(feature = "tracing")]
+pub mod stats;
 
 #[cfg(feature = "tracing")]
 macro_rules! event {
@@ -32,23 +36,43 @@ pub use evm_core::*;
 
 pub use crate::context::{CreateScheme, CallScheme, Context};
 pub use crate::interrupt::{Resolve, ResolveCall, ResolveCreate};
-pub use crate::handler::{Transfer, Handler};
+pub use crate::handler::{Transfer, Handler, CustomOpcode, Frame};
 
 use alloc::vec::Vec;
 use alloc::rc::Rc;
+use primitive_types::H256;
+use sha3::{Keccak256, Digest};
 
 macro_rules! step {
 	( $self:expr, $handler:expr, $return:tt $($err:path)?; $($ok:path)? ) => ({
 		if let Some((opcode, stack)) = $self.machine.inspect() {
+			$self.last_opcode = Some(opcode);
+			$self.last_position = $self.machine.position().as_ref().ok().copied();
+
 			event!(Step {
+				frame: $handler.frame(),
 				context: &$self.context,
+				code: $self.machine.code(),
 				opcode,
 				position: $self.machine.position(),
 				stack,
 				memory: $self.machine.memory()
 			});
 
-			match $handler.pre_validate(&$self.context, opcode, stack) {
+			let validated = match opcode.stack_inputs() {
+				Some(inputs) if $self.config.strict_stack_validation && stack.len() < inputs => {
+					event!(StackUnderflow {
+						frame: $handler.frame(),
+						opcode,
+						required: inputs,
+						available: stack.len(),
+					});
+					Err(ExitError::OpcodeStackUnderflow(opcode))
+				},
+				_ => $handler.pre_validate(&$self.context, opcode, stack),
+			};
+
+			match validated {
 				Ok(()) => (),
 				Err(e) => {
 					$self.machine.exit(e.clone().into());
@@ -68,20 +92,24 @@ macro_rules! step {
 		let result = $self.machine.step();
 
 		event!(StepResult {
+			frame: $handler.frame(),
 			result: &result,
 			return_value: &$self.machine.return_value(),
 		});
 
 		match result {
-			Ok(()) => $($ok)?(()),
+			Ok(()) => { $($ok(()))? },
 			Err(Capture::Exit(e)) => {
 				$self.status = Err(e.clone());
 				#[allow(unused_parens)]
 				$return $($err)*(Capture::Exit(e))
 			},
-			Err(Capture::Trap(opcode)) => {
+			// Breakpoints can only be set on machines owned by the caller, the
+			// runtime never sets any.
+			Err(Capture::Trap(Trap::Breakpoint(_))) => { $($ok(()))? },
+			Err(Capture::Trap(Trap::External(opcode))) => {
 				match eval::eval($self, opcode, $handler) {
-					eval::Control::Continue => $($ok)?(()),
+					eval::Control::Continue => { $($ok(()))? },
 					eval::Control::CallInterrupt(interrupt) => {
 						let resolve = ResolveCall::new($self);
 						#[allow(unused_parens)]
@@ -107,15 +135,19 @@ macro_rules! step {
 /// EVM runtime.
 ///
 /// The runtime wraps an EVM `Machine` with support of return data and context.
-pub struct Runtime<'config> {
-	machine: Machine,
+///
+/// Borrowed code and input live for `'code`, independently of the config.
+pub struct Runtime<'code, 'config> {
+	machine: Machine<'code>,
 	status: Result<(), ExitReason>,
 	return_data_buffer: Vec<u8>,
 	context: Context,
-	_config: &'config Config,
+	last_opcode: Option<Opcode>,
+	last_position: Option<usize>,
+	config: &'config Config,
 }
 
-impl<'config> Runtime<'config> {
+impl<'code, 'config> Runtime<'code, 'config> {
 	/// Create a new runtime with given code and data.
 	pub fn new(
 		code: Rc<Vec<u8>>,
@@ -128,12 +160,33 @@ impl<'config> Runtime<'config> {
 			status: Ok(()),
 			return_data_buffer: Vec::new(),
 			context,
-			_config: config,
+			last_opcode: None,
+			last_position: None,
+			config,
+		}
+	}
+
+	/// Create a new runtime borrowing the given code and data, without copying
+	/// them or allocating an `Rc`.
+	pub fn new_borrowed(
+		code: &'code [u8],
+		data: &'code [u8],
+		context: Context,
+		config: &'config Config,
+	) -> Self {
+		Self {
+			machine: Machine::new_borrowed(code, data, config.stack_limit, config.memory_limit),
+			status: Ok(()),
+			return_data_buffer: Vec::new(),
+			context,
+			last_opcode: None,
+			last_position: None,
+			config,
 		}
 	}
 
 	/// Get a reference to the machine.
-	pub fn machine(&self) -> &Machine {
+	pub fn machine(&self) -> &Machine<'code> {
 		&self.machine
 	}
 
@@ -142,11 +195,34 @@ impl<'config> Runtime<'config> {
 		&self.context
 	}
 
+	/// Position of the next opcode, or the exit reason if the runtime has
+	/// stopped.
+	pub fn position(&self) -> &Result<usize, ExitReason> {
+		self.machine.position()
+	}
+
+	/// Opcode executed by the next step, if the runtime has not stopped.
+	pub fn peek_opcode(&self) -> Option<Opcode> {
+		self.machine.inspect().map(|(opcode, _)| opcode)
+	}
+
+	/// Last opcode stepped into. While the runtime is interrupted by a call or
+	/// create, this is the interrupting opcode.
+	pub fn last_opcode(&self) -> Option<Opcode> {
+		self.last_opcode
+	}
+
+	/// Position of the last opcode stepped into. Once the runtime has exited
+	/// with an error, this is the position of the failing opcode.
+	pub fn last_position(&self) -> Option<usize> {
+		self.last_position
+	}
+
 	/// Step the runtime.
 	pub fn step<'a, H: Handler>(
 		&'a mut self,
 		handler: &mut H,
-	) -> Result<(), Capture<ExitReason, Resolve<'a, 'config, H>>> {
+	) -> Result<(), Capture<ExitReason, Resolve<'a, 'code, 'config, H>>> {
 		step!(self, handler, return Err; Ok)
 	}
 
@@ -154,15 +230,50 @@ impl<'config> Runtime<'config> {
 	pub fn run<'a, H: Handler>(
 		&'a mut self,
 		handler: &mut H,
-	) -> Capture<ExitReason, Resolve<'a, 'config, H>> {
+	) -> Capture<ExitReason, Resolve<'a, 'code, 'config, H>> {
 		loop {
 			step!(self, handler, return;)
 		}
 	}
 }
 
+/// Override of the intrinsic gas of transactions. Given the config, whether
+/// the transaction is a create, and its count of zero and non-zero data bytes,
+/// returns the base cost and the data cost.
+pub type IntrinsicGasFn = fn(&Config, bool, usize, usize) -> (u64, u64);
+
+/// Keccak-256 implementation, returning the hash of its input.
+pub type KeccakFn = fn(&[u8]) -> H256;
+
+/// Gas of the `SUICIDE` opcode, as set in `Config` by a fork.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SuicideGas {
+	/// Base cost, `Config::gas_suicide`.
+	pub base: u64,
+	/// Surcharge when hitting a new account, `Config::gas_suicide_new_account`.
+	pub new_account: u64,
+	/// Refund, `Config::refund_suicide`.
+	pub refund: i64,
+}
+
+impl SuicideGas {
+	/// Frontier, free with a refund.
+	pub const FRONTIER: SuicideGas = SuicideGas { base: 0, new_account: 0, refund: 24000 };
+	/// EIP-150, charging the opcode and new accounts.
+	pub const EIP150: SuicideGas = SuicideGas { base: 5000, new_account: 25000, refund: 24000 };
+	/// EIP-3529, removing the refund.
+	pub const EIP3529: SuicideGas = SuicideGas { base: 5000, new_account: 25000, refund: 0 };
+}
+
 /// Runtime configuration.
+///
+/// With the `with-serde` feature, configs can be shipped across process
+/// boundaries through `VersionedConfig`. Unknown fields are rejected. The
+/// `intrinsic_gas` and `keccak` overrides are not serialized, and have to be
+/// installed again by the receiver.
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "with-serde", serde(deny_unknown_fields))]
 pub struct Config {
 	/// Gas paid for extcode.
 	pub gas_ext_code: u64,
@@ -182,6 +293,8 @@ pub struct Config {
 	pub gas_suicide: u64,
 	/// Gas paid for SUICIDE opcode when it hits a new account.
 	pub gas_suicide_new_account: u64,
+	/// Gas refunded for SUICIDE opcode.
+	pub refund_suicide: i64,
 	/// Gas paid for CALL opcode.
 	pub gas_call: u64,
 	/// Gas paid for EXP opcode for every byte.
@@ -206,6 +319,12 @@ pub struct Config {
 	pub call_l64_after_gas: bool,
 	/// Whether empty account is considered exists.
 	pub empty_considered_exists: bool,
+	/// Whether CALL and SUICIDE only charge the new account cost when value
+	/// is transferred to a non-existing account (EIP-161b).
+	pub new_account_charged_only_on_value: bool,
+	/// Whether touched empty accounts are deleted at the end of the
+	/// transaction (EIP-161d).
+	pub clear_empty_touched_accounts: bool,
 	/// Whether create transactions and create opcode increases nonce by one.
 	pub create_increase_nonce: bool,
 	/// Stack limit.
@@ -216,8 +335,20 @@ pub struct Config {
 	pub call_stack_limit: usize,
 	/// Create contract limit.
 	pub create_contract_limit: Option<usize>,
-	/// Call stipend.
+	/// Whether deploying code starting with 0xEF fails with
+	/// `ExitError::InvalidCodePrefix` (EIP-3541). The prefix is reserved for
+	/// EVM object format containers.
+	pub disallow_executable_format: bool,
+	/// Whether a create whose gas cannot cover the deposit of code of
+	/// `create_contract_limit` bytes fails before running the init code,
+	/// rather than when depositing. Not part of any Ethereum fork.
+	pub precheck_code_deposit: bool,
+	/// Call stipend, given to the callee of a value transfer.
 	pub call_stipend: u64,
+	/// Gas paid by `CALL` and `CALLCODE` for a value transfer.
+	pub gas_call_value: u64,
+	/// Gas paid by `CALL` for creating a new account.
+	pub gas_new_account: u64,
 	/// Has delegate call.
 	pub has_delegate_call: bool,
 	/// Has create2.
@@ -234,6 +365,23 @@ pub struct Config {
 	pub has_self_balance: bool,
 	/// Has ext code hash.
 	pub has_ext_code_hash: bool,
+	/// Whether gas refunds are disabled, as on some layer 2 chains.
+	pub disable_refunds: bool,
+	/// Intrinsic gas override. If unset, it is derived from the
+	/// `gas_transaction_*` fields.
+	#[cfg_attr(feature = "with-serde", serde(skip))]
+	pub intrinsic_gas: Option<IntrinsicGasFn>,
+	/// Number of past blocks whose hash is available to `BLOCKHASH`. Older
+	/// blocks, the current one and future ones hash to zero.
+	pub block_hash_history: u64,
+	/// Keccak-256 override used by `SHA3`, code hashes and create addresses,
+	/// such as a host function. If unset, the `sha3` crate is used.
+	#[cfg_attr(feature = "with-serde", serde(skip))]
+	pub keccak: Option<KeccakFn>,
+	/// Whether the stack height is checked against the inputs of each opcode
+	/// before its gas is computed, for clearer errors on malformed code. A
+	/// failed check exits with `ExitError::OpcodeStackUnderflow`.
+	pub strict_stack_validation: bool,
 	/// Whether the gasometer is running in estimate mode.
 	pub estimate: bool,
 }
@@ -251,6 +399,7 @@ impl Config {
 			refund_sstore_clears: 15000,
 			gas_suicide: 0,
 			gas_suicide_new_account: 0,
+			refund_suicide: 24000,
 			gas_call: 40,
 			gas_expbyte: 10,
 			gas_transaction_create: 21000,
@@ -261,13 +410,19 @@ impl Config {
 			sstore_revert_under_stipend: false,
 			err_on_call_with_more_gas: true,
 			empty_considered_exists: true,
+			new_account_charged_only_on_value: false,
+			clear_empty_touched_accounts: false,
 			create_increase_nonce: false,
 			call_l64_after_gas: false,
 			stack_limit: 1024,
 			memory_limit: usize::max_value(),
 			call_stack_limit: 1024,
 			create_contract_limit: None,
+			disallow_executable_format: false,
+			precheck_code_deposit: false,
 			call_stipend: 2300,
+			gas_call_value: 9000,
+			gas_new_account: 25000,
 			has_delegate_call: false,
 			has_create2: false,
 			has_revert: false,
@@ -276,6 +431,11 @@ impl Config {
 			has_chain_id: false,
 			has_self_balance: false,
 			has_ext_code_hash: false,
+			disable_refunds: false,
+			intrinsic_gas: None,
+			block_hash_history: 256,
+			keccak: None,
+			strict_stack_validation: false,
 			estimate: false,
 		}
 	}
@@ -292,6 +452,7 @@ impl Config {
 			refund_sstore_clears: 15000,
 			gas_suicide: 5000,
 			gas_suicide_new_account: 25000,
+			refund_suicide: 24000,
 			gas_call: 700,
 			gas_expbyte: 50,
 			gas_transaction_create: 53000,
@@ -302,13 +463,19 @@ impl Config {
 			sstore_revert_under_stipend: true,
 			err_on_call_with_more_gas: false,
 			empty_considered_exists: false,
+			new_account_charged_only_on_value: true,
+			clear_empty_touched_accounts: true,
 			create_increase_nonce: true,
 			call_l64_after_gas: true,
 			stack_limit: 1024,
 			memory_limit: usize::max_value(),
 			call_stack_limit: 1024,
 			create_contract_limit: Some(0x6000),
+			disallow_executable_format: false,
+			precheck_code_deposit: false,
 			call_stipend: 2300,
+			gas_call_value: 9000,
+			gas_new_account: 25000,
 			has_delegate_call: true,
 			has_create2: true,
 			has_revert: true,
@@ -317,7 +484,354 @@ impl Config {
 			has_chain_id: true,
 			has_self_balance: true,
 			has_ext_code_hash: true,
+			disable_refunds: false,
+			intrinsic_gas: None,
+			block_hash_history: 256,
+			keccak: None,
+			strict_stack_validation: false,
 			estimate: false,
 		}
 	}
+
+	/// Gas of `SUICIDE`.
+	pub fn suicide_gas(&self) -> SuicideGas {
+		SuicideGas {
+			base: self.gas_suicide,
+			new_account: self.gas_suicide_new_account,
+			refund: self.refund_suicide,
+		}
+	}
+
+	/// This config with the gas of `SUICIDE` of another fork, such as
+	/// `SuicideGas::EIP3529`.
+	pub fn with_suicide_gas(self, gas: SuicideGas) -> Config {
+		Config {
+			gas_suicide: gas.base,
+			gas_suicide_new_account: gas.new_account,
+			refund_suicide: gas.refund,
+			..self
+		}
+	}
+
+	/// Keccak-256 hash of `data`, with the configured implementation.
+	pub fn keccak256(&self, data: &[u8]) -> H256 {
+		match self.keccak {
+			Some(keccak) => keccak(data),
+			None => H256::from_slice(Keccak256::digest(data).as_slice()),
+		}
+	}
+
+	/// Whether this config follows the same rules as `preset`. Keccak
+	/// overrides and the estimate mode do not change the rules, while an
+	/// intrinsic gas override always does.
+	fn has_rules_of(&self, preset: &Config) -> bool {
+		let Config {
+			gas_ext_code, gas_ext_code_hash, gas_sstore_set, gas_sstore_reset,
+			refund_sstore_clears, gas_balance, gas_sload, gas_suicide,
+			gas_suicide_new_account, refund_suicide, gas_call, gas_expbyte,
+			gas_transaction_create, gas_transaction_call, gas_transaction_zero_data,
+			gas_transaction_non_zero_data, sstore_gas_metering,
+			sstore_revert_under_stipend, err_on_call_with_more_gas, call_l64_after_gas,
+			empty_considered_exists, new_account_charged_only_on_value,
+			clear_empty_touched_accounts, create_increase_nonce, stack_limit, memory_limit,
+			call_stack_limit, create_contract_limit, disallow_executable_format,
+			precheck_code_deposit, call_stipend, gas_call_value, gas_new_account,
+			has_delegate_call, has_create2, has_revert, has_return_data,
+			has_bitwise_shifting, has_chain_id, has_self_balance, has_ext_code_hash,
+			disable_refunds, intrinsic_gas, block_hash_history, keccak: _,
+			strict_stack_validation, estimate: _,
+		} = self;
+
+		*gas_ext_code == preset.gas_ext_code &&
+			*gas_ext_code_hash == preset.gas_ext_code_hash &&
+			*gas_sstore_set == preset.gas_sstore_set &&
+			*gas_sstore_reset == preset.gas_sstore_reset &&
+			*refund_sstore_clears == preset.refund_sstore_clears &&
+			*gas_balance == preset.gas_balance &&
+			*gas_sload == preset.gas_sload &&
+			*gas_suicide == preset.gas_suicide &&
+			*gas_suicide_new_account == preset.gas_suicide_new_account &&
+			*refund_suicide == preset.refund_suicide &&
+			*gas_call == preset.gas_call &&
+			*gas_expbyte == preset.gas_expbyte &&
+			*gas_transaction_create == preset.gas_transaction_create &&
+			*gas_transaction_call == preset.gas_transaction_call &&
+			*gas_transaction_zero_data == preset.gas_transaction_zero_data &&
+			*gas_transaction_non_zero_data == preset.gas_transaction_non_zero_data &&
+			*sstore_gas_metering == preset.sstore_gas_metering &&
+			*sstore_revert_under_stipend == preset.sstore_revert_under_stipend &&
+			*err_on_call_with_more_gas == preset.err_on_call_with_more_gas &&
+			*call_l64_after_gas == preset.call_l64_after_gas &&
+			*empty_considered_exists == preset.empty_considered_exists &&
+			*new_account_charged_only_on_value == preset.new_account_charged_only_on_value &&
+			*clear_empty_touched_accounts == preset.clear_empty_touched_accounts &&
+			*create_increase_nonce == preset.create_increase_nonce &&
+			*stack_limit == preset.stack_limit &&
+			*memory_limit == preset.memory_limit &&
+			*call_stack_limit == preset.call_stack_limit &&
+			*create_contract_limit == preset.create_contract_limit &&
+			*disallow_executable_format == preset.disallow_executable_format &&
+			*precheck_code_deposit == preset.precheck_code_deposit &&
+			*call_stipend == preset.call_stipend &&
+			*gas_call_value == preset.gas_call_value &&
+			*gas_new_account == preset.gas_new_account &&
+			*has_delegate_call == preset.has_delegate_call &&
+			*has_create2 == preset.has_create2 &&
+			*has_revert == preset.has_revert &&
+			*has_return_data == preset.has_return_data &&
+			*has_bitwise_shifting == preset.has_bitwise_shifting &&
+			*has_chain_id == preset.has_chain_id &&
+			*has_self_balance == preset.has_self_balance &&
+			*has_ext_code_hash == preset.has_ext_code_hash &&
+			*disable_refunds == preset.disable_refunds &&
+			*block_hash_history == preset.block_hash_history &&
+			*strict_stack_validation == preset.strict_stack_validation &&
+			intrinsic_gas.is_none() && preset.intrinsic_gas.is_none()
+	}
+
+	/// Name of the preset this config has the rules of, or `"custom"` if any
+	/// of its fields differs from every preset.
+	pub fn fork_name(&self) -> &'static str {
+		if self.has_rules_of(&Config::istanbul()) {
+			"istanbul"
+		} else if self.has_rules_of(&Config::frontier()) {
+			"frontier"
+		} else {
+			"custom"
+		}
+	}
+
+	/// EIPs changing the behavior of the EVM that are enabled, in ascending
+	/// order. Each EIP is judged from the fields it introduced, so custom
+	/// configs report the EIPs their fields enable.
+	pub fn enabled_eips(&self) -> Vec<u32> {
+		let eips = [
+			(2, self.gas_transaction_create > self.gas_transaction_call),
+			(7, self.has_delegate_call),
+			(140, self.has_revert),
+			(145, self.has_bitwise_shifting),
+			(150, self.call_l64_after_gas && !self.err_on_call_with_more_gas),
+			(160, self.gas_expbyte >= 50),
+			(161, self.clear_empty_touched_accounts && self.new_account_charged_only_on_value),
+			(170, self.create_contract_limit.is_some()),
+			(211, self.has_return_data),
+			(1014, self.has_create2),
+			(1052, self.has_ext_code_hash),
+			(1344, self.has_chain_id),
+			(1884, self.has_self_balance),
+			(2028, self.gas_transaction_non_zero_data == 16),
+			(2200, self.sstore_gas_metering && self.sstore_revert_under_stipend),
+			(3541, self.disallow_executable_format),
+		];
+
+		eips.iter().filter(|(_, enabled)| *enabled).map(|(eip, _)| *eip).collect()
+	}
+
+	/// Report of the capabilities of this config.
+	pub fn capabilities(&self) -> Capabilities {
+		Capabilities {
+			fork_name: self.fork_name(),
+			eips: self.enabled_eips(),
+			stack_limit: self.stack_limit,
+			memory_limit: self.memory_limit,
+			call_stack_limit: self.call_stack_limit,
+			create_contract_limit: self.create_contract_limit,
+			block_hash_history: self.block_hash_history,
+			refunds: !self.disable_refunds,
+		}
+	}
+}
+
+/// Capabilities of a config, for embedders and RPC layers reporting what the
+/// EVM supports.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Capabilities {
+	/// Name of the fork, see `Config::fork_name`.
+	pub fork_name: &'static str,
+	/// Enabled EIPs, in ascending order.
+	pub eips: Vec<u32>,
+	/// Stack limit.
+	pub stack_limit: usize,
+	/// Memory limit.
+	pub memory_limit: usize,
+	/// Call stack limit.
+	pub call_stack_limit: usize,
+	/// Create contract limit.
+	pub create_contract_limit: Option<usize>,
+	/// Number of past blocks available to `BLOCKHASH`.
+	pub block_hash_history: u64,
+	/// Whether gas refunds are applied.
+	pub refunds: bool,
+}
+
+/// Version of the layout of `Config`, bumped whenever a field is added,
+/// removed or changes meaning.
+pub const CONFIG_VERSION: u32 = 3;
+
+/// Config tagged with the version of its layout, for shipping it to another
+/// process.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VersionedConfig {
+	/// Version of the layout of `config`.
+	pub version: u32,
+	/// The config.
+	pub config: Config,
+}
+
+/// The version of a received config is not the one of this crate.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ConfigVersionMismatch {
+	/// Version of this crate, `CONFIG_VERSION`.
+	pub expected: u32,
+	/// Version of the received config.
+	pub found: u32,
+}
+
+impl VersionedConfig {
+	/// Tag a config with the current version.
+	pub fn new(config: Config) -> Self {
+		Self { version: CONFIG_VERSION, config }
+	}
+
+	/// Unwrap the config, checking that its version is the current one.
+	pub fn into_config(self) -> Result<Config, ConfigVersionMismatch> {
+		if self.version != CONFIG_VERSION {
+			return Err(ConfigVersionMismatch {
+				expected: CONFIG_VERSION,
+				found: self.version,
+			})
+		}
+
+		Ok(self.config)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use alloc::vec::Vec;
+	use primitive_types::{H160, H256, U256};
+	use super::*;
+
+	/// Handler for code never reaching the host.
+	struct Host;
+
+	impl Handler for Host {
+		type CreateInterrupt = ();
+		type CreateFeedback = ();
+		type CallInterrupt = ();
+		type CallFeedback = ();
+
+		fn balance(&self, _: H160) -> U256 { unreachable!() }
+		fn code_size(&self, _: H160) -> U256 { unreachable!() }
+		fn code_hash(&self, _: H160) -> H256 { unreachable!() }
+		fn code(&self, _: H160) -> Vec<u8> { unreachable!() }
+		fn storage(&self, _: H160, _: H256) -> H256 { unreachable!() }
+		fn original_storage(&self, _: H160, _: H256) -> H256 { unreachable!() }
+		fn gas_left(&self) -> U256 { unreachable!() }
+		fn gas_price(&self) -> U256 { unreachable!() }
+		fn origin(&self) -> H160 { unreachable!() }
+		fn block_hash(&self, _: U256) -> H256 { unreachable!() }
+		fn block_number(&self) -> U256 { unreachable!() }
+		fn block_coinbase(&self) -> H160 { unreachable!() }
+		fn block_timestamp(&self) -> U256 { unreachable!() }
+		fn block_difficulty(&self) -> U256 { unreachable!() }
+		fn block_gas_limit(&self) -> U256 { unreachable!() }
+		fn chain_id(&self) -> U256 { unreachable!() }
+		fn exists(&self, _: H160) -> bool { unreachable!() }
+		fn deleted(&self, _: H160) -> bool { unreachable!() }
+		fn set_storage(&mut self, _: H160, _: H256, _: H256) -> Result<(), ExitError> {
+			unreachable!()
+		}
+		fn log(&mut self, _: H160, _: Vec<H256>, _: Vec<u8>) -> Result<(), ExitError> {
+			unreachable!()
+		}
+		fn mark_delete(&mut self, _: H160, _: H160) -> Result<(), ExitError> { unreachable!() }
+		fn create(
+			&mut self,
+			_: H160,
+			_: CreateScheme,
+			_: U256,
+			_: Vec<u8>,
+			_: Option<u64>,
+		) -> Capture<(ExitReason, Option<H160>, Vec<u8>), ()> {
+			unreachable!()
+		}
+		fn call(
+			&mut self,
+			_: H160,
+			_: Option<Transfer>,
+			_: Vec<u8>,
+			_: Option<u64>,
+			_: bool,
+			_: Context,
+		) -> Capture<(ExitReason, Vec<u8>), ()> {
+			unreachable!()
+		}
+		fn pre_validate(&mut self, _: &Context, _: Opcode, _: &Stack) -> Result<(), ExitError> {
+			Ok(())
+		}
+	}
+
+	#[test]
+	fn breakpoints_are_stepped_over() {
+		let config = Config::istanbul();
+		let context = Context {
+			address: H160::default(),
+			caller: H160::default(),
+			apparent_value: U256::zero(),
+		};
+		// PUSH1 1; PUSH1 2; ADD; STOP
+		let code = [0x60, 0x01, 0x60, 0x02, 0x01, 0x00];
+		let mut runtime = Runtime::new_borrowed(&code, &[], context, &config);
+
+		// The machine alone traps before the breakpoint, then resumes.
+		runtime.machine.add_breakpoint(4);
+		assert_eq!(runtime.machine.run(), Capture::Trap(Trap::Breakpoint(4)));
+		assert_eq!(runtime.machine.stack().len(), 2);
+		assert_eq!(runtime.machine.run(), Capture::Exit(ExitSucceed::Stopped.into()));
+		assert_eq!(runtime.machine.stack().len(), 1);
+
+		// The runtime steps over it.
+		let mut runtime = Runtime::new_borrowed(&code, &[], runtime.context.clone(), &config);
+		runtime.machine.add_breakpoint(2);
+		runtime.machine.add_breakpoint(4);
+		match runtime.run(&mut Host) {
+			Capture::Exit(reason) => assert_eq!(reason, ExitSucceed::Stopped.into()),
+			Capture::Trap(_) => panic!("unexpected trap"),
+		}
+		assert_eq!(runtime.machine.stack().len(), 1);
+		assert_eq!(runtime.machine.stack().peek(0), Ok(H256::from_low_u64_be(3)));
+	}
+
+	#[test]
+	fn position_and_opcodes() {
+		let config = Config::istanbul();
+		let context = Context {
+			address: H160::default(),
+			caller: H160::default(),
+			apparent_value: U256::zero(),
+		};
+		// PUSH1 1; PUSH1 2; ADD; STOP
+		let code = [0x60, 0x01, 0x60, 0x02, 0x01, 0x00];
+		let mut runtime = Runtime::new_borrowed(&code, &[], context, &config);
+		assert_eq!(runtime.position(), &Ok(0));
+		assert_eq!(runtime.peek_opcode(), Some(Opcode::PUSH1));
+		assert_eq!(runtime.last_opcode(), None);
+
+		assert!(runtime.step(&mut Host).is_ok());
+		assert!(runtime.step(&mut Host).is_ok());
+		assert_eq!(runtime.position(), &Ok(4));
+		assert_eq!(runtime.peek_opcode(), Some(Opcode::ADD));
+		assert_eq!(runtime.last_opcode(), Some(Opcode::PUSH1));
+
+		assert!(runtime.step(&mut Host).is_ok());
+		assert_eq!(runtime.peek_opcode(), Some(Opcode::STOP));
+		assert_eq!(runtime.last_opcode(), Some(Opcode::ADD));
+
+		assert!(matches!(runtime.step(&mut Host), Err(Capture::Exit(_))));
+		assert_eq!(runtime.position(), &Err(ExitSucceed::Stopped.into()));
+		assert_eq!(runtime.peek_opcode(), None);
+		assert_eq!(runtime.last_opcode(), Some(Opcode::STOP));
+	}
 }
@@ -0,0 +1,102 @@
+//! Execution statistics collected from runtime tracing events.
+//!
+//! Install a `StatsListener` with `tracing::using`, run a transaction, and
+//! then take its `ExecutionStats`, for protocol research or for tuning gas
+//! schedules.
+
+use alloc::vec::Vec;
+use crate::Opcode;
+use crate::tracing::{Event, EventListener};
+
+/// Statistics of the executed code.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ExecutionStats {
+	/// Number of times each opcode was executed, indexed by opcode.
+	pub opcodes: [u64; 256],
+	/// Largest stack size reached by any frame.
+	pub max_stack: usize,
+	/// Largest memory size reached by any frame, in bytes.
+	pub max_memory: usize,
+	/// Number of calls and creates made.
+	pub external_calls: u64,
+	/// Number of storage reads.
+	pub storage_reads: u64,
+	/// Number of storage writes.
+	pub storage_writes: u64,
+}
+
+impl Default for ExecutionStats {
+	fn default() -> Self {
+		Self {
+			opcodes: [0; 256],
+			max_stack: 0,
+			max_memory: 0,
+			external_calls: 0,
+			storage_reads: 0,
+			storage_writes: 0,
+		}
+	}
+}
+
+impl ExecutionStats {
+	/// Total number of executed opcodes.
+	pub fn steps(&self) -> u64 {
+		self.opcodes.iter().sum()
+	}
+
+	/// Executed opcodes with their count, most frequent first.
+	pub fn histogram(&self) -> Vec<(Opcode, u64)> {
+		let mut histogram = self.opcodes.iter()
+			.enumerate()
+			.filter(|(_, count)| **count > 0)
+			.map(|(opcode, count)| (Opcode(opcode as u8), *count))
+			.collect::<Vec<_>>();
+		histogram.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.as_u8().cmp(&b.0.as_u8())));
+		histogram
+	}
+}
+
+/// Listener collecting `ExecutionStats`.
+#[derive(Clone, Debug, Default)]
+pub struct StatsListener {
+	stats: ExecutionStats,
+}
+
+impl StatsListener {
+	/// Create a new listener.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Statistics collected so far.
+	pub fn stats(&self) -> &ExecutionStats {
+		&self.stats
+	}
+
+	/// Take the statistics collected so far, resetting them for the next
+	/// transaction.
+	pub fn take(&mut self) -> ExecutionStats {
+		core::mem::take(&mut self.stats)
+	}
+}
+
+impl EventListener for StatsListener {
+	fn event(&mut self, event: Event) {
+		match event {
+			Event::Step { opcode, stack, memory, .. } => {
+				self.stats.opcodes[opcode.as_usize()] += 1;
+				self.stats.max_stack = core::cmp::max(self.stats.max_stack, stack.len());
+				self.stats.max_memory = core::cmp::max(self.stats.max_memory, memory.len());
+				match opcode {
+					Opcode::CALL | Opcode::CALLCODE | Opcode::DELEGATECALL |
+					Opcode::STATICCALL | Opcode::CREATE | Opcode::CREATE2 =>
+						self.stats.external_calls += 1,
+					_ => (),
+				}
+			},
+			Event::SLoad { .. } => self.stats.storage_reads += 1,
+			Event::SStore { .. } => self.stats.storage_writes += 1,
+			_ => (),
+		}
+	}
+}
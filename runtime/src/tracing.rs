@@ -1,6 +1,6 @@
 //! Allows to listen to runtime events.
 
-use crate::{Context, Opcode, Stack, Memory, Capture, ExitReason, Trap};
+use crate::{Context, Opcode, Stack, Memory, Capture, ExitReason, Trap, Bytes, Frame};
 use primitive_types::{H160, H256};
 
 environmental::environmental!(listener: dyn EventListener + 'static);
@@ -12,29 +12,42 @@ pub trait EventListener {
     );
 }
 
+/// Runtime event. `frame` is the call frame the event happened in.
 #[derive(Debug, Copy, Clone)]
 pub enum Event<'a> {
     Step {
+        frame: Frame,
         context: &'a Context,
+        code: &'a Bytes<'a>,
         opcode: Opcode,
         position: &'a Result<usize, ExitReason>,
         stack: &'a Stack,
         memory: &'a Memory
     },
     StepResult {
+        frame: Frame,
         result: &'a Result<(), Capture<ExitReason, Trap>>,
         return_value: &'a [u8],
     },
     SLoad {
+        frame: Frame,
         address: H160,
         index: H256,
         value: H256
     },
     SStore {
+        frame: Frame,
         address: H160,
         index: H256,
         value: H256
     },
+    /// Strict stack validation failed before the opcode was executed.
+    StackUnderflow {
+        frame: Frame,
+        opcode: Opcode,
+        required: usize,
+        available: usize,
+    },
 }
 
 impl<'a> Event<'a> {
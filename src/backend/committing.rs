@@ -0,0 +1,308 @@
+use core::ops::RangeInclusive;
+use alloc::vec::Vec;
+use alloc::collections::BTreeMap;
+use primitive_types::{H160, H256, U256};
+use sha3::{Digest, Keccak256};
+use super::{Apply, ApplyBackend, Backend, Basic, Log, MemoryAccount, MemoryBackend};
+
+fn keccak(parts: &[&[u8]]) -> H256 {
+	let mut hasher = Keccak256::new();
+	for part in parts {
+		hasher.input(part);
+	}
+	H256::from_slice(hasher.result().as_slice())
+}
+
+/// `key` with its bits from `depth` on cleared, or set if `ones`.
+fn fill(mut key: H256, depth: usize, ones: bool) -> H256 {
+	let byte = depth / 8;
+	if byte < 32 {
+		let key = key.as_bytes_mut();
+		let mask = 0xffu8 >> (depth % 8);
+		if ones {
+			key[byte] |= mask;
+		} else {
+			key[byte] &= !mask;
+		}
+		for b in &mut key[byte + 1..] {
+			*b = if ones { 0xff } else { 0 };
+		}
+	}
+	key
+}
+
+/// `key` with its bit at `depth` set.
+fn set_bit(mut key: H256, depth: usize) -> H256 {
+	key.as_bytes_mut()[depth / 8] |= 0x80 >> (depth % 8);
+	key
+}
+
+/// Sparse Merkle tree over 256-bit keys, most significant bit first.
+///
+/// An empty subtree hashes to zero, and a subtree holding a single leaf to
+/// `keccak(0x00 ++ key ++ value)`, so that only the levels where keys
+/// diverge are hashed, as `keccak(0x01 ++ left ++ right)`.
+#[derive(Clone, Debug, Default)]
+struct SparseMerkleTree {
+	leaves: BTreeMap<H256, H256>,
+	/// Hashes of the subtrees holding two leaves or more, by depth and
+	/// lowest key of the subtree.
+	nodes: BTreeMap<(usize, H256), H256>,
+}
+
+impl SparseMerkleTree {
+	fn root(&self) -> H256 {
+		self.node(0, H256::default())
+	}
+
+	/// Set the leaf at `key`, removing it if `value` is zero, and rehash the
+	/// path to the root.
+	fn set(&mut self, key: H256, value: H256) {
+		if value == H256::default() {
+			self.leaves.remove(&key);
+		} else {
+			self.leaves.insert(key, value);
+		}
+
+		for depth in (0..256).rev() {
+			let start = fill(key, depth, false);
+			if self.leaves.range(Self::range(depth, start)).nth(1).is_none() {
+				self.nodes.remove(&(depth, start));
+			} else {
+				let left = self.node(depth + 1, start);
+				let right = self.node(depth + 1, set_bit(start, depth));
+				self.nodes.insert((depth, start), keccak(&[&[0x01], &left[..], &right[..]]));
+			}
+		}
+	}
+
+	fn range(depth: usize, start: H256) -> RangeInclusive<H256> {
+		start..=fill(start, depth, true)
+	}
+
+	fn node(&self, depth: usize, start: H256) -> H256 {
+		let mut leaves = self.leaves.range(Self::range(depth, start));
+		match (leaves.next(), leaves.next()) {
+			(None, _) => H256::default(),
+			(Some((key, value)), None) => keccak(&[&[0x00], &key[..], &value[..]]),
+			_ => self.nodes[&(depth, start)],
+		}
+	}
+}
+
+#[derive(Clone, Debug)]
+struct AccountCommitment {
+	nonce: U256,
+	balance: U256,
+	code_hash: H256,
+	storage: SparseMerkleTree,
+}
+
+impl Default for AccountCommitment {
+	fn default() -> Self {
+		Self {
+			nonce: U256::zero(),
+			balance: U256::zero(),
+			code_hash: keccak(&[]),
+			storage: SparseMerkleTree::default(),
+		}
+	}
+}
+
+impl AccountCommitment {
+	fn new(account: &MemoryAccount) -> Self {
+		let mut commitment = Self {
+			nonce: account.nonce,
+			balance: account.balance,
+			code_hash: keccak(&[&account.code]),
+			storage: SparseMerkleTree::default(),
+		};
+		for (index, value) in &account.storage {
+			commitment.storage.set(keccak(&[&index[..]]), *value);
+		}
+		commitment
+	}
+
+	fn is_empty(&self) -> bool {
+		self.balance == U256::zero() &&
+			self.nonce == U256::zero() &&
+			self.code_hash == AccountCommitment::default().code_hash
+	}
+
+	/// Leaf of the account in the state tree.
+	fn leaf(&self) -> H256 {
+		let mut data = [0u8; 4 * 32];
+		self.nonce.to_big_endian(&mut data[..32]);
+		self.balance.to_big_endian(&mut data[32..64]);
+		data[64..96].copy_from_slice(&self.code_hash[..]);
+		data[96..].copy_from_slice(&self.storage.root()[..]);
+		keccak(&[&data])
+	}
+}
+
+/// Account listed to `CommittingBackend::new` that differs from the inner
+/// backend.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct AccountMismatch {
+	/// Address of the account.
+	pub address: H160,
+}
+
+/// Backend wrapper maintaining state commitments as changes are written
+/// through to the inner backend.
+///
+/// Storage is committed to by a sparse Merkle tree of the non-zero slots of
+/// each account, keyed by `keccak(index)`, and the state by a sparse Merkle
+/// tree of the accounts, keyed by `keccak(address)`, whose leaves are
+/// `keccak(nonce ++ balance ++ code_hash ++ storage_root)`. Applying a change
+/// rehashes the paths of the changed leaves only.
+pub struct CommittingBackend<B> {
+	inner: B,
+	accounts: BTreeMap<H160, AccountCommitment>,
+	state: SparseMerkleTree,
+}
+
+impl<'vicinity> CommittingBackend<MemoryBackend<'vicinity>> {
+	/// Wrap a memory backend, committing to its whole state.
+	pub fn from_memory(inner: MemoryBackend<'vicinity>) -> Self {
+		let (accounts, state) = commit(inner.state());
+		Self { inner, accounts, state }
+	}
+}
+
+impl<B: Backend> CommittingBackend<B> {
+	/// Wrap a backend holding `accounts`, which must list all its accounts
+	/// with their full storage. Listed accounts differing from the backend
+	/// are rejected, but accounts and slots missing from the list cannot be
+	/// detected.
+	pub fn new(inner: B, accounts: &BTreeMap<H160, MemoryAccount>) -> Result<Self, AccountMismatch> {
+		for (address, account) in accounts {
+			let matches = inner.exists(*address) &&
+				inner.basic(*address) == Basic { balance: account.balance, nonce: account.nonce } &&
+				inner.code(*address) == account.code &&
+				account.storage.iter().all(|(index, value)| inner.storage(*address, *index) == *value);
+			if !matches {
+				return Err(AccountMismatch { address: *address })
+			}
+		}
+
+		let (accounts, state) = commit(accounts);
+		Ok(Self { inner, accounts, state })
+	}
+}
+
+fn commit(
+	accounts: &BTreeMap<H160, MemoryAccount>,
+) -> (BTreeMap<H160, AccountCommitment>, SparseMerkleTree) {
+	let mut commitments = BTreeMap::new();
+	let mut state = SparseMerkleTree::default();
+	for (address, account) in accounts {
+		let commitment = AccountCommitment::new(account);
+		state.set(keccak(&[&address[..]]), commitment.leaf());
+		commitments.insert(*address, commitment);
+	}
+	(commitments, state)
+}
+
+impl<B> CommittingBackend<B> {
+	/// Commitment to the whole state, the root of the account tree.
+	pub fn state_commitment(&self) -> H256 {
+		self.state.root()
+	}
+
+	/// Commitment to the storage of `address`, the root of its storage tree.
+	/// Zero if the account does not exist or has no storage.
+	pub fn storage_commitment(&self, address: H160) -> H256 {
+		self.accounts.get(&address).map(|a| a.storage.root()).unwrap_or_default()
+	}
+
+	/// Inner backend.
+	pub fn inner(&self) -> &B {
+		&self.inner
+	}
+
+	/// Unwrap the inner backend.
+	pub fn into_inner(self) -> B {
+		self.inner
+	}
+}
+
+impl<B: Backend> Backend for CommittingBackend<B> {
+	fn gas_price(&self) -> U256 { self.inner.gas_price() }
+	fn origin(&self) -> H160 { self.inner.origin() }
+	fn block_hash(&self, number: U256) -> H256 { self.inner.block_hash(number) }
+	fn block_number(&self) -> U256 { self.inner.block_number() }
+	fn block_coinbase(&self) -> H160 { self.inner.block_coinbase() }
+	fn block_timestamp(&self) -> U256 { self.inner.block_timestamp() }
+	fn block_difficulty(&self) -> U256 { self.inner.block_difficulty() }
+	fn block_gas_limit(&self) -> U256 { self.inner.block_gas_limit() }
+	fn chain_id(&self) -> U256 { self.inner.chain_id() }
+
+	fn exists(&self, address: H160) -> bool { self.inner.exists(address) }
+	fn basic(&self, address: H160) -> Basic { self.inner.basic(address) }
+	fn code(&self, address: H160) -> Vec<u8> { self.inner.code(address) }
+	fn code_chunk(&self, address: H160, offset: usize, len: usize) -> Vec<u8> {
+		self.inner.code_chunk(address, offset, len)
+	}
+	fn storage(&self, address: H160, index: H256) -> H256 { self.inner.storage(address, index) }
+	fn original_storage(&self, address: H160, index: H256) -> Option<H256> {
+		self.inner.original_storage(address, index)
+	}
+}
+
+impl<B: Backend + ApplyBackend> ApplyBackend for CommittingBackend<B> {
+	fn apply<A, I, L>(
+		&mut self,
+		values: A,
+		logs: L,
+		delete_empty: bool,
+	) where
+		A: IntoIterator<Item=Apply<I>>,
+		I: IntoIterator<Item=(H256, H256)>,
+		L: IntoIterator<Item=Log>,
+	{
+		let mut applies = Vec::new();
+		for apply in values {
+			match apply {
+				Apply::Modify {
+					address, basic, code, storage, reset_storage,
+				} => {
+					let storage = storage.into_iter().collect::<Vec<_>>();
+					let account = self.accounts.entry(address).or_default();
+
+					account.nonce = basic.nonce;
+					account.balance = basic.balance;
+					if let Some(code) = &code {
+						account.code_hash = keccak(&[code]);
+					}
+					if reset_storage {
+						account.storage = SparseMerkleTree::default();
+					}
+					for (index, value) in &storage {
+						account.storage.set(keccak(&[&index[..]]), *value);
+					}
+
+					let leaf = if account.is_empty() && delete_empty {
+						self.accounts.remove(&address);
+						H256::default()
+					} else {
+						account.leaf()
+					};
+					self.state.set(keccak(&[&address[..]]), leaf);
+
+					applies.push(Apply::Modify { address, basic, code, storage, reset_storage });
+				},
+				Apply::Delete {
+					address,
+				} => {
+					self.accounts.remove(&address);
+					self.state.set(keccak(&[&address[..]]), H256::default());
+
+					applies.push(Apply::Delete { address });
+				},
+			}
+		}
+
+		self.inner.apply(applies, logs, delete_empty)
+	}
+}
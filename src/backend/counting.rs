@@ -0,0 +1,178 @@
+use core::cell::RefCell;
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+use primitive_types::{H160, H256, U256};
+use super::{Apply, ApplyBackend, Backend, Basic, Log};
+
+/// Number of backend accesses of one kind.
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Default)]
+pub struct AccessCount {
+	/// Accesses to a key not accessed before in the measurement.
+	pub cold: u64,
+	/// Accesses to a key already accessed in the measurement.
+	pub warm: u64,
+}
+
+impl AccessCount {
+	/// Total number of accesses.
+	pub fn total(&self) -> u64 {
+		self.cold + self.warm
+	}
+
+	fn record(&mut self, cold: bool) {
+		if cold {
+			self.cold += 1;
+		} else {
+			self.warm += 1;
+		}
+	}
+}
+
+/// Backend accesses observed by a `CountingBackend`.
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Default)]
+pub struct AccessStats {
+	/// Calls to `exists`.
+	pub exists: AccessCount,
+	/// Calls to `basic`.
+	pub basic: AccessCount,
+	/// Calls to `code` and `code_chunk`.
+	pub code: AccessCount,
+	/// Calls to `storage`.
+	pub storage: AccessCount,
+	/// Calls to `original_storage`.
+	pub original_storage: AccessCount,
+}
+
+impl AccessStats {
+	/// Total number of state accesses.
+	pub fn total(&self) -> u64 {
+		self.exists.total() + self.basic.total() + self.code.total() +
+			self.storage.total() + self.original_storage.total()
+	}
+}
+
+#[derive(Default)]
+struct Accesses {
+	stats: AccessStats,
+	exists: BTreeSet<H160>,
+	basic: BTreeSet<H160>,
+	code: BTreeSet<H160>,
+	storage: BTreeSet<(H160, H256)>,
+	original_storage: BTreeSet<(H160, H256)>,
+}
+
+/// Backend wrapper counting the state accesses made to the inner backend.
+///
+/// An access is cold the first time its key (address, or address and index)
+/// is requested with a given method since the last `take_stats`, and warm
+/// afterwards. Call `take_stats` after each transaction to get its I/O
+/// profile. Environmental getters are not counted.
+pub struct CountingBackend<B> {
+	inner: B,
+	accesses: RefCell<Accesses>,
+}
+
+impl<B> CountingBackend<B> {
+	/// Wrap a backend.
+	pub fn new(inner: B) -> Self {
+		Self {
+			inner,
+			accesses: RefCell::new(Accesses::default()),
+		}
+	}
+
+	/// Accesses observed since the last reset.
+	pub fn stats(&self) -> AccessStats {
+		self.accesses.borrow().stats
+	}
+
+	/// Return the accesses observed since the last reset, and start a new
+	/// measurement. All keys become cold again.
+	pub fn take_stats(&mut self) -> AccessStats {
+		self.accesses.replace(Accesses::default()).stats
+	}
+
+	/// Inner backend.
+	pub fn inner(&self) -> &B {
+		&self.inner
+	}
+
+	/// Mutable inner backend.
+	pub fn inner_mut(&mut self) -> &mut B {
+		&mut self.inner
+	}
+
+	/// Unwrap the inner backend.
+	pub fn into_inner(self) -> B {
+		self.inner
+	}
+}
+
+impl<B: Backend> Backend for CountingBackend<B> {
+	fn gas_price(&self) -> U256 { self.inner.gas_price() }
+	fn origin(&self) -> H160 { self.inner.origin() }
+	fn block_hash(&self, number: U256) -> H256 { self.inner.block_hash(number) }
+	fn block_number(&self) -> U256 { self.inner.block_number() }
+	fn block_coinbase(&self) -> H160 { self.inner.block_coinbase() }
+	fn block_timestamp(&self) -> U256 { self.inner.block_timestamp() }
+	fn block_difficulty(&self) -> U256 { self.inner.block_difficulty() }
+	fn block_gas_limit(&self) -> U256 { self.inner.block_gas_limit() }
+	fn chain_id(&self) -> U256 { self.inner.chain_id() }
+
+	fn exists(&self, address: H160) -> bool {
+		let mut accesses = self.accesses.borrow_mut();
+		let cold = accesses.exists.insert(address);
+		accesses.stats.exists.record(cold);
+		self.inner.exists(address)
+	}
+
+	fn basic(&self, address: H160) -> Basic {
+		let mut accesses = self.accesses.borrow_mut();
+		let cold = accesses.basic.insert(address);
+		accesses.stats.basic.record(cold);
+		self.inner.basic(address)
+	}
+
+	fn code(&self, address: H160) -> Vec<u8> {
+		let mut accesses = self.accesses.borrow_mut();
+		let cold = accesses.code.insert(address);
+		accesses.stats.code.record(cold);
+		self.inner.code(address)
+	}
+
+	fn code_chunk(&self, address: H160, offset: usize, len: usize) -> Vec<u8> {
+		let mut accesses = self.accesses.borrow_mut();
+		let cold = accesses.code.insert(address);
+		accesses.stats.code.record(cold);
+		self.inner.code_chunk(address, offset, len)
+	}
+
+	fn storage(&self, address: H160, index: H256) -> H256 {
+		let mut accesses = self.accesses.borrow_mut();
+		let cold = accesses.storage.insert((address, index));
+		accesses.stats.storage.record(cold);
+		self.inner.storage(address, index)
+	}
+
+	fn original_storage(&self, address: H160, index: H256) -> Option<H256> {
+		let mut accesses = self.accesses.borrow_mut();
+		let cold = accesses.original_storage.insert((address, index));
+		accesses.stats.original_storage.record(cold);
+		self.inner.original_storage(address, index)
+	}
+}
+
+impl<B: ApplyBackend> ApplyBackend for CountingBackend<B> {
+	fn apply<A, I, L>(
+		&mut self,
+		values: A,
+		logs: L,
+		delete_empty: bool,
+	) where
+		A: IntoIterator<Item=Apply<I>>,
+		I: IntoIterator<Item=(H256, H256)>,
+		L: IntoIterator<Item=Log>,
+	{
+		self.inner.apply(values, logs, delete_empty)
+	}
+}
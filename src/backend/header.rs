@@ -0,0 +1,111 @@
+use alloc::vec::Vec;
+use primitive_types::{H160, H256, U256};
+use ethereum::Header;
+use super::{Basic, Backend, MemoryVicinity};
+
+/// Source of account state and past block hashes for a `HeaderBackend`.
+pub trait StateProvider {
+	/// Whether account at address exists.
+	fn exists(&self, address: H160) -> bool;
+	/// Get basic account information.
+	fn basic(&self, address: H160) -> Basic;
+	/// Get account code.
+	fn code(&self, address: H160) -> Vec<u8>;
+	/// Get storage value of address at index.
+	fn storage(&self, address: H160, index: H256) -> H256;
+	/// Get original storage value of address at index, if available.
+	fn original_storage(&self, _address: H160, _index: H256) -> Option<H256> {
+		None
+	}
+	/// Hash of the block with the given number. Only called for blocks
+	/// preceding the current one, the executor restricting BLOCKHASH to the
+	/// last `Config::block_hash_history` of them.
+	fn block_hash(&self, number: U256) -> H256;
+}
+
+/// Backend reading the block environment from an `ethereum` block header and
+/// account state from a `StateProvider`.
+pub struct HeaderBackend<'header, P> {
+	header: &'header Header,
+	chain_id: U256,
+	gas_price: U256,
+	origin: H160,
+	state: P,
+}
+
+impl<'header, P: StateProvider> HeaderBackend<'header, P> {
+	/// Create a new backend for a transaction of the block with the given
+	/// header.
+	pub fn new(
+		header: &'header Header,
+		chain_id: U256,
+		gas_price: U256,
+		origin: H160,
+		state: P,
+	) -> Self {
+		Self { header, chain_id, gas_price, origin, state }
+	}
+
+	/// Switch to another transaction of the same block.
+	pub fn set_transaction(&mut self, gas_price: U256, origin: H160) {
+		self.gas_price = gas_price;
+		self.origin = origin;
+	}
+
+	/// Get the state provider.
+	pub fn state(&self) -> &P {
+		&self.state
+	}
+}
+
+impl<'header, P: StateProvider> Backend for HeaderBackend<'header, P> {
+	fn gas_price(&self) -> U256 { self.gas_price }
+	fn origin(&self) -> H160 { self.origin }
+	fn block_hash(&self, number: U256) -> H256 {
+		if number >= self.header.number {
+			H256::default()
+		} else {
+			self.state.block_hash(number)
+		}
+	}
+	fn block_number(&self) -> U256 { self.header.number }
+	fn block_coinbase(&self) -> H160 { self.header.beneficiary }
+	fn block_timestamp(&self) -> U256 { U256::from(self.header.timestamp) }
+	fn block_difficulty(&self) -> U256 { self.header.difficulty }
+	fn block_gas_limit(&self) -> U256 { self.header.gas_limit }
+	fn chain_id(&self) -> U256 { self.chain_id }
+
+	fn exists(&self, address: H160) -> bool { self.state.exists(address) }
+	fn basic(&self, address: H160) -> Basic { self.state.basic(address) }
+	fn code(&self, address: H160) -> Vec<u8> { self.state.code(address) }
+	fn storage(&self, address: H160, index: H256) -> H256 { self.state.storage(address, index) }
+	fn original_storage(&self, address: H160, index: H256) -> Option<H256> {
+		self.state.original_storage(address, index)
+	}
+}
+
+impl MemoryVicinity {
+	/// Create a vicinity from a block header. `block_hashes` are the hashes of
+	/// the preceding blocks, most recent first. As many hashes, and at least
+	/// 256, are kept when pushing blocks.
+	pub fn from_header(
+		header: &Header,
+		chain_id: U256,
+		gas_price: U256,
+		origin: H160,
+		block_hashes: Vec<H256>,
+	) -> Self {
+		Self {
+			gas_price,
+			origin,
+			chain_id,
+			max_block_hashes: block_hashes.len().max(256),
+			block_hashes,
+			block_number: header.number,
+			block_coinbase: header.beneficiary,
+			block_timestamp: U256::from(header.timestamp),
+			block_difficulty: header.difficulty,
+			block_gas_limit: header.gas_limit,
+		}
+	}
+}
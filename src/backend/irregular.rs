@@ -0,0 +1,149 @@
+use alloc::vec::Vec;
+use primitive_types::{H160, H256, U256};
+use crate::ExitError;
+use super::{Apply, ApplyBackend, Backend, Basic, Log};
+
+/// Irregular state change, applied outside of any transaction. Chains use
+/// them to hard-fork state, such as the DAO fork.
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "with-codec", derive(codec::Encode, codec::Decode))]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum IrregularChange {
+	/// Move `value` from `source` to `target`.
+	Transfer {
+		/// Source address.
+		source: H160,
+		/// Target address.
+		target: H160,
+		/// Transferred value.
+		value: U256,
+	},
+	/// Move the whole balance of `source` to `target`.
+	Drain {
+		/// Source address.
+		source: H160,
+		/// Target address.
+		target: H160,
+	},
+	/// Set the balance of an account.
+	SetBalance {
+		/// Address.
+		address: H160,
+		/// New balance.
+		balance: U256,
+	},
+	/// Set the nonce of an account.
+	SetNonce {
+		/// Address.
+		address: H160,
+		/// New nonce.
+		nonce: U256,
+	},
+	/// Set the code of an account.
+	SetCode {
+		/// Address.
+		address: H160,
+		/// New code.
+		code: Vec<u8>,
+	},
+	/// Set a storage value of an account.
+	SetStorage {
+		/// Address.
+		address: H160,
+		/// Storage index.
+		index: H256,
+		/// New value.
+		value: H256,
+	},
+}
+
+/// Irregular state change that could not be applied.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct IrregularChangeError {
+	/// Index of the change in the applied list.
+	pub index: usize,
+	/// Reason of the failure, `ExitError::OutOfFund` for a transfer exceeding
+	/// the source balance.
+	pub error: ExitError,
+}
+
+/// Apply irregular state changes to a backend, in order.
+///
+/// Changes go through `ApplyBackend::apply` like transaction results, so any
+/// backend supports them. They consume no gas, emit no logs and never delete
+/// accounts. Applying stops at the first change that fails, which has no
+/// effect, while the changes before it stay applied.
+pub fn apply_irregular_changes<B: Backend + ApplyBackend>(
+	backend: &mut B,
+	changes: &[IrregularChange],
+) -> Result<(), IrregularChangeError> {
+	for (index, change) in changes.iter().enumerate() {
+		match change {
+			IrregularChange::Transfer { source, target, value } => {
+				if *value > backend.basic(*source).balance {
+					return Err(IrregularChangeError { index, error: ExitError::OutOfFund })
+				}
+				move_balance(backend, *source, *target, *value);
+			},
+			IrregularChange::Drain { source, target } => {
+				let value = backend.basic(*source).balance;
+				move_balance(backend, *source, *target, value);
+			},
+			IrregularChange::SetBalance { address, balance } => {
+				let basic = backend.basic(*address);
+				modify(backend, *address, Basic { balance: *balance, nonce: basic.nonce }, None, Vec::new());
+			},
+			IrregularChange::SetNonce { address, nonce } => {
+				let basic = backend.basic(*address);
+				modify(backend, *address, Basic { balance: basic.balance, nonce: *nonce }, None, Vec::new());
+			},
+			IrregularChange::SetCode { address, code } => {
+				let basic = backend.basic(*address);
+				modify(backend, *address, basic, Some(code.clone()), Vec::new());
+			},
+			IrregularChange::SetStorage { address, index, value } => {
+				let basic = backend.basic(*address);
+				modify(backend, *address, basic, None, alloc::vec![(*index, *value)]);
+			},
+		}
+	}
+
+	Ok(())
+}
+
+fn move_balance<B: Backend + ApplyBackend>(
+	backend: &mut B,
+	source: H160,
+	target: H160,
+	value: U256,
+) {
+	if source == target || value == U256::zero() {
+		return
+	}
+
+	let source_basic = backend.basic(source);
+	modify(backend, source, Basic {
+		balance: source_basic.balance - value,
+		nonce: source_basic.nonce,
+	}, None, Vec::new());
+
+	let target_basic = backend.basic(target);
+	modify(backend, target, Basic {
+		balance: target_basic.balance.saturating_add(value),
+		nonce: target_basic.nonce,
+	}, None, Vec::new());
+}
+
+fn modify<B: ApplyBackend>(
+	backend: &mut B,
+	address: H160,
+	basic: Basic,
+	code: Option<Vec<u8>>,
+	storage: Vec<(H256, H256)>,
+) {
+	backend.apply(
+		Some(Apply::Modify { address, basic, code, storage, reset_storage: false }),
+		Vec::<Log>::new(),
+		false,
+	);
+}
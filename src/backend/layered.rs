@@ -0,0 +1,184 @@
+use alloc::vec::Vec;
+use alloc::collections::BTreeMap;
+use primitive_types::{H160, H256, U256};
+use super::{Basic, Backend, ApplyBackend, Apply, Log, MemoryAccount, MemoryVicinity};
+use super::memory::modify_account;
+
+/// Changes of the block being executed. `None` accounts are deleted.
+#[derive(Clone, Debug, Default)]
+struct PendingBlock {
+	accounts: BTreeMap<H160, Option<MemoryAccount>>,
+	logs: Vec<Log>,
+}
+
+/// In-memory backend with block boundaries, for multi-block test scenarios
+/// and light devnets.
+///
+/// Transactions are applied to a layer holding the changes of the current
+/// block, opened with `begin_block`. The layer is then either merged into
+/// the committed state with `commit_block`, which also advances the block
+/// number and records the block hash for `BLOCKHASH`, or dropped with
+/// `rollback_block`.
+#[derive(Clone, Debug)]
+pub struct LayeredBackend {
+	vicinity: MemoryVicinity,
+	state: BTreeMap<H160, MemoryAccount>,
+	logs: Vec<Log>,
+	pending: Option<PendingBlock>,
+}
+
+impl LayeredBackend {
+	/// Create a new layered backend. `vicinity.block_number` is the number of
+	/// the first block to execute.
+	pub fn new(vicinity: MemoryVicinity, state: BTreeMap<H160, MemoryAccount>) -> Self {
+		Self {
+			vicinity,
+			state,
+			logs: Vec::new(),
+			pending: None,
+		}
+	}
+
+	/// Block environment.
+	pub fn vicinity(&self) -> &MemoryVicinity {
+		&self.vicinity
+	}
+
+	/// Mutable block environment, to set the timestamp, coinbase or
+	/// transaction fields between blocks and transactions.
+	pub fn vicinity_mut(&mut self) -> &mut MemoryVicinity {
+		&mut self.vicinity
+	}
+
+	/// Committed state, excluding the changes of the current block.
+	pub fn state(&self) -> &BTreeMap<H160, MemoryAccount> {
+		&self.state
+	}
+
+	/// Committed logs, excluding the ones of the current block.
+	pub fn logs(&self) -> &[Log] {
+		&self.logs
+	}
+
+	/// Whether a block is open.
+	pub fn in_block(&self) -> bool {
+		self.pending.is_some()
+	}
+
+	/// Open a new block, to which transactions are applied. Panic if a block
+	/// is already open.
+	pub fn begin_block(&mut self) {
+		assert!(self.pending.is_none(), "Block already open");
+		self.pending = Some(PendingBlock::default());
+	}
+
+	/// Commit the open block with the given hash, and move to the next block
+	/// number. Panic if no block is open.
+	pub fn commit_block(&mut self, hash: H256) {
+		let pending = self.pending.take().expect("No open block to commit");
+
+		for (address, account) in pending.accounts {
+			match account {
+				Some(account) => {
+					self.state.insert(address, account);
+				},
+				None => {
+					self.state.remove(&address);
+				},
+			}
+		}
+		self.logs.extend(pending.logs);
+
+		self.vicinity.push_block(hash);
+	}
+
+	/// Drop the changes of the open block. Panic if no block is open.
+	pub fn rollback_block(&mut self) {
+		self.pending.take().expect("No open block to roll back");
+	}
+
+	fn account(&self, address: H160) -> Option<&MemoryAccount> {
+		match self.pending.as_ref().and_then(|pending| pending.accounts.get(&address)) {
+			Some(account) => account.as_ref(),
+			None => self.state.get(&address),
+		}
+	}
+}
+
+impl Backend for LayeredBackend {
+	fn gas_price(&self) -> U256 { self.vicinity.gas_price }
+	fn origin(&self) -> H160 { self.vicinity.origin }
+	fn block_hash(&self, number: U256) -> H256 { self.vicinity.block_hash(number) }
+	fn block_number(&self) -> U256 { self.vicinity.block_number }
+	fn block_coinbase(&self) -> H160 { self.vicinity.block_coinbase }
+	fn block_timestamp(&self) -> U256 { self.vicinity.block_timestamp }
+	fn block_difficulty(&self) -> U256 { self.vicinity.block_difficulty }
+	fn block_gas_limit(&self) -> U256 { self.vicinity.block_gas_limit }
+
+	fn chain_id(&self) -> U256 { self.vicinity.chain_id }
+
+	fn exists(&self, address: H160) -> bool {
+		self.account(address).is_some()
+	}
+
+	fn basic(&self, address: H160) -> Basic {
+		self.account(address).map(|a| {
+			Basic { balance: a.balance, nonce: a.nonce }
+		}).unwrap_or_default()
+	}
+
+	fn code(&self, address: H160) -> Vec<u8> {
+		self.account(address).map(|v| v.code.clone()).unwrap_or_default()
+	}
+
+	fn storage(&self, address: H160, index: H256) -> H256 {
+		self.account(address)
+			.and_then(|v| v.storage.get(&index).cloned())
+			.unwrap_or_default()
+	}
+
+	fn original_storage(&self, address: H160, index: H256) -> Option<H256> {
+		Some(self.storage(address, index))
+	}
+}
+
+impl ApplyBackend for LayeredBackend {
+	/// Apply a transaction to the open block. Panic if no block is open.
+	fn apply<A, I, L>(
+		&mut self,
+		values: A,
+		logs: L,
+		delete_empty: bool,
+	) where
+		A: IntoIterator<Item=Apply<I>>,
+		I: IntoIterator<Item=(H256, H256)>,
+		L: IntoIterator<Item=Log>,
+	{
+		let state = &self.state;
+		let pending = self.pending.as_mut().expect("No open block to apply to");
+
+		for apply in values {
+			match apply {
+				Apply::Modify {
+					address, basic, code, storage, reset_storage,
+				} => {
+					let account = pending.accounts.entry(address)
+						.or_insert_with(|| state.get(&address).cloned())
+						.get_or_insert_with(Default::default);
+					let is_empty = modify_account(account, basic, code, storage, reset_storage);
+
+					if is_empty && delete_empty {
+						pending.accounts.insert(address, None);
+					}
+				},
+				Apply::Delete {
+					address,
+				} => {
+					pending.accounts.insert(address, None);
+				},
+			}
+		}
+
+		pending.logs.extend(logs);
+	}
+}
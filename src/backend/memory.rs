@@ -1,4 +1,5 @@
 use alloc::vec::Vec;
+use alloc::borrow::Cow;
 use alloc::collections::BTreeMap;
 use primitive_types::{H160, H256, U256};
 use super::{Basic, Backend, ApplyBackend, Apply, Log};
@@ -14,8 +15,10 @@ pub struct MemoryVicinity {
 	pub origin: H160,
 	/// Chain ID.
 	pub chain_id: U256,
-	/// Environmental block hashes.
+	/// Environmental block hashes, most recent first.
 	pub block_hashes: Vec<H256>,
+	/// Number of block hashes kept by `push_block`.
+	pub max_block_hashes: usize,
 	/// Environmental block number.
 	pub block_number: U256,
 	/// Environmental coinbase.
@@ -28,6 +31,33 @@ pub struct MemoryVicinity {
 	pub block_gas_limit: U256,
 }
 
+impl MemoryVicinity {
+	/// Hash of the block with the given number, from `block_hashes`. The
+	/// current block, future blocks and blocks older than the history held
+	/// hash to zero. The executor limits `BLOCKHASH` further, to
+	/// `Config::block_hash_history` blocks.
+	pub fn block_hash(&self, number: U256) -> H256 {
+		if number >= self.block_number {
+			return H256::default()
+		}
+
+		let age = self.block_number - number - U256::one();
+		if age >= U256::from(self.block_hashes.len()) {
+			H256::default()
+		} else {
+			self.block_hashes[age.as_usize()]
+		}
+	}
+
+	/// Move to the next block, recording the hash of the current one. Only
+	/// the `max_block_hashes` most recent hashes are kept.
+	pub fn push_block(&mut self, hash: H256) {
+		self.block_hashes.insert(0, hash);
+		self.block_hashes.truncate(self.max_block_hashes);
+		self.block_number += U256::one();
+	}
+}
+
 /// Account information of a memory backend.
 #[derive(Default, Clone, Debug, Eq, PartialEq)]
 #[cfg_attr(feature = "with-codec", derive(codec::Encode, codec::Decode))]
@@ -44,23 +74,42 @@ pub struct MemoryAccount {
 }
 
 /// Memory backend, storing all state values in a `BTreeMap` in memory.
+///
+/// The backend is `Send` and `Sync`, so that it can be shared immutably by
+/// executors running on several threads.
 #[derive(Clone, Debug)]
 pub struct MemoryBackend<'vicinity> {
-	vicinity: &'vicinity MemoryVicinity,
+	vicinity: Cow<'vicinity, MemoryVicinity>,
 	state: BTreeMap<H160, MemoryAccount>,
 	logs: Vec<Log>,
 }
 
+const _: fn() = || {
+	fn assert_send_sync<T: Send + Sync>() {}
+	assert_send_sync::<MemoryBackend<'static>>();
+};
+
 impl<'vicinity> MemoryBackend<'vicinity> {
 	/// Create a new memory backend.
 	pub fn new(vicinity: &'vicinity MemoryVicinity, state: BTreeMap<H160, MemoryAccount>) -> Self {
 		Self {
-			vicinity,
+			vicinity: Cow::Borrowed(vicinity),
 			state,
 			logs: Vec::new(),
 		}
 	}
 
+	/// Block environment.
+	pub fn vicinity(&self) -> &MemoryVicinity {
+		&self.vicinity
+	}
+
+	/// Move to the next block after executing one, recording the hash of the
+	/// executed block for `BLOCKHASH`. The vicinity is copied the first time.
+	pub fn push_block(&mut self, hash: H256) {
+		self.vicinity.to_mut().push_block(hash);
+	}
+
 	/// Get the underlying `BTreeMap` storing the state.
 	pub fn state(&self) -> &BTreeMap<H160, MemoryAccount> {
 		&self.state
@@ -70,16 +119,7 @@ impl<'vicinity> MemoryBackend<'vicinity> {
 impl<'vicinity> Backend for MemoryBackend<'vicinity> {
 	fn gas_price(&self) -> U256 { self.vicinity.gas_price }
 	fn origin(&self) -> H160 { self.vicinity.origin }
-	fn block_hash(&self, number: U256) -> H256 {
-		if number >= self.vicinity.block_number ||
-			self.vicinity.block_number - number - U256::one() >= U256::from(self.vicinity.block_hashes.len())
-		{
-			H256::default()
-		} else {
-			let index = (self.vicinity.block_number - number - U256::one()).as_usize();
-			self.vicinity.block_hashes[index]
-		}
-	}
+	fn block_hash(&self, number: U256) -> H256 { self.vicinity.block_hash(number) }
 	fn block_number(&self) -> U256 { self.vicinity.block_number }
 	fn block_coinbase(&self) -> H160 { self.vicinity.block_coinbase }
 	fn block_timestamp(&self) -> U256 { self.vicinity.block_timestamp }
@@ -129,39 +169,8 @@ impl<'vicinity> ApplyBackend for MemoryBackend<'vicinity> {
 				Apply::Modify {
 					address, basic, code, storage, reset_storage,
 				} => {
-					let is_empty = {
-						let account = self.state.entry(address).or_insert(Default::default());
-						account.balance = basic.balance;
-						account.nonce = basic.nonce;
-						if let Some(code) = code {
-							account.code = code;
-						}
-
-						if reset_storage {
-							account.storage = BTreeMap::new();
-						}
-
-						let zeros = account.storage.iter()
-							.filter(|(_, v)| v == &&H256::default())
-							.map(|(k, _)| k.clone())
-							.collect::<Vec<H256>>();
-
-						for zero in zeros {
-							account.storage.remove(&zero);
-						}
-
-						for (index, value) in storage {
-							if value == H256::default() {
-								account.storage.remove(&index);
-							} else {
-								account.storage.insert(index, value);
-							}
-						}
-
-						account.balance == U256::zero() &&
-							account.nonce == U256::zero() &&
-							account.code.len() == 0
-					};
+					let account = self.state.entry(address).or_insert(Default::default());
+					let is_empty = modify_account(account, basic, code, storage, reset_storage);
 
 					if is_empty && delete_empty {
 						self.state.remove(&address);
@@ -180,3 +189,44 @@ impl<'vicinity> ApplyBackend for MemoryBackend<'vicinity> {
 		}
 	}
 }
+
+/// Apply an `Apply::Modify` to an account, returning whether the account is
+/// empty afterwards.
+pub(super) fn modify_account<I: IntoIterator<Item=(H256, H256)>>(
+	account: &mut MemoryAccount,
+	basic: Basic,
+	code: Option<Vec<u8>>,
+	storage: I,
+	reset_storage: bool,
+) -> bool {
+	account.balance = basic.balance;
+	account.nonce = basic.nonce;
+	if let Some(code) = code {
+		account.code = code;
+	}
+
+	if reset_storage {
+		account.storage = BTreeMap::new();
+	}
+
+	let zeros = account.storage.iter()
+		.filter(|(_, v)| v == &&H256::default())
+		.map(|(k, _)| k.clone())
+		.collect::<Vec<H256>>();
+
+	for zero in zeros {
+		account.storage.remove(&zero);
+	}
+
+	for (index, value) in storage {
+		if value == H256::default() {
+			account.storage.remove(&index);
+		} else {
+			account.storage.insert(index, value);
+		}
+	}
+
+	account.balance == U256::zero() &&
+		account.nonce == U256::zero() &&
+		account.code.len() == 0
+}
@@ -3,11 +3,24 @@
 //! Backends store state information of the VM, and exposes it to runtime.
 
 mod memory;
+mod withdrawal;
+mod irregular;
+mod header;
+mod counting;
+mod layered;
+mod committing;
 
 pub use self::memory::{MemoryBackend, MemoryVicinity, MemoryAccount};
+pub use self::withdrawal::{Withdrawal, process_withdrawals};
+pub use self::irregular::{IrregularChange, IrregularChangeError, apply_irregular_changes};
+pub use self::header::{HeaderBackend, StateProvider};
+pub use self::counting::{CountingBackend, AccessStats, AccessCount};
+pub use self::layered::LayeredBackend;
+pub use self::committing::{CommittingBackend, AccountMismatch};
 
 use alloc::vec::Vec;
 use primitive_types::{H160, H256, U256};
+use crate::code_chunk;
 
 /// Basic account information.
 #[derive(Clone, Eq, PartialEq, Debug, Default)]
@@ -73,6 +86,11 @@ pub trait Backend {
 	fn basic(&self, address: H160) -> Basic;
 	/// Get account code.
 	fn code(&self, address: H160) -> Vec<u8>;
+	/// Get `len` bytes of the account code starting at `offset`, truncated at
+	/// the end of the code.
+	fn code_chunk(&self, address: H160, offset: usize, len: usize) -> Vec<u8> {
+		code_chunk(&self.code(address), offset, len)
+	}
 	/// Get storage value of address at index.
 	fn storage(&self, address: H160, index: H256) -> H256;
 	/// Get original storage value of address at index, if available.
@@ -0,0 +1,60 @@
+use alloc::vec::Vec;
+use primitive_types::{H160, H256, U256};
+use super::{Apply, ApplyBackend, Backend, Basic, Log};
+
+/// Number of wei in one Gwei.
+const GWEI: u64 = 1_000_000_000;
+
+/// Validator withdrawal, as introduced by EIP-4895.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+#[cfg_attr(feature = "with-codec", derive(codec::Encode, codec::Decode))]
+#[cfg_attr(feature = "with-serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Withdrawal {
+	/// Monotonically increasing withdrawal index.
+	pub index: u64,
+	/// Index of the withdrawing validator.
+	pub validator_index: u64,
+	/// Recipient of the withdrawn amount.
+	pub address: H160,
+	/// Withdrawn amount, in Gwei.
+	pub amount: u64,
+}
+
+impl Withdrawal {
+	/// Withdrawn amount, in wei.
+	pub fn amount_wei(&self) -> U256 {
+		U256::from(self.amount) * U256::from(GWEI)
+	}
+}
+
+/// Credit the withdrawals to their recipients, in order.
+///
+/// Withdrawals are processed after all transactions of a block. They are
+/// not transactions: they consume no gas, emit no logs and cannot fail. Zero
+/// amount withdrawals leave the state untouched.
+pub fn process_withdrawals<B: Backend + ApplyBackend>(
+	backend: &mut B,
+	withdrawals: &[Withdrawal],
+) {
+	for withdrawal in withdrawals {
+		if withdrawal.amount == 0 {
+			continue
+		}
+
+		let basic = backend.basic(withdrawal.address);
+		backend.apply(
+			Some(Apply::Modify {
+				address: withdrawal.address,
+				basic: Basic {
+					balance: basic.balance.saturating_add(withdrawal.amount_wei()),
+					nonce: basic.nonce,
+				},
+				code: None,
+				storage: Vec::<(H256, H256)>::new(),
+				reset_storage: false,
+			}),
+			Vec::<Log>::new(),
+			false,
+		);
+	}
+}
@@ -0,0 +1,91 @@
+//! Differential execution of a transaction under two configs.
+//!
+//! Useful when auditing a fork transition: the same transaction is executed
+//! against clones of the same pre-state, and divergences in exit reason, gas
+//! and post-state are reported.
+
+use alloc::collections::BTreeMap;
+use primitive_types::H160;
+use crate::Config;
+use crate::backend::{MemoryVicinity, MemoryAccount};
+use crate::state_test::{StateTestTransaction, StateTestOutcome};
+
+/// Difference between two states. Accounts equal in both are omitted.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct StateDiff {
+	/// Diverging accounts, with their value on the left and on the right.
+	/// `None` means the account does not exist on that side.
+	pub accounts: BTreeMap<H160, (Option<MemoryAccount>, Option<MemoryAccount>)>,
+}
+
+impl StateDiff {
+	/// Compute the difference between two states.
+	pub fn between(
+		left: &BTreeMap<H160, MemoryAccount>,
+		right: &BTreeMap<H160, MemoryAccount>,
+	) -> Self {
+		let mut accounts = BTreeMap::new();
+
+		for (address, account) in left {
+			if right.get(address) != Some(account) {
+				accounts.insert(*address, (Some(account.clone()), right.get(address).cloned()));
+			}
+		}
+		for (address, account) in right {
+			if !left.contains_key(address) {
+				accounts.insert(*address, (None, Some(account.clone())));
+			}
+		}
+
+		Self { accounts }
+	}
+
+	/// Whether both states are equal.
+	pub fn is_empty(&self) -> bool {
+		self.accounts.is_empty()
+	}
+}
+
+/// Report of a differential execution.
+#[derive(Clone, Debug)]
+pub struct DifferentialReport {
+	/// Outcome under the left config.
+	pub left: StateTestOutcome,
+	/// Outcome under the right config.
+	pub right: StateTestOutcome,
+	/// Difference between the post-states.
+	pub state_diff: StateDiff,
+}
+
+impl DifferentialReport {
+	/// Whether the exit reasons differ.
+	pub fn exit_diverges(&self) -> bool {
+		self.left.exit_reason != self.right.exit_reason
+	}
+
+	/// Whether the used gas differs.
+	pub fn gas_diverges(&self) -> bool {
+		self.left.used_gas != self.right.used_gas
+	}
+
+	/// Whether the executions diverge in any way.
+	pub fn diverges(&self) -> bool {
+		self.exit_diverges() || self.gas_diverges() || !self.state_diff.is_empty()
+	}
+}
+
+/// Execute `transaction` on top of `pre` under both configs and compare the
+/// results.
+pub fn execute_differential(
+	left: &Config,
+	right: &Config,
+	vicinity: &MemoryVicinity,
+	pre: &BTreeMap<H160, MemoryAccount>,
+	transaction: &StateTestTransaction,
+) -> DifferentialReport {
+	let left = transaction.execute(left, vicinity, pre.clone());
+	let right = transaction.execute(right, vicinity, pre.clone());
+	let state_diff = StateDiff::between(&left.post, &right.post);
+
+	DifferentialReport { left, right, state_diff }
+}
@@ -0,0 +1,344 @@
+//! ERC-4337 user operation simulation.
+//!
+//! Runs the validation and execution phases of a user operation against a
+//! `MemoryBackend`, each phase with its own gas limit. The validation phase is
+//! checked against the ERC-7562 rules on banned opcodes and storage access,
+//! and its return value is decoded as the `validationData` of ERC-4337.
+//!
+//! The account is called directly from the entry point address, and ABI
+//! encoding of those calls is left to the caller. Factories and paymasters are
+//! not simulated, and every entity other than the sender is treated as
+//! unstaked.
+
+use alloc::{vec::Vec, collections::BTreeSet};
+use primitive_types::{H160, H256, U256};
+use crate::{Config, ExitReason, Opcode};
+use crate::backend::{Apply, ApplyBackend, Backend, MemoryBackend};
+use crate::executor::{StackExecutor, MemoryStackState, StackSubstateMetadata};
+use crate::tracing::runtime::{self, Event, EventListener};
+
+/// Opcodes forbidden during validation, because their result can change
+/// between simulation and inclusion.
+const BANNED_OPCODES: [Opcode; 15] = [
+	Opcode::GASPRICE, Opcode::GASLIMIT, Opcode::DIFFICULTY, Opcode::TIMESTAMP, Opcode::NUMBER,
+	Opcode::BLOCKHASH, Opcode::COINBASE, Opcode::ORIGIN, Opcode::BALANCE, Opcode::SELFBALANCE,
+	Opcode::CREATE, Opcode::CREATE2, Opcode::SUICIDE, Opcode::INVALID, Opcode(0x48), // BASEFEE
+];
+
+/// Number of slots following an associated slot that are associated too, so
+/// that structs stored in a mapping keyed by the sender are accessible.
+const ASSOCIATED_SLOTS: u64 = 128;
+
+/// User operation, with its calls to the account already ABI-encoded.
+#[derive(Clone, Debug)]
+pub struct UserOperation {
+	/// Account the operation is for.
+	pub sender: H160,
+	/// Entry point calling the account.
+	pub entry_point: H160,
+	/// Encoded `validateUserOp` call.
+	pub validation_data: Vec<u8>,
+	/// Gas limit of the validation phase.
+	pub verification_gas_limit: u64,
+	/// Encoded call executed by the account.
+	pub call_data: Vec<u8>,
+	/// Gas limit of the execution phase.
+	pub call_gas_limit: u64,
+}
+
+/// Rule broken during validation.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Violation {
+	/// Banned opcode executed by the given contract. `GAS` is only reported
+	/// when not immediately followed by a call.
+	BannedOpcode {
+		/// Executing contract.
+		address: H160,
+		/// Opcode executed.
+		opcode: Opcode,
+	},
+	/// Access to storage that is neither the sender's own nor associated with
+	/// the sender.
+	StorageAccess {
+		/// Owner of the storage.
+		address: H160,
+		/// Slot accessed.
+		index: H256,
+	},
+	/// Validation returned something else than a `validationData` word.
+	InvalidReturnData,
+	/// Validation reported a signature failure.
+	SignatureFailure,
+	/// Validation requires an aggregator, which is not simulated.
+	Aggregator(H160),
+	/// The block timestamp is outside of the validity range returned by the
+	/// validation.
+	Expired {
+		/// First valid timestamp.
+		valid_after: u64,
+		/// Last valid timestamp, zero for no limit.
+		valid_until: u64,
+	},
+}
+
+/// `validationData` returned by `validateUserOp`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ValidationData {
+	/// Aggregator, zero for none and one for a signature failure.
+	pub aggregator: H160,
+	/// Last valid timestamp, zero for no limit.
+	pub valid_until: u64,
+	/// First valid timestamp.
+	pub valid_after: u64,
+}
+
+impl ValidationData {
+	/// Decode the return value of `validateUserOp`. `None` if it is not a
+	/// single word.
+	pub fn decode(output: &[u8]) -> Option<Self> {
+		if output.len() != 32 {
+			return None
+		}
+
+		let mut valid_after = [0u8; 8];
+		let mut valid_until = [0u8; 8];
+		valid_after[2..].copy_from_slice(&output[..6]);
+		valid_until[2..].copy_from_slice(&output[6..12]);
+		Some(Self {
+			aggregator: H160::from_slice(&output[12..]),
+			valid_until: u64::from_be_bytes(valid_until),
+			valid_after: u64::from_be_bytes(valid_after),
+		})
+	}
+
+	/// Rule broken by this validation data at `timestamp`, if any.
+	fn violation(&self, timestamp: U256) -> Option<Violation> {
+		if self.aggregator == H160::from_low_u64_be(1) {
+			return Some(Violation::SignatureFailure)
+		}
+		if self.aggregator != H160::zero() {
+			return Some(Violation::Aggregator(self.aggregator))
+		}
+
+		let too_early = timestamp < U256::from(self.valid_after);
+		let too_late = self.valid_until != 0 && timestamp > U256::from(self.valid_until);
+		if too_early || too_late {
+			return Some(Violation::Expired {
+				valid_after: self.valid_after,
+				valid_until: self.valid_until,
+			})
+		}
+
+		None
+	}
+}
+
+/// Outcome of one phase of a user operation.
+#[derive(Clone, Debug)]
+pub struct PhaseOutcome {
+	/// Exit reason of the call to the account.
+	pub exit_reason: ExitReason,
+	/// Return value of the call to the account.
+	pub output: Vec<u8>,
+	/// Gas used by the phase.
+	pub used_gas: u64,
+}
+
+/// Outcome of a user operation simulation.
+#[derive(Clone, Debug)]
+pub struct UserOperationSimulation {
+	/// Validation phase.
+	pub validation: PhaseOutcome,
+	/// Rules broken during validation.
+	pub violations: Vec<Violation>,
+	/// Execution phase, run only if the validation succeeded without
+	/// violations.
+	pub execution: Option<PhaseOutcome>,
+}
+
+impl UserOperationSimulation {
+	/// Whether the validation succeeded without violations.
+	pub fn is_valid(&self) -> bool {
+		self.validation.exit_reason.is_succeed() && self.violations.is_empty()
+	}
+}
+
+/// Simulate `operation` on top of `backend`. The changes of each phase are
+/// applied to the backend, except those of a validation that is not valid.
+pub fn simulate_user_operation(
+	backend: &mut MemoryBackend,
+	config: &Config,
+	operation: &UserOperation,
+) -> UserOperationSimulation {
+	let mut inspector = ValidationInspector::new(config.clone(), operation.sender);
+	let (validation, changes) = runtime::using(&mut inspector, || run_phase(
+		backend,
+		config,
+		operation.entry_point,
+		operation.sender,
+		operation.validation_data.clone(),
+		operation.verification_gas_limit,
+	));
+	let mut violations = inspector.violations;
+	if validation.exit_reason.is_succeed() {
+		let violation = match ValidationData::decode(&validation.output) {
+			Some(data) => data.violation(backend.block_timestamp()),
+			None => Some(Violation::InvalidReturnData),
+		};
+		violations.extend(violation);
+	}
+
+	let mut simulation = UserOperationSimulation { validation, violations, execution: None };
+	if !simulation.is_valid() {
+		return simulation
+	}
+	changes.apply_to(backend, config);
+
+	let (execution, changes) = run_phase(
+		backend,
+		config,
+		operation.entry_point,
+		operation.sender,
+		operation.call_data.clone(),
+		operation.call_gas_limit,
+	);
+	changes.apply_to(backend, config);
+	simulation.execution = Some(execution);
+
+	simulation
+}
+
+/// Changes of a phase, not yet applied.
+struct PhaseChanges {
+	values: Vec<Apply<Vec<(H256, H256)>>>,
+	logs: Vec<crate::backend::Log>,
+}
+
+impl PhaseChanges {
+	fn apply_to(self, backend: &mut MemoryBackend, config: &Config) {
+		backend.apply(self.values, self.logs, config.clear_empty_touched_accounts);
+	}
+}
+
+fn no_intrinsic_gas(_config: &Config, _is_create: bool, _zero: usize, _non_zero: usize) -> (u64, u64) {
+	(0, 0)
+}
+
+fn run_phase(
+	backend: &MemoryBackend,
+	config: &Config,
+	entry_point: H160,
+	sender: H160,
+	data: Vec<u8>,
+	gas_limit: u64,
+) -> (PhaseOutcome, PhaseChanges) {
+	// Phases are calls made by the entry point, not transactions.
+	let config = Config { intrinsic_gas: Some(no_intrinsic_gas), ..config.clone() };
+	let entry_point_nonce = backend.basic(entry_point).nonce;
+
+	let metadata = StackSubstateMetadata::new(gas_limit, &config);
+	let state = MemoryStackState::new(metadata, backend);
+	let mut executor = StackExecutor::new(state, &config);
+
+	let (exit_reason, output) = executor.transact_call(
+		entry_point, sender, U256::zero(), data, gas_limit,
+	);
+	let used_gas = executor.used_gas();
+
+	let (values, logs) = executor.into_state().deconstruct();
+	let values = values.into_iter().map(|apply| match apply {
+		Apply::Modify { address, mut basic, code, storage, reset_storage } => {
+			if address == entry_point {
+				basic.nonce = entry_point_nonce;
+			}
+			let storage = storage.into_iter().collect();
+			Apply::Modify { address, basic, code, storage, reset_storage }
+		},
+		Apply::Delete { address } => Apply::Delete { address },
+	}).collect();
+	let logs = logs.into_iter().collect();
+
+	(
+		PhaseOutcome { exit_reason, output, used_gas },
+		PhaseChanges { values, logs },
+	)
+}
+
+/// Runtime listener checking validation rules.
+struct ValidationInspector {
+	/// Config of the simulation, for its Keccak-256 override.
+	config: Config,
+	sender: H160,
+	/// Slots of mappings keyed by the sender, from the `SHA3` preimages seen.
+	associated: BTreeSet<H256>,
+	/// Contract that executed `GAS` on the previous step.
+	pending_gas: Option<H160>,
+	violations: Vec<Violation>,
+}
+
+impl ValidationInspector {
+	fn new(config: Config, sender: H160) -> Self {
+		Self {
+			config,
+			sender,
+			associated: BTreeSet::new(),
+			pending_gas: None,
+			violations: Vec::new(),
+		}
+	}
+
+	fn is_associated(&self, index: H256) -> bool {
+		self.associated.range(..=index).next_back().map(|slot| {
+			let offset = U256::from_big_endian(&index[..]) - U256::from_big_endian(&slot[..]);
+			offset <= U256::from(ASSOCIATED_SLOTS)
+		}).unwrap_or(false)
+	}
+
+	fn storage_access(&mut self, address: H160, index: H256) {
+		if address != self.sender && !self.is_associated(index) {
+			self.violations.push(Violation::StorageAccess { address, index });
+		}
+	}
+}
+
+impl EventListener for ValidationInspector {
+	fn event(&mut self, event: Event) {
+		match event {
+			Event::Step { context, opcode, stack, memory, .. } => {
+				if let Some(address) = self.pending_gas.take() {
+					let is_call = [
+						Opcode::CALL, Opcode::CALLCODE, Opcode::DELEGATECALL, Opcode::STATICCALL,
+					].contains(&opcode);
+					if !is_call {
+						self.violations.push(Violation::BannedOpcode { address, opcode: Opcode::GAS });
+					}
+				}
+
+				if opcode == Opcode::GAS {
+					self.pending_gas = Some(context.address);
+				} else if BANNED_OPCODES.contains(&opcode) {
+					self.violations.push(Violation::BannedOpcode { address: context.address, opcode });
+				} else if opcode == Opcode::SHA3 {
+					// Keys of mappings are hashed as `key ++ slot`, with the
+					// key padded to a word.
+					let (offset, len) = match (stack.peek(0), stack.peek(1)) {
+						(Ok(offset), Ok(len)) =>
+							(U256::from_big_endian(&offset[..]), U256::from_big_endian(&len[..])),
+						_ => return,
+					};
+					if len < U256::from(32) || len > U256::from(1024) || offset > U256::from(memory.len()) {
+						return
+					}
+
+					let preimage = memory.get(offset.as_usize(), len.as_usize());
+					if preimage[..32] == H256::from(self.sender)[..] {
+						self.associated.insert(self.config.keccak256(&preimage));
+					}
+				}
+			},
+			Event::SLoad { address, index, .. } | Event::SStore { address, index, .. } =>
+				self.storage_access(address, index),
+			Event::StepResult { .. } | Event::StackUnderflow { .. } => (),
+		}
+	}
+}
@@ -5,4 +5,4 @@
 
 mod stack;
 
-pub use self::stack::{StackExecutor, MemoryStackState, StackState, StackSubstateMetadata, StackExitKind, PrecompileOutput};
+pub use self::stack::{StackExecutor, MemoryStackState, MemoryStackSubstate, StackState, DepositState, StackSubstateMetadata, StackExitKind, PrecompileOutput, Watchdog, CustomOpcodes, ExecutionLogs, LogFilter, LogListener, MemoryStackExecutor, ExecutionSummary, ErrorContext, TransactionOutcome, CallArgs, CreateArgs, FeeDistribution, FeeSettlement, BaseFeeRecipient, FeeOverflow};
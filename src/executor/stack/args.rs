@@ -0,0 +1,114 @@
+use alloc::vec::Vec;
+use primitive_types::{H160, H256, U256};
+
+/// Arguments of a call transaction, built with `CallArgs::new` and the
+/// setters below.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct CallArgs {
+	/// Caller of the transaction.
+	pub caller: H160,
+	/// Called address.
+	pub address: H160,
+	/// Transferred value.
+	pub value: U256,
+	/// Call data.
+	pub data: Vec<u8>,
+	/// Gas limit.
+	pub gas_limit: u64,
+	/// Origin seen by `ORIGIN`, if not the backend one.
+	pub origin: Option<H160>,
+	/// Code installed at an address for the duration of the call.
+	pub pending_code: Option<(H160, Vec<u8>)>,
+}
+
+impl CallArgs {
+	/// Call of `address` by `caller`, without value nor data.
+	pub fn new(caller: H160, address: H160, gas_limit: u64) -> Self {
+		Self {
+			caller,
+			address,
+			value: U256::zero(),
+			data: Vec::new(),
+			gas_limit,
+			origin: None,
+			pending_code: None,
+		}
+	}
+
+	/// Set the transferred value.
+	pub fn value(mut self, value: U256) -> Self {
+		self.value = value;
+		self
+	}
+
+	/// Set the call data.
+	pub fn data(mut self, data: Vec<u8>) -> Self {
+		self.data = data;
+		self
+	}
+
+	/// Override the transaction origin.
+	pub fn origin(mut self, origin: H160) -> Self {
+		self.origin = Some(origin);
+		self
+	}
+
+	/// Install `code` at `address` for the duration of the call.
+	pub fn pending_code(mut self, address: H160, code: Vec<u8>) -> Self {
+		self.pending_code = Some((address, code));
+		self
+	}
+}
+
+/// Arguments of a create transaction, built with `CreateArgs::new` and the
+/// setters below.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct CreateArgs {
+	/// Caller of the transaction.
+	pub caller: H160,
+	/// Transferred value.
+	pub value: U256,
+	/// Init code.
+	pub init_code: Vec<u8>,
+	/// Gas limit.
+	pub gas_limit: u64,
+	/// Salt of a `CREATE2`-style address. `None` derives the address from
+	/// the caller nonce.
+	pub salt: Option<H256>,
+	/// Origin seen by `ORIGIN`, if not the backend one.
+	pub origin: Option<H160>,
+}
+
+impl CreateArgs {
+	/// Create of `init_code` by `caller`, without value.
+	pub fn new(caller: H160, init_code: Vec<u8>, gas_limit: u64) -> Self {
+		Self {
+			caller,
+			value: U256::zero(),
+			init_code,
+			gas_limit,
+			salt: None,
+			origin: None,
+		}
+	}
+
+	/// Set the transferred value.
+	pub fn value(mut self, value: U256) -> Self {
+		self.value = value;
+		self
+	}
+
+	/// Derive the created address from the salt.
+	pub fn salt(mut self, salt: H256) -> Self {
+		self.salt = Some(salt);
+		self
+	}
+
+	/// Override the transaction origin.
+	pub fn origin(mut self, origin: H160) -> Self {
+		self.origin = Some(origin);
+		self
+	}
+}
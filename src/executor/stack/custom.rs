@@ -0,0 +1,26 @@
+use alloc::vec::Vec;
+use primitive_types::H256;
+use crate::{ExitError, Opcode, Context, CustomOpcode};
+
+/// Host-defined opcodes of a stack executor.
+///
+/// Opcodes unknown to the EVM are looked up here. The executor charges the
+/// declared gas, pops the declared inputs and pushes the returned outputs, so
+/// host-defined opcodes are metered and traced like any other.
+pub trait CustomOpcodes<S> {
+	/// Signature of `opcode`, if it is defined by the host. Signatures of
+	/// opcodes known to the EVM are ignored, even where the fork or a static
+	/// context disables them.
+	fn signature(&self, opcode: Opcode) -> Option<CustomOpcode>;
+
+	/// Execute `opcode` with its inputs, top of the stack first. Exactly
+	/// `signature.outputs` values must be returned, the last one ends up on
+	/// top of the stack.
+	fn execute(
+		&mut self,
+		opcode: Opcode,
+		context: &Context,
+		inputs: Vec<H256>,
+		state: &mut S,
+	) -> Result<Vec<H256>, ExitError>;
+}
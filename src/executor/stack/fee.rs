@@ -0,0 +1,136 @@
+use alloc::borrow::Cow;
+use alloc::collections::BTreeMap;
+use primitive_types::{H160, U256};
+use crate::ExitError;
+use super::DepositState;
+
+/// Where the base fee paid by a transaction goes.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BaseFeeRecipient {
+	/// The base fee is burnt, as specified by EIP-1559.
+	Burn,
+	/// The base fee is credited to the given account, such as a treasury.
+	Address(H160),
+}
+
+/// What to do when crediting a fee overflows the balance of its recipient.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FeeOverflow {
+	/// Cap the balance at `U256::MAX`.
+	Saturate,
+	/// Fail the settlement, leaving the state untouched.
+	Fail,
+}
+
+/// Amounts credited by `FeeDistribution::settle`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct FeeSettlement {
+	/// Refunded to the caller for the unused gas.
+	pub refund: U256,
+	/// Credited to the coinbase.
+	pub tip: U256,
+	/// Burnt or credited to the base fee recipient.
+	pub base_fee: U256,
+}
+
+/// End of transaction fee transfers.
+///
+/// The caller is expected to have prepaid `gas_limit * (base_fee +
+/// priority_fee)` before execution. Settling refunds the unused gas to the
+/// caller, credits the priority fee of the used gas to the coinbase, and
+/// burns the base fee of the used gas or credits it to a recipient.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FeeDistribution {
+	/// Base fee per gas.
+	pub base_fee: U256,
+	/// Priority fee per gas, already capped by the max fee of the
+	/// transaction.
+	pub priority_fee: U256,
+	/// Where the base fee goes.
+	pub base_fee_recipient: BaseFeeRecipient,
+	/// Behavior on balance overflow.
+	pub overflow: FeeOverflow,
+}
+
+impl FeeDistribution {
+	/// Fee distribution burning the base fee and saturating balances.
+	pub fn new(base_fee: U256, priority_fee: U256) -> Self {
+		Self {
+			base_fee,
+			priority_fee,
+			base_fee_recipient: BaseFeeRecipient::Burn,
+			overflow: FeeOverflow::Saturate,
+		}
+	}
+
+	/// Set where the base fee goes.
+	pub fn base_fee_recipient(mut self, recipient: BaseFeeRecipient) -> Self {
+		self.base_fee_recipient = recipient;
+		self
+	}
+
+	/// Set the behavior on balance overflow.
+	pub fn overflow(mut self, overflow: FeeOverflow) -> Self {
+		self.overflow = overflow;
+		self
+	}
+
+	/// Settle the fees of a transaction of `caller` with the given gas limit,
+	/// which used `used_gas` after refunds.
+	pub fn settle<'config, S: DepositState<'config>>(
+		&self,
+		state: &mut S,
+		caller: H160,
+		coinbase: H160,
+		gas_limit: u64,
+		used_gas: u64,
+	) -> Result<FeeSettlement, ExitError> {
+		let fee = |gas: u64, price: U256| {
+			price.checked_mul(U256::from(gas))
+				.ok_or(ExitError::Other(Cow::Borrowed("fee overflow")))
+		};
+
+		let price = self.base_fee.checked_add(self.priority_fee)
+			.ok_or(ExitError::Other(Cow::Borrowed("fee overflow")))?;
+		let settlement = FeeSettlement {
+			refund: fee(gas_limit.saturating_sub(used_gas), price)?,
+			tip: fee(used_gas, self.priority_fee)?,
+			base_fee: fee(used_gas, self.base_fee)?,
+		};
+
+		let base_fee_recipient = match self.base_fee_recipient {
+			BaseFeeRecipient::Burn => None,
+			BaseFeeRecipient::Address(address) => Some(address),
+		};
+
+		// Recipients may coincide, so credits are summed per account.
+		let mut credits = BTreeMap::<H160, U256>::new();
+		for (address, value) in [
+			(Some(caller), settlement.refund),
+			(Some(coinbase), settlement.tip),
+			(base_fee_recipient, settlement.base_fee),
+		] {
+			if let Some(address) = address {
+				let total = credits.entry(address).or_default();
+				*total = total.saturating_add(value);
+			}
+		}
+
+		if self.overflow == FeeOverflow::Fail {
+			for (address, value) in &credits {
+				let balance = state.basic(*address).balance;
+				if *value == U256::MAX || balance.checked_add(*value).is_none() {
+					return Err(ExitError::Other(Cow::Borrowed("balance overflow")))
+				}
+			}
+		}
+
+		for (address, value) in credits {
+			if !value.is_zero() {
+				state.deposit(address, value);
+			}
+		}
+
+		Ok(settlement)
+	}
+}
@@ -0,0 +1,57 @@
+use alloc::{boxed::Box, vec::Vec};
+use primitive_types::{H160, H256};
+use ethereum::Log;
+
+/// Callback invoked on each log as it is emitted.
+pub type LogListener = Box<dyn FnMut(&Log)>;
+
+/// Filter of logs, following the semantics of `eth_getLogs`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct LogFilter {
+	/// Accepted emitters. Empty accepts any address.
+	pub addresses: Vec<H160>,
+	/// Accepted topic at each position. `None` accepts any topic, positions
+	/// past the end of the filter are not checked.
+	pub topics: Vec<Option<H256>>,
+}
+
+impl LogFilter {
+	/// Whether the log passes the filter.
+	pub fn matches(&self, log: &Log) -> bool {
+		if !self.addresses.is_empty() && !self.addresses.contains(&log.address) {
+			return false
+		}
+
+		self.topics.iter().enumerate().all(|(i, topic)| match topic {
+			Some(topic) => log.topics.get(i) == Some(topic),
+			None => true,
+		})
+	}
+}
+
+/// Logs of an execution, in emission order.
+#[derive(Clone, Debug, Default)]
+pub struct ExecutionLogs(pub Vec<Log>);
+
+impl ExecutionLogs {
+	/// Logs emitted by `address`.
+	pub fn by_address(&self, address: H160) -> impl Iterator<Item=&Log> {
+		self.0.iter().filter(move |log| log.address == address)
+	}
+
+	/// Logs with `topic` as their first topic, usually the event signature.
+	pub fn by_signature(&self, topic: H256) -> impl Iterator<Item=&Log> {
+		self.0.iter().filter(move |log| log.topics.first() == Some(&topic))
+	}
+
+	/// Logs passing `filter`.
+	pub fn filter<'a>(&'a self, filter: &'a LogFilter) -> impl Iterator<Item=&'a Log> {
+		self.0.iter().filter(move |log| filter.matches(log))
+	}
+}
+
+impl core::iter::FromIterator<Log> for ExecutionLogs {
+	fn from_iter<I: IntoIterator<Item=Log>>(iter: I) -> Self {
+		Self(iter.into_iter().collect())
+	}
+}
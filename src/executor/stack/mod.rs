@@ -1,26 +1,40 @@
 mod state;
-
-pub use self::state::{MemoryStackSubstate, MemoryStackState, StackState};
-
-use core::{convert::Infallible, cmp::min};
-use alloc::{rc::Rc, vec::Vec};
+mod watchdog;
+mod custom;
+mod logs;
+mod args;
+mod fee;
+
+pub use self::state::{MemoryStackSubstate, MemoryStackState, StackState, DepositState};
+pub use self::watchdog::Watchdog;
+pub use self::custom::CustomOpcodes;
+pub use self::logs::{ExecutionLogs, LogFilter, LogListener};
+pub use self::args::{CallArgs, CreateArgs};
+pub use self::fee::{FeeDistribution, FeeSettlement, BaseFeeRecipient, FeeOverflow};
+
+use core::{convert::Infallible, cmp::{min, max}};
+use alloc::{rc::Rc, vec::Vec, boxed::Box, collections::BTreeMap};
 use primitive_types::{U256, H256, H160};
-use sha3::{Keccak256, Digest};
-use crate::{ExitError, Stack, Opcode, Capture, Handler, Transfer,
-			Context, CreateScheme, Runtime, ExitReason, ExitSucceed, Config};
+use crate::{ExitError, ExitFatal, Stack, Opcode, Capture, Handler, Transfer,
+			Context, CreateScheme, Runtime, ExitReason, ExitSucceed, Config, ForkSchedule,
+			CustomOpcode, Frame, code_chunk};
 use ethereum::Log;
 use crate::gasometer::{self, Gasometer};
+use crate::backend::{Apply, Backend, MemoryBackend};
 
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum StackExitKind {
 	Succeeded,
 	Reverted,
 	Failed,
 }
 
+#[derive(Clone)]
 pub struct StackSubstateMetadata<'config> {
 	gasometer: Gasometer<'config>,
 	is_static: bool,
 	depth: Option<usize>,
+	logs: usize,
 }
 
 impl<'config> StackSubstateMetadata<'config> {
@@ -32,12 +46,14 @@ impl<'config> StackSubstateMetadata<'config> {
 			gasometer: Gasometer::new(gas_limit, config),
 			is_static: false,
 			depth: None,
+			logs: 0,
 		}
 	}
 
 	pub fn swallow_commit(&mut self, other: Self) -> Result<(), ExitError> {
 		self.gasometer.record_stipend(other.gasometer.gas())?;
 		self.gasometer.record_refund(other.gasometer.refunded_gas())?;
+		self.logs += other.logs;
 
 		Ok(())
 	}
@@ -53,14 +69,20 @@ impl<'config> StackSubstateMetadata<'config> {
 	}
 
 	pub fn spit_child(&self, gas_limit: u64, is_static: bool) -> Self {
-		Self {
-			gasometer: Gasometer::new(gas_limit, self.gasometer.config()),
+		let mut gasometer = Gasometer::new(gas_limit, self.gasometer.config());
+		gasometer.set_external_cost_oracle(self.gasometer.external_cost_oracle());
+
+		let mut metadata = Self {
+			gasometer,
 			is_static: is_static || self.is_static,
 			depth: match self.depth {
 				None => Some(0),
 				Some(n) => Some(n + 1),
 			},
-		}
+			logs: 0,
+		};
+		metadata.gasometer.set_frame(metadata.frame());
+		metadata
 	}
 
 	pub fn gasometer(&self) -> &Gasometer<'config> {
@@ -78,6 +100,62 @@ impl<'config> StackSubstateMetadata<'config> {
 	pub fn depth(&self) -> Option<usize> {
 		self.depth
 	}
+
+	/// Frame of this substate.
+	pub fn frame(&self) -> Frame {
+		Frame { depth: self.depth, is_static: self.is_static }
+	}
+
+	/// Number of logs emitted in this substate, including the ones committed
+	/// by its children.
+	pub fn logs(&self) -> usize {
+		self.logs
+	}
+}
+
+/// Location of the error a frame exited with, see
+/// `StackExecutor::last_error`.
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct ErrorContext {
+	/// Error the frame exited with.
+	pub error: ExitError,
+	/// Address whose code was executing. For a create, the address of the
+	/// created contract.
+	pub address: H160,
+	/// Position of the failing opcode, if the error happened while executing
+	/// code. Errors on the deployed code of a create have none.
+	pub position: Option<usize>,
+	/// Failing opcode, if the error happened while executing code.
+	pub opcode: Option<Opcode>,
+}
+
+/// Gas accounting of an execution.
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub struct ExecutionSummary {
+	/// Gas limit of the execution.
+	pub gas_limit: u64,
+	/// Gas used before refunds.
+	pub gas_used_pre_refund: u64,
+	/// Refund accumulated during execution.
+	pub refund_requested: i64,
+	/// Refund applied after the cap of half the used gas.
+	pub refund_applied: u64,
+	/// Gas used after refunds, which is what the sender pays for.
+	pub effective_gas_used: u64,
+}
+
+/// Result of the last transaction of an executor, see
+/// `StackExecutor::finalize`.
+#[derive(Debug, Clone)]
+pub struct TransactionOutcome {
+	/// Exit reason of the transaction.
+	pub exit_reason: ExitReason,
+	/// Return data of a call, or revert data. Empty for successful creates.
+	pub return_data: Vec<u8>,
+	/// Logs emitted so far, in emission order.
+	pub logs: Vec<Log>,
+	/// Changes to apply to the backend.
+	pub changes: Vec<Apply<BTreeMap<H256, H256>>>,
 }
 
 #[derive(Debug, Eq, PartialEq, Clone)]
@@ -96,11 +174,27 @@ pub struct PrecompileOutput {
 ///  * Is static
 type PrecompileFn<S> = fn(H160, &[u8], Option<u64>, &Context, &mut S, bool) -> Option<Result<PrecompileOutput, ExitError>>;
 
+/// Stack-based executor over a `MemoryBackend`, the most common instantiation.
+pub type MemoryStackExecutor<'backend, 'vicinity, 'config> =
+	StackExecutor<'config, MemoryStackState<'backend, 'config, MemoryBackend<'vicinity>>>;
+
 /// Stack-based executor.
 pub struct StackExecutor<'config, S> {
 	config: &'config Config,
+	opcode_costs: Option<&'config gasometer::OpcodeCostTable>,
 	precompile: PrecompileFn<S>,
 	state: S,
+	watchdog: Option<Box<dyn Watchdog>>,
+	custom_opcodes: Option<Box<dyn CustomOpcodes<S>>>,
+	log_listener: Option<LogListener>,
+	origin: Option<H160>,
+	pending_code: BTreeMap<H160, Vec<u8>>,
+	instruction_limit: Option<u64>,
+	instructions: u64,
+	last_error: Option<ErrorContext>,
+	last_result: Option<(ExitReason, Vec<u8>)>,
+	#[cfg(feature = "tracing")]
+	listeners: Option<crate::tracing::Listeners>,
 }
 
 fn no_precompile<S>(
@@ -123,6 +217,18 @@ impl<'config, S: StackState<'config>> StackExecutor<'config, S> {
 		Self::new_with_precompile(state, config, no_precompile)
 	}
 
+	/// Create a new stack-based executor with the config active for the given
+	/// block. The state is created from that config.
+	pub fn for_block<F: FnOnce(&'config Config) -> S>(
+		schedule: &'config ForkSchedule,
+		block_number: U256,
+		timestamp: U256,
+		state: F,
+	) -> Self {
+		let config = schedule.config_at(block_number, timestamp);
+		Self::new(state(config), config)
+	}
+
 	/// Return a reference of the Config.
 	pub fn config(
 		&self
@@ -138,8 +244,159 @@ impl<'config, S: StackState<'config>> StackExecutor<'config, S> {
 	) -> Self {
 		Self {
 			config,
+			opcode_costs: None,
 			precompile,
 			state,
+			watchdog: None,
+			custom_opcodes: None,
+			log_listener: None,
+			origin: None,
+			pending_code: BTreeMap::new(),
+			instruction_limit: None,
+			instructions: 0,
+			last_error: None,
+			last_result: None,
+			#[cfg(feature = "tracing")]
+			listeners: None,
+		}
+	}
+
+	/// Set the table of opcode costs, computed once for the config of the
+	/// executor, to save looking up static costs and config flags on each
+	/// opcode.
+	pub fn set_opcode_cost_table(&mut self, table: Option<&'config gasometer::OpcodeCostTable>) {
+		self.opcode_costs = table;
+	}
+
+	/// Set the watchdog notified of call depth and gas thresholds.
+	pub fn set_watchdog(&mut self, watchdog: Box<dyn Watchdog>) {
+		self.watchdog = Some(watchdog);
+	}
+
+	/// Remove the watchdog and return it.
+	pub fn take_watchdog(&mut self) -> Option<Box<dyn Watchdog>> {
+		self.watchdog.take()
+	}
+
+	/// Set the host-defined opcodes.
+	pub fn set_custom_opcodes(&mut self, custom_opcodes: Box<dyn CustomOpcodes<S>>) {
+		self.custom_opcodes = Some(custom_opcodes);
+	}
+
+	/// Remove the host-defined opcodes and return them.
+	pub fn take_custom_opcodes(&mut self) -> Option<Box<dyn CustomOpcodes<S>>> {
+		self.custom_opcodes.take()
+	}
+
+	/// Set a callback invoked on each log as it is emitted. Logs of frames
+	/// that later revert are reported too, as they happen before the revert.
+	pub fn set_log_listener(&mut self, listener: LogListener) {
+		self.log_listener = Some(listener);
+	}
+
+	/// Remove the log callback and return it.
+	pub fn take_log_listener(&mut self) -> Option<LogListener> {
+		self.log_listener.take()
+	}
+
+	/// Override the transaction origin returned by `ORIGIN`, which otherwise
+	/// comes from the backend. The caller passed to the transact functions is
+	/// left as is, so the origin can differ from it, as when a bundler submits
+	/// user operations.
+	pub fn set_origin(&mut self, origin: Option<H160>) {
+		self.origin = origin;
+	}
+
+	/// Attach event listeners to this executor. Events of its transactions
+	/// are reported to them instead of the listeners installed on the thread,
+	/// so that executors sharing threads can be traced independently.
+	#[cfg(feature = "tracing")]
+	pub fn set_listeners(&mut self, listeners: crate::tracing::Listeners) {
+		self.listeners = Some(listeners);
+	}
+
+	/// Detach the event listeners and return them.
+	#[cfg(feature = "tracing")]
+	pub fn take_listeners(&mut self) -> Option<crate::tracing::Listeners> {
+		self.listeners.take()
+	}
+
+	/// Run `f` with the attached listeners installed. They are detached
+	/// meanwhile, so that nested calls do not install them again. Panic if no
+	/// listeners are attached.
+	#[cfg(feature = "tracing")]
+	fn with_listeners<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
+		let mut listeners = self.listeners.take().expect("Listeners are attached");
+		let result = listeners.using(|| f(self));
+		self.listeners = Some(listeners);
+		result
+	}
+
+	/// Bound the number of instructions executed by each transaction,
+	/// whatever the gas limit. Once it is exceeded, execution aborts with
+	/// `ExitFatal::ExecutionTimeout`. The count of executed instructions is
+	/// reset here and at the start of every transaction.
+	pub fn set_instruction_limit(&mut self, limit: Option<u64>) {
+		self.instruction_limit = limit;
+		self.instructions = 0;
+	}
+
+	/// Number of instructions executed by the last transaction, counted only
+	/// while an instruction limit is set.
+	pub fn instructions(&self) -> u64 {
+		self.instructions
+	}
+
+	/// Location of the error of the last frame that exited with an error
+	/// during the last transaction. Inner frames exit first, so after a
+	/// failed transaction this is the error it failed with. Errors raised
+	/// before any code runs, such as insufficient intrinsic gas, call depth,
+	/// funds, collisions or failing precompiles, have no position. `None` if
+	/// no frame of the last transaction failed.
+	pub fn last_error(&self) -> Option<&ErrorContext> {
+		self.last_error.as_ref()
+	}
+
+	fn record_error(&mut self, error: &ExitError, address: H160, runtime: Option<&Runtime>) {
+		self.last_error = Some(ErrorContext {
+			error: error.clone(),
+			address,
+			position: runtime.and_then(|runtime| runtime.last_position()),
+			opcode: runtime.and_then(|runtime| runtime.last_opcode()),
+		});
+	}
+
+	fn timed_out(&self) -> bool {
+		self.instruction_limit.map_or(false, |limit| self.instructions > limit)
+	}
+
+	fn watch_depth(&mut self) -> Result<(), ExitError> {
+		let depth = self.state.metadata().depth;
+		match (self.watchdog.as_mut(), depth) {
+			(Some(watchdog), Some(depth)) if watchdog.depth_thresholds().contains(&depth) =>
+				watchdog.on_depth(depth),
+			_ => Ok(()),
+		}
+	}
+
+	fn watch_gas(&mut self, gas_before: u64) -> Result<(), ExitError> {
+		let watchdog = match self.watchdog.as_mut() {
+			Some(watchdog) => watchdog,
+			None => return Ok(()),
+		};
+		let percent = match watchdog.low_gas_percent() {
+			Some(percent) => percent,
+			None => return Ok(()),
+		};
+
+		let gasometer = &self.state.metadata().gasometer;
+		let gas_limit = gasometer.gas_limit();
+		let gas_left = gasometer.gas();
+		let threshold = gas_limit / 100 * percent + gas_limit % 100 * percent / 100;
+		if gas_before >= threshold && gas_left < threshold {
+			watchdog.on_low_gas(gas_left, gas_limit)
+		} else {
+			Ok(())
 		}
 	}
 
@@ -162,6 +419,12 @@ impl<'config, S: StackState<'config>> StackExecutor<'config, S> {
 		is_static: bool,
 	) {
 		self.state.enter(gas_limit, is_static);
+		metric!(histogram "evm_substate_depth"; self.state.metadata().depth.unwrap_or(0));
+		event!(EnterFrame {
+			depth: self.state.metadata().depth.unwrap_or(0),
+			gas_limit,
+			is_static: self.state.metadata().is_static,
+		});
 	}
 
 	/// Exit a substate. Panic if it results an empty substate stack.
@@ -169,6 +432,25 @@ impl<'config, S: StackState<'config>> StackExecutor<'config, S> {
 		&mut self,
 		kind: StackExitKind,
 	) -> Result<(), ExitError> {
+		trace_event!(
+			depth = self.state.metadata().depth.unwrap_or(0),
+			kind = ?kind,
+			used_gas = self.state.metadata().gasometer.total_used_gas(),
+			logs = self.state.metadata().logs,
+			"exit frame",
+		);
+		event!(ExitFrame {
+			depth: self.state.metadata().depth.unwrap_or(0),
+			kind,
+			gas_limit: self.state.metadata().gasometer.gas_limit(),
+			used_gas: self.state.metadata().gasometer.total_used_gas(),
+			refunded_gas: self.state.metadata().gasometer.refunded_gas(),
+			logs: self.state.metadata().logs,
+			burnt_gas: match kind {
+				StackExitKind::Failed => self.state.metadata().gasometer.gas_limit(),
+				_ => self.state.metadata().gasometer.total_used_gas(),
+			},
+		});
 		match kind {
 			StackExitKind::Succeeded => self.state.exit_commit(),
 			StackExitKind::Reverted => self.state.exit_revert(),
@@ -178,7 +460,13 @@ impl<'config, S: StackState<'config>> StackExecutor<'config, S> {
 
 	/// Execute the runtime until it returns.
 	pub fn execute(&mut self, runtime: &mut Runtime) -> ExitReason {
+		#[cfg(feature = "tracing")]
+		if self.listeners.is_some() {
+			return self.with_listeners(|executor| executor.execute(runtime))
+		}
+
 		match runtime.run(self) {
+			Capture::Exit(_) if self.timed_out() => ExitFatal::ExecutionTimeout.into(),
 			Capture::Exit(s) => s,
 			Capture::Trap(_) => unreachable!("Trap is Infallible"),
 		}
@@ -197,21 +485,44 @@ impl<'config, S: StackState<'config>> StackExecutor<'config, S> {
 		init_code: Vec<u8>,
 		gas_limit: u64,
 	) -> ExitReason {
+		#[cfg(feature = "tracing")]
+		if self.listeners.is_some() {
+			return self.with_listeners(|executor| {
+				executor.transact_create(caller, value, init_code, gas_limit)
+			})
+		}
+
+		let _span = trace_span!("transact_create", caller = ?caller, gas_limit);
+		metric!(counter "evm_transactions_total"; 1);
+		self.last_error = None;
+		self.instructions = 0;
+		let scheme = CreateScheme::Legacy { caller };
 		let transaction_cost = gasometer::create_transaction_cost(&init_code);
 		match self.state.metadata_mut().gasometer.record_transaction(transaction_cost) {
-			Ok(()) => (),
-			Err(e) => return e.into(),
+			Ok(()) => trace_event!(
+				gas = self.state.metadata().gasometer.gas(),
+				used_gas = self.used_gas(),
+				"transaction cost recorded",
+			),
+			Err(e) => {
+				self.record_error(&e, self.create_address(scheme), None);
+				return self.record_result(e.into(), Vec::new()).0
+			},
 		}
 
 		match self.create_inner(
 			caller,
-			CreateScheme::Legacy { caller },
+			scheme,
 			value,
 			init_code,
 			Some(gas_limit),
 			false,
 		) {
-			Capture::Exit((s, _, _)) => s,
+			Capture::Exit((s, _, v)) => {
+				metric!(histogram "evm_transaction_gas_used"; self.used_gas());
+				trace_event!(used_gas = self.used_gas(), "transaction finished");
+				self.record_result(s, v).0
+			},
 			Capture::Trap(_) => unreachable!(),
 		}
 	}
@@ -225,22 +536,45 @@ impl<'config, S: StackState<'config>> StackExecutor<'config, S> {
 		salt: H256,
 		gas_limit: u64,
 	) -> ExitReason {
+		#[cfg(feature = "tracing")]
+		if self.listeners.is_some() {
+			return self.with_listeners(|executor| {
+				executor.transact_create2(caller, value, init_code, salt, gas_limit)
+			})
+		}
+
+		let _span = trace_span!("transact_create2", caller = ?caller, gas_limit);
+		metric!(counter "evm_transactions_total"; 1);
+		self.last_error = None;
+		self.instructions = 0;
+		let code_hash = self.config.keccak256(&init_code);
+		let scheme = CreateScheme::Create2 { caller, code_hash, salt };
 		let transaction_cost = gasometer::create_transaction_cost(&init_code);
 		match self.state.metadata_mut().gasometer.record_transaction(transaction_cost) {
-			Ok(()) => (),
-			Err(e) => return e.into(),
+			Ok(()) => trace_event!(
+				gas = self.state.metadata().gasometer.gas(),
+				used_gas = self.used_gas(),
+				"transaction cost recorded",
+			),
+			Err(e) => {
+				self.record_error(&e, self.create_address(scheme), None);
+				return self.record_result(e.into(), Vec::new()).0
+			},
 		}
-		let code_hash = H256::from_slice(Keccak256::digest(&init_code).as_slice());
 
 		match self.create_inner(
 			caller,
-			CreateScheme::Create2 { caller, code_hash, salt },
+			scheme,
 			value,
 			init_code,
 			Some(gas_limit),
 			false,
 		) {
-			Capture::Exit((s, _, _)) => s,
+			Capture::Exit((s, _, v)) => {
+				metric!(histogram "evm_transaction_gas_used"; self.used_gas());
+				trace_event!(used_gas = self.used_gas(), "transaction finished");
+				self.record_result(s, v).0
+			},
 			Capture::Trap(_) => unreachable!(),
 		}
 	}
@@ -254,10 +588,28 @@ impl<'config, S: StackState<'config>> StackExecutor<'config, S> {
 		data: Vec<u8>,
 		gas_limit: u64,
 	) -> (ExitReason, Vec<u8>) {
+		#[cfg(feature = "tracing")]
+		if self.listeners.is_some() {
+			return self.with_listeners(|executor| {
+				executor.transact_call(caller, address, value, data, gas_limit)
+			})
+		}
+
+		let _span = trace_span!("transact_call", caller = ?caller, address = ?address, gas_limit);
+		metric!(counter "evm_transactions_total"; 1);
+		self.last_error = None;
+		self.instructions = 0;
 		let transaction_cost = gasometer::call_transaction_cost(&data);
 		match self.state.metadata_mut().gasometer.record_transaction(transaction_cost) {
-			Ok(()) => (),
-			Err(e) => return (e.into(), Vec::new()),
+			Ok(()) => trace_event!(
+				gas = self.state.metadata().gasometer.gas(),
+				used_gas = self.used_gas(),
+				"transaction cost recorded",
+			),
+			Err(e) => {
+				self.record_error(&e, address, None);
+				return self.record_result(e.into(), Vec::new())
+			},
 		}
 
 		self.state.inc_nonce(caller);
@@ -273,18 +625,109 @@ impl<'config, S: StackState<'config>> StackExecutor<'config, S> {
 			target: address,
 			value
 		}), data, Some(gas_limit), false, false, false, context) {
-			Capture::Exit((s, v)) => (s, v),
+			Capture::Exit((s, v)) => {
+				metric!(histogram "evm_transaction_gas_used"; self.used_gas());
+				trace_event!(used_gas = self.used_gas(), "transaction finished");
+				self.record_result(s, v)
+			},
 			Capture::Trap(_) => unreachable!(),
 		}
 	}
 
+	/// Execute a call transaction described by `args`.
+	pub fn transact_call_with_args(&mut self, args: CallArgs) -> (ExitReason, Vec<u8>) {
+		let origin = self.origin;
+		if args.origin.is_some() {
+			self.origin = args.origin;
+		}
+
+		let result = match args.pending_code {
+			Some((pending_address, pending_code)) => self.transact_call_with_pending_code(
+				pending_address,
+				pending_code,
+				args.caller,
+				args.address,
+				args.value,
+				args.data,
+				args.gas_limit,
+			),
+			None => self.transact_call(
+				args.caller, args.address, args.value, args.data, args.gas_limit,
+			),
+		};
+
+		self.origin = origin;
+		result
+	}
+
+	/// Execute a create transaction described by `args`.
+	pub fn transact_create_with_args(&mut self, args: CreateArgs) -> ExitReason {
+		let origin = self.origin;
+		if args.origin.is_some() {
+			self.origin = args.origin;
+		}
+
+		let result = match args.salt {
+			Some(salt) => self.transact_create2(
+				args.caller, args.value, args.init_code, salt, args.gas_limit,
+			),
+			None => self.transact_create(
+				args.caller, args.value, args.init_code, args.gas_limit,
+			),
+		};
+
+		self.origin = origin;
+		result
+	}
+
+	/// Execute a `CALL` transaction while `pending_address` has
+	/// `pending_code`, as if it was already deployed. The code shadows the
+	/// state only for the duration of the transaction and is never applied, so
+	/// calls can be simulated against contracts a factory has yet to deploy.
+	#[allow(clippy::too_many_arguments)]
+	pub fn transact_call_with_pending_code(
+		&mut self,
+		pending_address: H160,
+		pending_code: Vec<u8>,
+		caller: H160,
+		address: H160,
+		value: U256,
+		data: Vec<u8>,
+		gas_limit: u64,
+	) -> (ExitReason, Vec<u8>) {
+		self.pending_code.insert(pending_address, pending_code);
+		let result = self.transact_call(caller, address, value, data, gas_limit);
+		self.pending_code.remove(&pending_address);
+
+		result
+	}
+
+	fn record_result(&mut self, reason: ExitReason, data: Vec<u8>) -> (ExitReason, Vec<u8>) {
+		self.last_result = Some((reason.clone(), data.clone()));
+		(reason, data)
+	}
+
 	/// Get used gas for the current executor, given the price.
 	pub fn used_gas(
 		&self,
 	) -> u64 {
-		self.state.metadata().gasometer.total_used_gas() -
-			min(self.state.metadata().gasometer.total_used_gas() / 2,
-				self.state.metadata().gasometer.refunded_gas() as u64)
+		self.execution_summary().effective_gas_used
+	}
+
+	/// Gas accounting of the current executor, with the refund cap applied.
+	pub fn execution_summary(&self) -> ExecutionSummary {
+		let gasometer = &self.state.metadata().gasometer;
+		let gas_used_pre_refund = gasometer.total_used_gas();
+		let refund_requested = gasometer.refunded_gas();
+		let refund_applied = min(gas_used_pre_refund / 2, max(refund_requested, 0) as u64);
+
+		ExecutionSummary {
+			gas_limit: gasometer.gas_limit(),
+			gas_used_pre_refund,
+			refund_requested,
+			refund_applied,
+			effective_gas_used: gas_used_pre_refund - refund_applied,
+		}
 	}
 
 	/// Get fee needed for the current executor, given the price.
@@ -305,19 +748,19 @@ impl<'config, S: StackState<'config>> StackExecutor<'config, S> {
 	pub fn create_address(&self, scheme: CreateScheme) -> H160 {
 		match scheme {
 			CreateScheme::Create2 { caller, code_hash, salt } => {
-				let mut hasher = Keccak256::new();
-				hasher.input(&[0xff]);
-				hasher.input(&caller[..]);
-				hasher.input(&salt[..]);
-				hasher.input(&code_hash[..]);
-				H256::from_slice(hasher.result().as_slice()).into()
+				let mut preimage = [0u8; 85];
+				preimage[0] = 0xff;
+				preimage[1..21].copy_from_slice(&caller[..]);
+				preimage[21..53].copy_from_slice(&salt[..]);
+				preimage[53..85].copy_from_slice(&code_hash[..]);
+				self.config.keccak256(&preimage).into()
 			},
 			CreateScheme::Legacy { caller } => {
 				let nonce = self.nonce(caller);
 				let mut stream = rlp::RlpStream::new_list(2);
 				stream.append(&caller);
 				stream.append(&nonce);
-				H256::from_slice(Keccak256::digest(&stream.out()).as_slice()).into()
+				self.config.keccak256(&stream.out()).into()
 			},
 			CreateScheme::Fixed(naddress) => {
 				naddress
@@ -348,6 +791,7 @@ impl<'config, S: StackState<'config>> StackExecutor<'config, S> {
 		}
 
 		let address = self.create_address(scheme);
+		let _span = trace_span!("create", caller = ?caller, address = ?address, target_gas = ?target_gas);
 
 		event!(Create {
 			caller,
@@ -360,11 +804,13 @@ impl<'config, S: StackState<'config>> StackExecutor<'config, S> {
 
 		if let Some(depth) = self.state.metadata().depth {
 			if depth > self.config.call_stack_limit {
+				self.record_error(&ExitError::CallTooDeep, address, None);
 				return Capture::Exit((ExitError::CallTooDeep.into(), None, Vec::new()))
 			}
 		}
 
 		if self.balance(caller) < value {
+			self.record_error(&ExitError::OutOfFund, address, None);
 			return Capture::Exit((ExitError::OutOfFund.into(), None, Vec::new()))
 		}
 
@@ -390,15 +836,35 @@ impl<'config, S: StackState<'config>> StackExecutor<'config, S> {
 
 		self.state.inc_nonce(caller);
 
+		if self.config.precheck_code_deposit {
+			if let Some(cost) = gasometer::max_code_deposit_cost(self.config) {
+				if gas_limit < cost {
+					self.record_error(&ExitError::OutOfGas, address, None);
+					return Capture::Exit((ExitError::OutOfGas.into(), None, Vec::new()))
+				}
+			}
+		}
+
 		self.enter_substate(gas_limit, false);
 
+		if let Err(e) = self.watch_depth() {
+			self.record_error(&e, address, None);
+			let _ = self.exit_substate(StackExitKind::Failed);
+			return Capture::Exit((e.into(), None, Vec::new()))
+		}
+
 		{
-			if self.code_size(address) != U256::zero() {
-				let _ = self.exit_substate(StackExitKind::Failed);
-				return Capture::Exit((ExitError::CreateCollision.into(), None, Vec::new()))
-			}
+			let code_size = self.code_size(address);
+			let nonce = self.nonce(address);
+
+			if code_size != U256::zero() || nonce > U256::zero() {
+				event!(CreateCollision {
+					address,
+					code_size,
+					nonce,
+				});
 
-			if self.nonce(address) > U256::zero() {
+				self.record_error(&ExitError::CreateCollision, address, None);
 				let _ = self.exit_substate(StackExitKind::Failed);
 				return Capture::Exit((ExitError::CreateCollision.into(), None, Vec::new()))
 			}
@@ -419,6 +885,7 @@ impl<'config, S: StackState<'config>> StackExecutor<'config, S> {
 		match self.state.transfer(transfer) {
 			Ok(()) => (),
 			Err(e) => {
+				self.record_error(&e, address, None);
 				let _ = self.exit_substate(StackExitKind::Reverted);
 				return Capture::Exit((ExitReason::Error(e), None, Vec::new()))
 			},
@@ -444,12 +911,20 @@ impl<'config, S: StackState<'config>> StackExecutor<'config, S> {
 
 				if let Some(limit) = self.config.create_contract_limit {
 					if out.len() > limit {
+						self.record_error(&ExitError::CreateContractLimit, address, None);
 						self.state.metadata_mut().gasometer.fail();
 						let _ = self.exit_substate(StackExitKind::Failed);
 						return Capture::Exit((ExitError::CreateContractLimit.into(), None, Vec::new()))
 					}
 				}
 
+				if self.config.disallow_executable_format && out.first() == Some(&0xef) {
+					self.record_error(&ExitError::InvalidCodePrefix, address, None);
+					self.state.metadata_mut().gasometer.fail();
+					let _ = self.exit_substate(StackExitKind::Failed);
+					return Capture::Exit((ExitError::InvalidCodePrefix.into(), None, Vec::new()))
+				}
+
 				match self.state.metadata_mut().gasometer.record_deposit(out.len()) {
 					Ok(()) => {
 						let e = self.exit_substate(StackExitKind::Succeeded);
@@ -458,12 +933,14 @@ impl<'config, S: StackState<'config>> StackExecutor<'config, S> {
 						Capture::Exit((ExitReason::Succeed(s), Some(address), Vec::new()))
 					},
 					Err(e) => {
+						self.record_error(&e, address, None);
 						let _ = self.exit_substate(StackExitKind::Failed);
 						Capture::Exit((ExitReason::Error(e), None, Vec::new()))
 					},
 				}
 			},
 			ExitReason::Error(e) => {
+				self.record_error(&e, address, Some(&runtime));
 				self.state.metadata_mut().gasometer.fail();
 				let _ = self.exit_substate(StackExitKind::Failed);
 				Capture::Exit((ExitReason::Error(e), None, Vec::new()))
@@ -504,6 +981,13 @@ impl<'config, S: StackState<'config>> StackExecutor<'config, S> {
 			gas - gas / 64
 		}
 
+		let _span = trace_span!(
+			"call",
+			code_address = ?code_address,
+			target_gas = ?target_gas,
+			is_static,
+		);
+
 		event!(Call {
 			code_address,
 			transfer: &transfer,
@@ -546,15 +1030,23 @@ impl<'config, S: StackState<'config>> StackExecutor<'config, S> {
 
 		if let Some(depth) = self.state.metadata().depth {
 			if depth > self.config.call_stack_limit {
+				self.record_error(&ExitError::CallTooDeep, code_address, None);
 				let _ = self.exit_substate(StackExitKind::Reverted);
 				return Capture::Exit((ExitError::CallTooDeep.into(), Vec::new()))
 			}
 		}
 
+		if let Err(e) = self.watch_depth() {
+			self.record_error(&e, code_address, None);
+			let _ = self.exit_substate(StackExitKind::Failed);
+			return Capture::Exit((e.into(), Vec::new()))
+		}
+
 		if let Some(transfer) = transfer {
 			match self.state.transfer(transfer) {
 				Ok(()) => (),
 				Err(e) => {
+					self.record_error(&e, code_address, None);
 					let _ = self.exit_substate(StackExitKind::Reverted);
 					return Capture::Exit((ExitReason::Error(e), Vec::new()))
 				},
@@ -562,12 +1054,22 @@ impl<'config, S: StackState<'config>> StackExecutor<'config, S> {
 		}
 
 		if let Some(ret) = (self.precompile)(code_address, &input, Some(gas_limit), &context, &mut self.state, is_static) {
+			metric!(counter "evm_precompile_calls_total"; 1);
 			match ret {
 				Ok(PrecompileOutput { exit_status , output, cost, logs }) => {
+					event!(PrecompileCall {
+						address: code_address,
+						input_length: input.len(),
+						cost,
+						output_length: output.len(),
+						success: true,
+					});
+
 					for Log { address, topics, data} in logs {
 						match self.log(address, topics, data) {
 							Ok(_) => continue,
 							Err(error) => {
+								self.record_error(&error, code_address, None);
 								return Capture::Exit((ExitReason::Error(error), output));
 							}
 						}
@@ -578,6 +1080,15 @@ impl<'config, S: StackState<'config>> StackExecutor<'config, S> {
 					return Capture::Exit((ExitReason::Succeed(exit_status), output));
 				},
 				Err(e) => {
+					event!(PrecompileCall {
+						address: code_address,
+						input_length: input.len(),
+						cost: 0,
+						output_length: 0,
+						success: false,
+					});
+
+					self.record_error(&e, code_address, None);
 					let _ = self.exit_substate(StackExitKind::Failed);
 					return Capture::Exit((ExitReason::Error(e), Vec::new()));
 				},
@@ -600,6 +1111,7 @@ impl<'config, S: StackState<'config>> StackExecutor<'config, S> {
 				Capture::Exit((ExitReason::Succeed(s), runtime.machine().return_value()))
 			},
 			ExitReason::Error(e) => {
+				self.record_error(&e, code_address, Some(&runtime));
 				let _ = self.exit_substate(StackExitKind::Failed);
 				Capture::Exit((ExitReason::Error(e), Vec::new()))
 			},
@@ -616,6 +1128,19 @@ impl<'config, S: StackState<'config>> StackExecutor<'config, S> {
 	}
 }
 
+impl<'backend, 'config, B: Backend> StackExecutor<'config, MemoryStackState<'backend, 'config, B>> {
+	/// Outcome of the last transaction, with the logs and changes so far,
+	/// without consuming the executor. The state can then still be queried,
+	/// or further transactions executed on top of it. `None` if no
+	/// transaction was executed yet.
+	pub fn finalize(&self) -> Option<TransactionOutcome> {
+		let (exit_reason, return_data) = self.last_result.clone()?;
+		let (changes, logs) = self.state.changes();
+
+		Some(TransactionOutcome { exit_reason, return_data, logs, changes })
+	}
+}
+
 impl<'config, S: StackState<'config>> Handler for StackExecutor<'config, S> {
 	type CreateInterrupt = Infallible;
 	type CreateFeedback = Infallible;
@@ -627,7 +1152,10 @@ impl<'config, S: StackState<'config>> Handler for StackExecutor<'config, S> {
 	}
 
 	fn code_size(&self, address: H160) -> U256 {
-		U256::from(self.state.code(address).len())
+		match self.pending_code.get(&address) {
+			Some(code) => U256::from(code.len()),
+			None => U256::from(self.state.code(address).len()),
+		}
 	}
 
 	fn code_hash(&self, address: H160) -> H256 {
@@ -635,11 +1163,21 @@ impl<'config, S: StackState<'config>> Handler for StackExecutor<'config, S> {
 			return H256::default()
 		}
 
-		H256::from_slice(Keccak256::digest(&self.state.code(address)).as_slice())
+		self.config.keccak256(&self.code(address))
 	}
 
 	fn code(&self, address: H160) -> Vec<u8> {
-		self.state.code(address)
+		match self.pending_code.get(&address) {
+			Some(code) => code.clone(),
+			None => self.state.code(address),
+		}
+	}
+
+	fn code_chunk(&self, address: H160, offset: usize, len: usize) -> Vec<u8> {
+		match self.pending_code.get(&address) {
+			Some(code) => code_chunk(code, offset, len),
+			None => self.state.code_chunk(address, offset, len),
+		}
 	}
 
 	fn storage(&self, address: H160, index: H256) -> H256 {
@@ -651,7 +1189,9 @@ impl<'config, S: StackState<'config>> Handler for StackExecutor<'config, S> {
 	}
 
 	fn exists(&self, address: H160) -> bool {
-		if self.config.empty_considered_exists {
+		if self.pending_code.contains_key(&address) {
+			true
+		} else if self.config.empty_considered_exists {
 			self.state.exists(address)
 		} else {
 			self.state.exists(address) && !self.state.is_empty(address)
@@ -663,8 +1203,18 @@ impl<'config, S: StackState<'config>> Handler for StackExecutor<'config, S> {
 	}
 
 	fn gas_price(&self) -> U256 { self.state.gas_price() }
-	fn origin(&self) -> H160 { self.state.origin() }
-	fn block_hash(&self, number: U256) -> H256 { self.state.block_hash(number) }
+	fn origin(&self) -> H160 { self.origin.unwrap_or_else(|| self.state.origin()) }
+	fn block_hash(&self, number: U256) -> H256 {
+		let block_number = self.state.block_number();
+		if number >= block_number ||
+			block_number - number > U256::from(self.config.block_hash_history)
+		{
+			event!(BlockHashOutOfWindow { number, block_number });
+			H256::default()
+		} else {
+			self.state.block_hash(number)
+		}
+	}
 	fn block_number(&self) -> U256 { self.state.block_number() }
 	fn block_coinbase(&self) -> H160 { self.state.block_coinbase() }
 	fn block_timestamp(&self) -> U256 { self.state.block_timestamp() }
@@ -682,24 +1232,48 @@ impl<'config, S: StackState<'config>> Handler for StackExecutor<'config, S> {
 	}
 
 	fn log(&mut self, address: H160, topics: Vec<H256>, data: Vec<u8>) -> Result<(), ExitError> {
-		self.state.log(address, topics, data);
+		self.state.metadata_mut().logs += 1;
+		match self.log_listener.as_mut() {
+			Some(listener) => {
+				let log = Log { address, topics, data };
+				listener(&log);
+				self.state.log(log.address, log.topics, log.data);
+			},
+			None => self.state.log(address, topics, data),
+		}
 		Ok(())
 	}
 
 	fn mark_delete(&mut self, address: H160, target: H160) -> Result<(), ExitError> {
 		let balance = self.balance(address);
 
-		event!(Suicide {
-			target,
-			address,
-			balance,
-		});
+		if self.state.deleted(address) {
+			event!(SuicideRepeated {
+				address,
+				target,
+				balance,
+			});
+		} else if target == address {
+			event!(SuicideBurn {
+				address,
+				balance,
+			});
+		} else {
+			event!(Suicide {
+				target,
+				address,
+				balance,
+			});
+		}
 
-		self.state.transfer(Transfer {
-			source: address,
-			target,
-			value: balance,
-		})?;
+		// Suiciding to itself burns the balance, which is then reset below.
+		if target != address {
+			self.state.transfer(Transfer {
+				source: address,
+				target,
+				value: balance,
+			})?;
+		}
 		self.state.reset_balance(address);
 		self.state.set_deleted(address);
 
@@ -737,9 +1311,28 @@ impl<'config, S: StackState<'config>> Handler for StackExecutor<'config, S> {
 		stack: &Stack
 	) -> Result<(), ExitError> {
 		// log::trace!(target: "evm", "Running opcode: {:?}, Pre gas-left: {:?}", opcode, gasometer.gas());
+		metric!(counter "evm_opcodes_total"; 1);
+
+		if self.instruction_limit.is_some() {
+			self.instructions += 1;
+			if self.timed_out() {
+				// Turned into a fatal error by `execute`.
+				return Err(ExitError::OutOfGas)
+			}
+		}
+
+		let gas_before = self.state.metadata().gasometer.gas();
 
-		if let Some(cost) = gasometer::static_opcode_cost(opcode) {
-			self.state.metadata_mut().gasometer.record_cost(cost)?;
+		let static_cost = match self.opcode_costs {
+			Some(table) => match table.get(opcode) {
+				gasometer::OpcodeCost::Static(cost) => Some(cost),
+				_ => None,
+			},
+			None => gasometer::static_opcode_cost(opcode),
+		};
+
+		let result = if let Some(cost) = static_cost {
+			self.state.metadata_mut().gasometer.record_cost(cost)
 		} else {
 			let is_static = self.state.metadata().is_static;
 			let (gas_cost, memory_cost) = gasometer::dynamic_opcode_cost(
@@ -748,9 +1341,41 @@ impl<'config, S: StackState<'config>> Handler for StackExecutor<'config, S> {
 
 			let gasometer = &mut self.state.metadata_mut().gasometer;
 
-			gasometer.record_dynamic_cost(gas_cost, memory_cost)?;
+			gasometer.record_dynamic_cost(gas_cost, memory_cost)
+		};
+
+		#[cfg(feature = "tracing")]
+		if let Some(cost) = result.as_ref().err().and(self.state.metadata().gasometer.out_of_gas()) {
+			event!(OutOfGas {
+				address: context.address,
+				opcode,
+				gas_cost: cost.gas_cost,
+				memory_cost: cost.memory_cost,
+				gas_left: cost.gas_left,
+			});
 		}
+		result?;
 
-		Ok(())
+		self.watch_gas(gas_before)
+	}
+
+	fn frame(&self) -> Frame {
+		self.state.metadata().frame()
+	}
+
+	fn custom_opcode(&self, opcode: Opcode) -> Option<CustomOpcode> {
+		self.custom_opcodes.as_ref().and_then(|custom| custom.signature(opcode))
+	}
+
+	fn custom(
+		&mut self,
+		opcode: Opcode,
+		context: &Context,
+		inputs: Vec<H256>,
+	) -> Result<Vec<H256>, ExitError> {
+		match self.custom_opcodes.as_mut() {
+			Some(custom) => custom.execute(opcode, context, inputs, &mut self.state),
+			None => Err(ExitError::OutOfGas),
+		}
 	}
 }
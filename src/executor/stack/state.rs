@@ -1,7 +1,9 @@
 use core::mem;
-use alloc::{vec::Vec, boxed::Box, collections::{BTreeMap, BTreeSet}};
+use core::cell::RefCell;
+use core::fmt::Write;
+use alloc::{vec::Vec, boxed::Box, string::String, rc::Rc, collections::{BTreeMap, BTreeSet}};
 use primitive_types::{H160, H256, U256};
-use crate::{ExitError, Transfer};
+use crate::{ExitError, Transfer, code_chunk};
 use crate::backend::{Basic, Log, Backend, Apply};
 use crate::executor::stack::StackSubstateMetadata;
 
@@ -12,13 +14,18 @@ struct MemoryStackAccount {
 	pub reset: bool,
 }
 
+#[derive(Clone)]
 pub struct MemoryStackSubstate<'config> {
 	metadata: StackSubstateMetadata<'config>,
 	parent: Option<Box<MemoryStackSubstate<'config>>>,
+	/// Frozen top-level substate this one was forked from, shared with the
+	/// other forks. Only set on top-level substates.
+	base: Option<Rc<MemoryStackSubstate<'config>>>,
 	logs: Vec<Log>,
 	accounts: BTreeMap<H160, MemoryStackAccount>,
 	storages: BTreeMap<(H160, H256), H256>,
 	deletes: BTreeSet<H160>,
+	touched: BTreeSet<H160>,
 }
 
 impl<'config> MemoryStackSubstate<'config> {
@@ -26,10 +33,12 @@ impl<'config> MemoryStackSubstate<'config> {
 		Self {
 			metadata,
 			parent: None,
+			base: None,
 			logs: Vec::new(),
 			accounts: BTreeMap::new(),
 			storages: BTreeMap::new(),
 			deletes: BTreeSet::new(),
+			touched: BTreeSet::new(),
 		}
 	}
 
@@ -43,68 +52,201 @@ impl<'config> MemoryStackSubstate<'config> {
 
 	/// Deconstruct the executor, return state to be applied. Panic if the
 	/// executor is not in the top-level substate.
+	///
+	/// If `clear_empty_touched_accounts` is set, touched accounts that are
+	/// empty are deleted, as specified by EIP-161.
 	#[must_use]
 	pub fn deconstruct<B: Backend>(
 		mut self, backend: &B,
 	) -> (impl IntoIterator<Item=Apply<impl IntoIterator<Item=(H256, H256)>>>,
  		  impl IntoIterator<Item=Log>)
 	{
-		assert!(self.parent.is_none());
+		self.flatten();
+		let applies = self.applies(backend);
 
-		let mut applies = Vec::<Apply<BTreeMap<H256, H256>>>::new();
+		(applies, self.logs)
+	}
+
+	/// Changes and logs `deconstruct` would return, leaving the substate
+	/// usable. Panic if not in the top-level substate.
+	pub fn changes<B: Backend>(
+		&self, backend: &B,
+	) -> (Vec<Apply<BTreeMap<H256, H256>>>, Vec<Log>) {
+		let applies = self.applies(backend);
+		let logs = self.layers().into_iter()
+			.flat_map(|layer| layer.logs.iter().cloned())
+			.collect();
 
-		let mut addresses = BTreeSet::new();
+		(applies, logs)
+	}
 
-		for address in self.accounts.keys() {
-			addresses.insert(*address);
+	/// This substate and the ones it was forked from, oldest first.
+	fn layers(&self) -> Vec<&Self> {
+		let mut layers = vec![self];
+		while let Some(base) = layers[layers.len() - 1].base.as_deref() {
+			layers.push(base);
 		}
+		layers.reverse();
+		layers
+	}
+
+	/// Changes to apply, reading through the layers of forks as `flatten`
+	/// would merge them.
+	fn applies<B: Backend>(&self, backend: &B) -> Vec<Apply<BTreeMap<H256, H256>>> {
+		assert!(self.parent.is_none());
 
-		for (address, _) in self.storages.keys() {
-			addresses.insert(*address);
+		// Latest account of each address, and whether its storage was reset.
+		let mut accounts = BTreeMap::<H160, (&MemoryStackAccount, bool)>::new();
+		let mut storages = BTreeMap::<H160, BTreeMap<H256, H256>>::new();
+		let mut deletes = BTreeSet::new();
+		let mut touched = BTreeSet::new();
+		for layer in self.layers() {
+			for (address, account) in &layer.accounts {
+				let reset = account.reset ||
+					accounts.get(address).map_or(false, |(_, reset)| *reset);
+				if account.reset {
+					storages.remove(address);
+				}
+				accounts.insert(*address, (account, reset));
+			}
+			for ((address, index), value) in &layer.storages {
+				storages.entry(*address).or_default().insert(*index, *value);
+			}
+			deletes.extend(layer.deletes.iter().copied());
+			touched.extend(layer.touched.iter().copied());
 		}
 
+		let mut applies = Vec::<Apply<BTreeMap<H256, H256>>>::new();
+		let clear_empty = self.metadata.gasometer.config().clear_empty_touched_accounts;
+
+		let mut addresses = accounts.keys().copied().collect::<BTreeSet<_>>();
+		addresses.extend(storages.keys().copied());
+
 		for address in addresses {
-			if self.deletes.contains(&address) {
+			if deletes.contains(&address) {
 				continue
 			}
 
-			let mut storage = BTreeMap::new();
-			for ((oa, ok), ov) in &self.storages {
-				if *oa == address {
-					storage.insert(*ok, *ov);
+			let (basic, code, reset) = match accounts.get(&address) {
+				Some((account, reset)) => (account.basic.clone(), account.code.as_ref(), *reset),
+				None => (backend.basic(address), None, false),
+			};
+
+			if clear_empty && touched.contains(&address) {
+				let is_empty = basic.balance == U256::zero() &&
+					basic.nonce == U256::zero() &&
+					match code {
+						Some(code) => code.is_empty(),
+						None => backend.code(address).is_empty(),
+					};
+
+				if is_empty {
+					applies.push(Apply::Delete { address });
+					continue
 				}
 			}
 
-			let apply = {
-				let account = self.account_mut(address, backend);
+			applies.push(Apply::Modify {
+				address,
+				basic,
+				code: code.cloned(),
+				storage: storages.remove(&address).unwrap_or_default(),
+				reset_storage: reset,
+			});
+		}
 
-				Apply::Modify {
-					address,
-					basic: account.basic.clone(),
-					code: account.code.clone(),
-					storage,
-					reset_storage: account.reset,
-				}
-			};
+		for address in deletes {
+			applies.push(Apply::Delete { address });
+		}
 
-			applies.push(apply);
+		applies
+	}
+
+	/// Canonical JSON dump of the changes held by this substate and the
+	/// layers it was forked from, excluding its parents. Keys are sorted and
+	/// the output has no whitespace, so it can be compared as a string in
+	/// snapshot tests.
+	pub fn debug_dump(&self) -> String {
+		if self.base.is_some() {
+			let mut flattened = self.clone();
+			flattened.flatten();
+			return flattened.debug_dump()
 		}
 
-		for address in self.deletes {
-			applies.push(Apply::Delete { address });
+		fn hex(bytes: &[u8]) -> String {
+			let mut out = String::from("0x");
+			for byte in bytes {
+				let _ = write!(out, "{:02x}", byte);
+			}
+			out
 		}
 
-		(applies, self.logs)
+		fn list<T, F: Fn(&mut String, T)>(out: &mut String, items: impl IntoIterator<Item=T>, f: F) {
+			for (i, item) in items.into_iter().enumerate() {
+				if i > 0 {
+					out.push(',');
+				}
+				f(out, item);
+			}
+		}
+
+		let mut storages = BTreeMap::<H160, Vec<(H256, H256)>>::new();
+		for ((address, index), value) in &self.storages {
+			storages.entry(*address).or_default().push((*index, *value));
+		}
+
+		let mut out = String::from("{\"accounts\":{");
+		list(&mut out, &self.accounts, |out, (address, account)| {
+			let _ = write!(
+				out,
+				"\"{:?}\":{{\"balance\":\"{:#x}\",\"nonce\":\"{:#x}\",\"code\":",
+				address, account.basic.balance, account.basic.nonce,
+			);
+			match &account.code {
+				Some(code) => { let _ = write!(out, "\"{}\"", hex(code)); },
+				None => out.push_str("null"),
+			}
+			let _ = write!(out, ",\"reset\":{}}}", account.reset);
+		});
+		out.push_str("},\"storages\":{");
+		list(&mut out, &storages, |out, (address, slots)| {
+			let _ = write!(out, "\"{:?}\":{{", address);
+			list(out, slots.iter(), |out, (index, value)| {
+				let _ = write!(out, "\"{:?}\":\"{:?}\"", index, value);
+			});
+			out.push('}');
+		});
+		out.push_str("},\"deletes\":[");
+		list(&mut out, &self.deletes, |out, address| {
+			let _ = write!(out, "\"{:?}\"", address);
+		});
+		out.push_str("],\"touched\":[");
+		list(&mut out, &self.touched, |out, address| {
+			let _ = write!(out, "\"{:?}\"", address);
+		});
+		out.push_str("],\"logs\":[");
+		list(&mut out, &self.logs, |out, log| {
+			let _ = write!(out, "{{\"address\":\"{:?}\",\"topics\":[", log.address);
+			list(out, &log.topics, |out, topic| {
+				let _ = write!(out, "\"{:?}\"", topic);
+			});
+			let _ = write!(out, "],\"data\":\"{}\"}}", hex(&log.data));
+		});
+		out.push_str("]}");
+
+		out
 	}
 
 	pub fn enter(&mut self, gas_limit: u64, is_static: bool) {
 		let mut entering = Self {
 			metadata: self.metadata.spit_child(gas_limit, is_static),
 			parent: None,
+			base: None,
 			logs: Vec::new(),
 			accounts: BTreeMap::new(),
 			storages: BTreeMap::new(),
 			deletes: BTreeSet::new(),
+			touched: BTreeSet::new(),
 		};
 		mem::swap(&mut entering, self);
 
@@ -115,7 +257,41 @@ impl<'config> MemoryStackSubstate<'config> {
 		let mut exited = *self.parent.take().expect("Cannot commit on root substate");
 		mem::swap(&mut exited, self);
 
+		self.merge(&mut exited);
 		self.metadata.swallow_commit(exited.metadata)?;
+
+		Ok(())
+	}
+
+	/// Fork the top-level substate. The changes made so far are frozen into
+	/// a layer shared by `self` and the returned fork, so that both can
+	/// diverge without copying them. Panic if not in the top-level substate.
+	pub fn fork(&mut self) -> Self {
+		assert!(self.parent.is_none());
+
+		let metadata = self.metadata.clone();
+		let base = Rc::new(mem::replace(self, Self::new(metadata.clone())));
+		self.base = Some(base.clone());
+
+		let mut fork = Self::new(metadata);
+		fork.base = Some(base);
+		fork
+	}
+
+	/// Merge the shared layers back into this substate. Layers still used by
+	/// other forks are copied.
+	fn flatten(&mut self) {
+		if let Some(base) = self.base.take() {
+			let mut base = Rc::try_unwrap(base).unwrap_or_else(|base| (*base).clone());
+			base.flatten();
+
+			let mut layer = mem::replace(self, base);
+			self.merge(&mut layer);
+			self.metadata = layer.metadata;
+		}
+	}
+
+	fn merge(&mut self, exited: &mut Self) {
 		self.logs.append(&mut exited.logs);
 
 		let mut resets = BTreeSet::new();
@@ -134,11 +310,17 @@ impl<'config> MemoryStackSubstate<'config> {
 			self.storages.remove(&(address, key));
 		}
 
+		// An account reset here stays reset once modified in the exited
+		// substate, which copied it without the flag.
+		for (address, account) in exited.accounts.iter_mut() {
+			if self.accounts.get(address).map(|a| a.reset).unwrap_or(false) {
+				account.reset = true;
+			}
+		}
 		self.accounts.append(&mut exited.accounts);
 		self.storages.append(&mut exited.storages);
 		self.deletes.append(&mut exited.deletes);
-
-		Ok(())
+		self.touched.append(&mut exited.touched);
 	}
 
 	pub fn exit_revert(&mut self) -> Result<(), ExitError> {
@@ -164,6 +346,8 @@ impl<'config> MemoryStackSubstate<'config> {
 			Some(account)
 		} else if let Some(parent) = self.parent.as_ref() {
 			parent.known_account(address)
+		} else if let Some(base) = self.base.as_ref() {
+			base.known_account(address)
 		} else {
 			None
 		}
@@ -214,6 +398,10 @@ impl<'config> MemoryStackSubstate<'config> {
 			return parent.known_storage(address, key)
 		}
 
+		if let Some(base) = self.base.as_ref() {
+			return base.known_storage(address, key)
+		}
+
 		None
 	}
 
@@ -228,6 +416,10 @@ impl<'config> MemoryStackSubstate<'config> {
 			return parent.known_original_storage(address, key)
 		}
 
+		if let Some(base) = self.base.as_ref() {
+			return base.known_original_storage(address, key)
+		}
+
 		None
 	}
 
@@ -240,10 +432,17 @@ impl<'config> MemoryStackSubstate<'config> {
 			return parent.deleted(address)
 		}
 
+		if let Some(base) = self.base.as_ref() {
+			return base.deleted(address)
+		}
+
 		false
 	}
 
 	fn account_mut<B: Backend>(&mut self, address: H160, backend: &B) -> &mut MemoryStackAccount {
+		// Any modification of an account touches it, in the sense of EIP-161.
+		self.touched.insert(address);
+
 		if !self.accounts.contains_key(&address) {
 			let account = self.known_account(address)
 				.cloned()
@@ -328,7 +527,6 @@ impl<'config> MemoryStackSubstate<'config> {
 		Ok(())
 	}
 
-	// Only needed for jsontests.
 	pub fn deposit<B: Backend>(&mut self, address: H160, value: U256, backend: &B) {
 		let target = self.account_mut(address, backend);
 		target.basic.balance = target.basic.balance.saturating_add(value);
@@ -366,9 +564,27 @@ pub trait StackState<'config>: Backend {
 	fn touch(&mut self, address: H160);
 }
 
+/// Stack state able to credit balances out of nothing, as needed to settle
+/// fees. Kept apart from `StackState` so that existing implementations do
+/// not have to provide it.
+pub trait DepositState<'config>: StackState<'config> {
+	/// Credit `value` to the balance of `address`, saturating.
+	fn deposit(&mut self, address: H160, value: U256);
+}
+
+/// Stack state keeping its changes in memory, over a backend that must not
+/// change while the state is alive.
+///
+/// Backend answers to `exists` and `is_empty` are cached in `RefCell`s, since
+/// they are queried through `&self`, so the state is not `Sync`. Changes of
+/// the substate are consulted first, so the caches never need invalidating.
 pub struct MemoryStackState<'backend, 'config, B> {
 	backend: &'backend B,
 	substate: MemoryStackSubstate<'config>,
+	/// Backend existence answers, each address being queried at most once.
+	exists_cache: RefCell<BTreeMap<H160, bool>>,
+	/// Backend emptiness answers, each address being queried at most once.
+	empty_cache: RefCell<BTreeMap<H160, bool>>,
 }
 
 impl<'backend, 'config, B: Backend> Backend for MemoryStackState<'backend, 'config, B> {
@@ -383,20 +599,49 @@ impl<'backend, 'config, B: Backend> Backend for MemoryStackState<'backend, 'conf
 	fn chain_id(&self) -> U256 { self.backend.chain_id() }
 
 	fn exists(&self, address: H160) -> bool {
-		self.substate.known_account(address).is_some() || self.backend.exists(address)
+		if self.substate.known_account(address).is_some() {
+			return true
+		}
+
+		if let Some(exists) = self.exists_cache.borrow().get(&address) {
+			return *exists
+		}
+
+		metric!(counter "evm_backend_reads_total", "kind" => "exists"; 1);
+		let exists = self.backend.exists(address);
+		self.exists_cache.borrow_mut().insert(address, exists);
+		exists
 	}
 
 	fn basic(&self, address: H160) -> Basic {
-		self.substate.known_basic(address).unwrap_or_else(|| self.backend.basic(address))
+		self.substate.known_basic(address).unwrap_or_else(|| {
+			metric!(counter "evm_backend_reads_total", "kind" => "basic"; 1);
+			self.backend.basic(address)
+		})
 	}
 
 	fn code(&self, address: H160) -> Vec<u8> {
-		self.substate.known_code(address).unwrap_or_else(|| self.backend.code(address))
+		self.substate.known_code(address).unwrap_or_else(|| {
+			metric!(counter "evm_backend_reads_total", "kind" => "code"; 1);
+			self.backend.code(address)
+		})
+	}
+
+	fn code_chunk(&self, address: H160, offset: usize, len: usize) -> Vec<u8> {
+		match self.substate.known_code(address) {
+			Some(code) => code_chunk(&code, offset, len),
+			None => {
+				metric!(counter "evm_backend_reads_total", "kind" => "code"; 1);
+				self.backend.code_chunk(address, offset, len)
+			},
+		}
 	}
 
 	fn storage(&self, address: H160, key: H256) -> H256 {
-		self.substate.known_storage(address, key)
-			.unwrap_or_else(|| self.backend.storage(address, key))
+		self.substate.known_storage(address, key).unwrap_or_else(|| {
+			metric!(counter "evm_backend_reads_total", "kind" => "storage"; 1);
+			self.backend.storage(address, key)
+		})
 	}
 
 	fn original_storage(&self, address: H160, key: H256) -> Option<H256> {
@@ -404,6 +649,7 @@ impl<'backend, 'config, B: Backend> Backend for MemoryStackState<'backend, 'conf
 			return Some(value)
 		}
 
+		metric!(counter "evm_backend_reads_total", "kind" => "original_storage"; 1);
 		self.backend.original_storage(address, key)
 	}
 }
@@ -438,9 +684,16 @@ impl<'backend, 'config, B: Backend> StackState<'config> for MemoryStackState<'ba
 			return known_empty
 		}
 
-		self.backend.basic(address).balance == U256::zero() &&
-			self.backend.basic(address).nonce == U256::zero() &&
-			self.backend.code(address).len() == 0
+		if let Some(empty) = self.empty_cache.borrow().get(&address) {
+			return *empty
+		}
+
+		let basic = self.backend.basic(address);
+		let empty = basic.balance == U256::zero() &&
+			basic.nonce == U256::zero() &&
+			self.backend.code(address).len() == 0;
+		self.empty_cache.borrow_mut().insert(address, empty);
+		empty
 	}
 
 	fn deleted(&self, address: H160) -> bool {
@@ -484,11 +737,36 @@ impl<'backend, 'config, B: Backend> StackState<'config> for MemoryStackState<'ba
 	}
 }
 
+impl<'backend, 'config, B: Backend> DepositState<'config> for MemoryStackState<'backend, 'config, B> {
+	fn deposit(&mut self, address: H160, value: U256) {
+		self.substate.deposit(address, value, self.backend)
+	}
+}
+
 impl<'backend, 'config, B: Backend> MemoryStackState<'backend, 'config, B> {
 	pub fn new(metadata: StackSubstateMetadata<'config>, backend: &'backend B) -> Self {
 		Self {
 			backend,
 			substate: MemoryStackSubstate::new(metadata),
+			exists_cache: RefCell::new(BTreeMap::new()),
+			empty_cache: RefCell::new(BTreeMap::new()),
+		}
+	}
+
+	/// Current substate.
+	pub fn substate(&self) -> &MemoryStackSubstate<'config> {
+		&self.substate
+	}
+
+	/// Fork the state at the top level. Both states share the changes made
+	/// so far, and can then be executed on and deconstructed independently.
+	/// Panic if not in the top-level substate.
+	pub fn fork(&mut self) -> Self {
+		Self {
+			backend: self.backend,
+			substate: self.substate.fork(),
+			exists_cache: self.exists_cache.clone(),
+			empty_cache: self.empty_cache.clone(),
 		}
 	}
 
@@ -501,6 +779,12 @@ impl<'backend, 'config, B: Backend> MemoryStackState<'backend, 'config, B> {
 		self.substate.deconstruct(self.backend)
 	}
 
+	/// Changes and logs `deconstruct` would return, leaving the state
+	/// usable. Panic if not in the top-level substate.
+	pub fn changes(&self) -> (Vec<Apply<BTreeMap<H256, H256>>>, Vec<Log>) {
+		self.substate.changes(self.backend)
+	}
+
 	pub fn withdraw(&mut self, address: H160, value: U256) -> Result<(), ExitError> {
 		self.substate.withdraw(address, value, self.backend)
 	}
@@ -0,0 +1,30 @@
+use crate::ExitError;
+
+/// Callbacks triggered by the stack executor while it runs, for monitoring or
+/// aborting execution early without a full tracer.
+///
+/// Returning an error from a callback aborts the current frame with that
+/// error, consuming all of its gas.
+pub trait Watchdog {
+	/// Call depths at which `on_depth` is triggered.
+	fn depth_thresholds(&self) -> &[usize] {
+		&[]
+	}
+
+	/// Percentage of a frame gas limit. `on_low_gas` is triggered when the
+	/// remaining gas of the frame falls below it.
+	fn low_gas_percent(&self) -> Option<u64> {
+		None
+	}
+
+	/// A call or create entered a depth listed in `depth_thresholds`.
+	fn on_depth(&mut self, _depth: usize) -> Result<(), ExitError> {
+		Ok(())
+	}
+
+	/// Remaining gas of the current frame fell below `low_gas_percent` of its
+	/// gas limit.
+	fn on_low_gas(&mut self, _gas_left: u64, _gas_limit: u64) -> Result<(), ExitError> {
+		Ok(())
+	}
+}
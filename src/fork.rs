@@ -0,0 +1,53 @@
+use alloc::vec::Vec;
+use primitive_types::U256;
+use crate::Config;
+
+/// Activation condition of a hard fork.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ForkActivation {
+	/// Active from the given block number.
+	Block(U256),
+	/// Active from the given block timestamp.
+	Timestamp(U256),
+}
+
+impl ForkActivation {
+	/// Whether the fork is active for the given block.
+	pub fn is_active(&self, block_number: U256, timestamp: U256) -> bool {
+		match self {
+			Self::Block(number) => block_number >= *number,
+			Self::Timestamp(time) => timestamp >= *time,
+		}
+	}
+}
+
+/// Hard fork schedule of a chain, mapping activations to configs.
+#[derive(Clone, Debug)]
+pub struct ForkSchedule {
+	genesis: Config,
+	forks: Vec<(ForkActivation, Config)>,
+}
+
+impl ForkSchedule {
+	/// Create a schedule whose genesis config is active from the first block.
+	pub fn new(genesis: Config) -> Self {
+		Self {
+			genesis,
+			forks: Vec::new(),
+		}
+	}
+
+	/// Add a fork. Forks must be added in the order they activate.
+	pub fn with_fork(mut self, activation: ForkActivation, config: Config) -> Self {
+		self.forks.push((activation, config));
+		self
+	}
+
+	/// Config of the latest fork active for the given block.
+	pub fn config_at(&self, block_number: U256, timestamp: U256) -> &Config {
+		self.forks.iter().rev()
+			.find(|(activation, _)| activation.is_active(block_number, timestamp))
+			.map(|(_, config)| config)
+			.unwrap_or(&self.genesis)
+	}
+}
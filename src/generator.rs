@@ -0,0 +1,202 @@
+//! Deterministic generators of addresses, keys, code and state.
+//!
+//! Property tests and benchmarks use these to build state-heavy scenarios
+//! that are reproducible from a seed across runs and platforms, without
+//! depending on `rand`. The generator is SplitMix64, which is fast and
+//! well distributed, but not cryptographically secure. Only compiled with
+//! the `test-utils` feature, which the tests and benchmarks of this crate
+//! enable.
+
+use alloc::{vec::Vec, collections::BTreeMap};
+use primitive_types::{H160, H256, U256};
+use crate::backend::{Apply, Basic, MemoryAccount};
+
+/// Seeded pseudo-random generator.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Generator {
+	state: u64,
+}
+
+impl Generator {
+	/// Create a generator. Equal seeds yield equal sequences.
+	pub fn new(seed: u64) -> Self {
+		Self { state: seed }
+	}
+
+	/// Next 64 bits of the sequence.
+	pub fn next_u64(&mut self) -> u64 {
+		self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+		let mut z = self.state;
+		z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+		z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+		z ^ (z >> 31)
+	}
+
+	/// Value in `0..bound`. Panic if `bound` is zero.
+	pub fn below(&mut self, bound: u64) -> u64 {
+		assert!(bound > 0, "Empty range");
+		self.next_u64() % bound
+	}
+
+	/// Fill `dest` with pseudo-random bytes.
+	pub fn fill_bytes(&mut self, dest: &mut [u8]) {
+		for chunk in dest.chunks_mut(8) {
+			let bytes = self.next_u64().to_le_bytes();
+			chunk.copy_from_slice(&bytes[..chunk.len()]);
+		}
+	}
+
+	/// Vector of `len` pseudo-random bytes.
+	pub fn bytes(&mut self, len: usize) -> Vec<u8> {
+		let mut bytes = alloc::vec![0; len];
+		self.fill_bytes(&mut bytes);
+		bytes
+	}
+
+	/// Address.
+	pub fn address(&mut self) -> H160 {
+		let mut address = H160::default();
+		self.fill_bytes(&mut address[..]);
+		address
+	}
+
+	/// Storage key or value.
+	pub fn key(&mut self) -> H256 {
+		let mut key = H256::default();
+		self.fill_bytes(&mut key[..]);
+		key
+	}
+
+	/// Value in `0..bound`, such as a balance. Panic if `bound` is zero.
+	pub fn u256_below(&mut self, bound: U256) -> U256 {
+		assert!(!bound.is_zero(), "Empty range");
+		U256::from_big_endian(&self.key()[..]) % bound
+	}
+
+	/// Code of `len` bytes. Opcodes are drawn from the defined ones, and
+	/// push instructions are followed by their immediate, which may be
+	/// truncated at the end of the code. The code is not meant to run
+	/// successfully.
+	pub fn code(&mut self, len: usize) -> Vec<u8> {
+		let mut code = Vec::with_capacity(len);
+		while code.len() < len {
+			let opcode = loop {
+				let opcode = self.below(256) as u8;
+				if crate::Opcode(opcode).name().is_some() {
+					break opcode
+				}
+			};
+			code.push(opcode);
+
+			if (0x60..=0x7f).contains(&opcode) {
+				let immediate = (opcode - 0x5f) as usize;
+				let immediate = self.bytes(immediate);
+				let left = len - code.len();
+				code.extend_from_slice(&immediate[..immediate.len().min(left)]);
+			}
+		}
+		code
+	}
+
+	/// Account with `storage_len` storage slots and `code_len` bytes of
+	/// code. The nonce and balance are below `2^32` and `2^128`.
+	pub fn account(&mut self, storage_len: usize, code_len: usize) -> MemoryAccount {
+		let mut storage = BTreeMap::new();
+		while storage.len() < storage_len {
+			let key = self.key();
+			storage.insert(key, self.key());
+		}
+
+		MemoryAccount {
+			nonce: U256::from(self.below(1 << 32)),
+			balance: self.u256_below(U256::one() << 128),
+			storage,
+			code: self.code(code_len),
+		}
+	}
+
+	/// State of `accounts` accounts, each with up to `max_storage_len`
+	/// storage slots and `max_code_len` bytes of code.
+	pub fn state(
+		&mut self,
+		accounts: usize,
+		max_storage_len: usize,
+		max_code_len: usize,
+	) -> BTreeMap<H160, MemoryAccount> {
+		let mut state = BTreeMap::new();
+		while state.len() < accounts {
+			let address = self.address();
+			let storage_len = self.below(max_storage_len as u64 + 1) as usize;
+			let code_len = self.below(max_code_len as u64 + 1) as usize;
+			state.insert(address, self.account(storage_len, code_len));
+		}
+		state
+	}
+
+	/// Up to `max_changes` changes to `state`, as a transaction would apply
+	/// them. They modify, empty or delete existing and new accounts, and
+	/// overwrite, clear or add storage slots.
+	pub fn changes(
+		&mut self,
+		state: &BTreeMap<H160, MemoryAccount>,
+		max_changes: usize,
+	) -> Vec<Apply<Vec<(H256, H256)>>> {
+		let addresses = state.keys().copied().collect::<Vec<_>>();
+		let mut changes = Vec::new();
+
+		for _ in 0..self.below(max_changes as u64 + 1) {
+			let address = if addresses.is_empty() || self.below(4) == 0 {
+				self.address()
+			} else {
+				addresses[self.below(addresses.len() as u64) as usize]
+			};
+
+			if self.below(8) == 0 {
+				changes.push(Apply::Delete { address });
+				continue
+			}
+
+			let indexes = state.get(&address)
+				.map(|account| account.storage.keys().copied().collect::<Vec<_>>())
+				.unwrap_or_default();
+			let mut storage = Vec::new();
+			for _ in 0..self.below(4) {
+				let index = if indexes.is_empty() || self.below(2) == 0 {
+					self.key()
+				} else {
+					indexes[self.below(indexes.len() as u64) as usize]
+				};
+				let value = if self.below(2) == 0 { H256::default() } else { self.key() };
+				storage.push((index, value));
+			}
+
+			let empty = self.below(8) == 0;
+			let basic = if empty {
+				Basic::default()
+			} else {
+				Basic {
+					balance: self.u256_below(U256::one() << 128),
+					nonce: U256::from(self.below(1 << 32)),
+				}
+			};
+			let code = match self.below(4) {
+				_ if empty => Some(Vec::new()),
+				0 => None,
+				_ => {
+					let len = self.below(64) as usize;
+					Some(self.code(len))
+				},
+			};
+
+			changes.push(Apply::Modify {
+				address,
+				basic,
+				code,
+				storage,
+				reset_storage: self.below(8) == 0,
+			});
+		}
+
+		changes
+	}
+}
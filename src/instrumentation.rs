@@ -0,0 +1,22 @@
+//! Instrumentation through the `metrics` facade. Not compiled with the
+//! `deterministic` feature.
+
+/// Record an instrumentation metric through the `metrics` facade. Exported
+/// metrics are:
+///
+/// * `evm_transactions_total`: counter of executed transactions.
+/// * `evm_transaction_gas_used`: histogram of gas used per transaction.
+/// * `evm_opcodes_total`: counter of executed opcodes.
+/// * `evm_substate_depth`: histogram of call depths entered, whose maximum is
+///   the deepest substate reached.
+/// * `evm_precompile_calls_total`: counter of precompile invocations.
+/// * `evm_backend_reads_total`: counter of reads falling through to the
+///   backend, labelled by `kind`.
+macro_rules! metric {
+	(counter $name:expr $(, $key:expr => $value:expr)*; $n:expr) => {
+		::metrics::counter!($name $(, $key => $value)*).increment($n as u64)
+	};
+	(histogram $name:expr; $n:expr) => {
+		::metrics::histogram!($name).record($n as f64)
+	};
+}
@@ -0,0 +1,100 @@
+//! L1SLOAD host query opcode, for L2s reading the storage of their L1.
+//!
+//! `L1Sload` is a set of custom opcodes defining a single opcode, to be
+//! installed with `StackExecutor::set_custom_opcodes`. It pops an L1 address
+//! (top of the stack) and a storage key, and pushes the value of that slot
+//! as seen by the L1 provider, usually at the latest L1 block known to the
+//! L2. The gas is charged by the gasometer as for any custom opcode, and
+//! each query is reported by the `L1Sload` tracing event.
+//!
+//! This is also the reference for extending the instruction set: other host
+//! queries follow the same structure of a signature, a provider of host data
+//! and an `execute` translating stack items.
+
+use alloc::{borrow::Cow, vec::Vec, collections::BTreeMap};
+use primitive_types::{H160, H256};
+use crate::{Context, CustomOpcode, ExitError, Opcode};
+use crate::executor::CustomOpcodes;
+
+/// Gas of a single load, the fixed and per-load costs of RIP-7728.
+pub const L1SLOAD_GAS: u64 = 4000;
+
+/// Source of L1 state.
+pub trait L1StateProvider {
+	/// Value of the storage slot `key` of `address` on L1, or `None` if L1
+	/// state is not available.
+	fn storage(&self, address: H160, key: H256) -> Option<H256>;
+}
+
+/// Fully available L1 state, keyed by address and storage key. Missing
+/// slots are zero.
+impl L1StateProvider for BTreeMap<(H160, H256), H256> {
+	fn storage(&self, address: H160, key: H256) -> Option<H256> {
+		Some(self.get(&(address, key)).copied().unwrap_or_default())
+	}
+}
+
+/// L1SLOAD opcode.
+pub struct L1Sload<P> {
+	opcode: Opcode,
+	gas: u64,
+	provider: P,
+}
+
+impl<P: L1StateProvider> L1Sload<P> {
+	/// L1SLOAD at `opcode`, which must be undefined in the EVM, costing
+	/// `L1SLOAD_GAS`.
+	pub fn new(opcode: Opcode, provider: P) -> Self {
+		Self { opcode, gas: L1SLOAD_GAS, provider }
+	}
+
+	/// Set the gas of a load.
+	pub fn gas(mut self, gas: u64) -> Self {
+		self.gas = gas;
+		self
+	}
+
+	/// L1 state provider.
+	pub fn provider(&self) -> &P {
+		&self.provider
+	}
+
+	/// Mutable L1 state provider, to move to a newer L1 block between
+	/// transactions.
+	pub fn provider_mut(&mut self) -> &mut P {
+		&mut self.provider
+	}
+}
+
+impl<S, P: L1StateProvider> CustomOpcodes<S> for L1Sload<P> {
+	fn signature(&self, opcode: Opcode) -> Option<CustomOpcode> {
+		if opcode != self.opcode {
+			return None
+		}
+
+		Some(CustomOpcode { inputs: 2, outputs: 1, gas: self.gas })
+	}
+
+	fn execute(
+		&mut self,
+		_opcode: Opcode,
+		_context: &Context,
+		inputs: Vec<H256>,
+		_state: &mut S,
+	) -> Result<Vec<H256>, ExitError> {
+		let address = H160::from(inputs[0]);
+		let key = inputs[1];
+		let value = self.provider.storage(address, key);
+
+		event!(L1Sload {
+			address,
+			key,
+			value,
+		});
+
+		match value {
+			Some(value) => Ok(alloc::vec![value]),
+			None => Err(ExitError::Other(Cow::Borrowed("L1 state unavailable"))),
+		}
+	}
+}
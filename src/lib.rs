@@ -27,5 +27,65 @@ macro_rules! event {
 	($x:expr) => { }
 }
 
+// Metrics are process-global side effects outside of the state transition,
+// so the `deterministic` feature compiles them out. All other state is kept
+// in ordered maps and no code path depends on the platform, see
+// `tests/determinism.rs`.
+#[cfg(all(feature = "metrics", not(feature = "deterministic")))]
+#[macro_use]
+mod instrumentation;
+
+#[cfg(any(not(feature = "metrics"), feature = "deterministic"))]
+macro_rules! metric {
+	($($x:tt)*) => { }
+}
+
+/// Open a span of the `tracing` ecosystem, entered until the returned guard
+/// is dropped. Spans are opened for transactions (`transact_call`,
+/// `transact_create`, `transact_create2`) and for every call and create
+/// frame (`call`, `create`).
+#[cfg(feature = "tracing-subscriber")]
+macro_rules! trace_span {
+	($($x:tt)*) => {
+		tokio_tracing::debug_span!($($x)*).entered()
+	}
+}
+
+#[cfg(not(feature = "tracing-subscriber"))]
+macro_rules! trace_span {
+	($($x:tt)*) => { () }
+}
+
+/// Emit an event of the `tracing` ecosystem, for gas milestones: the
+/// transaction cost, the exit of each frame and the end of each transaction.
+#[cfg(feature = "tracing-subscriber")]
+macro_rules! trace_event {
+	($($x:tt)*) => {
+		tokio_tracing::debug!($($x)*)
+	}
+}
+
+#[cfg(not(feature = "tracing-subscriber"))]
+macro_rules! trace_event {
+	($($x:tt)*) => { () }
+}
+
+mod fork;
+mod revert;
+
+pub use crate::fork::{ForkSchedule, ForkActivation};
+pub use crate::revert::{RevertReason, revert_reason};
+
 pub mod executor;
 pub mod backend;
+pub mod state_test;
+pub mod differential;
+#[cfg(feature = "test-utils")]
+pub mod generator;
+#[cfg(feature = "std")]
+pub mod parallel;
+#[cfg(feature = "tracing")]
+pub mod erc4337;
+#[cfg(feature = "l1sload")]
+pub mod l1sload;
+pub mod prelude;
@@ -0,0 +1,210 @@
+//! Parallel simulation of independent transactions.
+//!
+//! Backends are only read during execution, all changes being buffered in the
+//! `MemoryStackState` of each executor. A backend that is `Sync`, such as
+//! `MemoryBackend`, can thus be shared by executors running on several
+//! threads, for example to evaluate candidate transactions when building a
+//! block. Simulations do not see the changes of each other.
+
+use std::thread;
+use core::cell::RefCell;
+use alloc::vec::Vec;
+use alloc::collections::BTreeSet;
+use primitive_types::{H160, H256, U256};
+use crate::{Config, ExitReason};
+use crate::backend::{Apply, Backend, Basic, Log};
+use crate::executor::{StackExecutor, MemoryStackState, StackSubstateMetadata};
+
+/// State changes of simulations, to be applied with `ApplyBackend::apply`.
+pub type Changes = Vec<Apply<Vec<(H256, H256)>>>;
+
+/// Transaction to simulate.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Simulation {
+	/// Caller of the transaction.
+	pub caller: H160,
+	/// Transaction target. `None` means a create transaction.
+	pub to: Option<H160>,
+	/// Transaction value.
+	pub value: U256,
+	/// Transaction data.
+	pub data: Vec<u8>,
+	/// Transaction gas limit.
+	pub gas_limit: u64,
+}
+
+/// Result of a simulation. The changes are not applied to the backend.
+#[derive(Clone, Debug)]
+pub struct SimulationResult {
+	/// Exit reason.
+	pub exit_reason: ExitReason,
+	/// Return data for calls, empty for creates.
+	pub output: Vec<u8>,
+	/// Gas used after refunds.
+	pub used_gas: u64,
+	/// State changes.
+	pub values: Changes,
+	/// Logs emitted.
+	pub logs: Vec<Log>,
+	/// Addresses whose account was read from the backend.
+	pub reads: BTreeSet<H160>,
+}
+
+/// Backend recording the accounts read from the inner backend.
+struct ReadRecorder<'backend, B> {
+	backend: &'backend B,
+	reads: RefCell<BTreeSet<H160>>,
+}
+
+impl<'backend, B: Backend> ReadRecorder<'backend, B> {
+	fn read(&self, address: H160) -> &'backend B {
+		self.reads.borrow_mut().insert(address);
+		self.backend
+	}
+}
+
+impl<'backend, B: Backend> Backend for ReadRecorder<'backend, B> {
+	fn gas_price(&self) -> U256 { self.backend.gas_price() }
+	fn origin(&self) -> H160 { self.backend.origin() }
+	fn block_hash(&self, number: U256) -> H256 { self.backend.block_hash(number) }
+	fn block_number(&self) -> U256 { self.backend.block_number() }
+	fn block_coinbase(&self) -> H160 { self.backend.block_coinbase() }
+	fn block_timestamp(&self) -> U256 { self.backend.block_timestamp() }
+	fn block_difficulty(&self) -> U256 { self.backend.block_difficulty() }
+	fn block_gas_limit(&self) -> U256 { self.backend.block_gas_limit() }
+	fn chain_id(&self) -> U256 { self.backend.chain_id() }
+
+	fn exists(&self, address: H160) -> bool { self.read(address).exists(address) }
+	fn basic(&self, address: H160) -> Basic { self.read(address).basic(address) }
+	fn code(&self, address: H160) -> Vec<u8> { self.read(address).code(address) }
+	fn code_chunk(&self, address: H160, offset: usize, len: usize) -> Vec<u8> {
+		self.read(address).code_chunk(address, offset, len)
+	}
+	fn storage(&self, address: H160, index: H256) -> H256 {
+		self.read(address).storage(address, index)
+	}
+	fn original_storage(&self, address: H160, index: H256) -> Option<H256> {
+		self.read(address).original_storage(address, index)
+	}
+}
+
+/// Simulate a single transaction on top of the backend.
+pub fn simulate<B: Backend>(
+	backend: &B,
+	config: &Config,
+	simulation: &Simulation,
+) -> SimulationResult {
+	let recorder = ReadRecorder { backend, reads: RefCell::new(BTreeSet::new()) };
+	let metadata = StackSubstateMetadata::new(simulation.gas_limit, config);
+	let state = MemoryStackState::new(metadata, &recorder);
+	let mut executor = StackExecutor::new(state, config);
+
+	let (exit_reason, output) = match simulation.to {
+		Some(to) => executor.transact_call(
+			simulation.caller,
+			to,
+			simulation.value,
+			simulation.data.clone(),
+			simulation.gas_limit,
+		),
+		None => (executor.transact_create(
+			simulation.caller,
+			simulation.value,
+			simulation.data.clone(),
+			simulation.gas_limit,
+		), Vec::new()),
+	};
+	let used_gas = executor.used_gas();
+
+	let (values, logs) = executor.into_state().deconstruct();
+	let values = values.into_iter().map(|apply| match apply {
+		Apply::Modify { address, basic, code, storage, reset_storage } => {
+			let storage = storage.into_iter().collect();
+			Apply::Modify { address, basic, code, storage, reset_storage }
+		},
+		Apply::Delete { address } => Apply::Delete { address },
+	}).collect();
+	let logs = logs.into_iter().collect();
+	let reads = recorder.reads.into_inner();
+
+	SimulationResult { exit_reason, output, used_gas, values, logs, reads }
+}
+
+/// Results of simulations run in parallel, merged in the order of the
+/// simulations.
+#[derive(Clone, Debug)]
+pub struct ParallelSimulation {
+	/// Result of each simulation.
+	pub results: Vec<SimulationResult>,
+	/// Gas used by all simulations.
+	pub used_gas: u64,
+	/// Addresses changed by a simulation and read or changed by a later one.
+	/// The merged changes match a sequential execution only if there are
+	/// none.
+	pub conflicts: BTreeSet<H160>,
+}
+
+impl ParallelSimulation {
+	/// Merge simulation results, given in order.
+	pub fn merge(results: Vec<SimulationResult>) -> Self {
+		let mut used_gas = 0u64;
+		let mut changed = BTreeSet::new();
+		let mut conflicts = BTreeSet::new();
+
+		for result in &results {
+			used_gas = used_gas.saturating_add(result.used_gas);
+
+			let writes = result.values.iter().map(|apply| match apply {
+				Apply::Modify { address, .. } | Apply::Delete { address } => *address,
+			}).collect::<BTreeSet<_>>();
+			conflicts.extend(result.reads.union(&writes).filter(|address| changed.contains(*address)));
+			changed.extend(writes);
+		}
+
+		Self { results, used_gas, conflicts }
+	}
+
+	/// State changes and logs of all simulations, in order, to be applied
+	/// with `ApplyBackend::apply`.
+	pub fn into_changes(self) -> (Changes, Vec<Log>) {
+		let mut values = Vec::new();
+		let mut logs = Vec::new();
+		for result in self.results {
+			values.extend(result.values);
+			logs.extend(result.logs);
+		}
+
+		(values, logs)
+	}
+}
+
+/// Simulate independent transactions on up to `workers` threads sharing the
+/// backend, and merge the results of the workers.
+pub fn simulate_parallel<B: Backend + Sync>(
+	backend: &B,
+	config: &Config,
+	simulations: &[Simulation],
+	workers: usize,
+) -> ParallelSimulation {
+	if simulations.is_empty() {
+		return ParallelSimulation::merge(Vec::new())
+	}
+	let workers = workers.max(1);
+	let chunk_size = (simulations.len() + workers - 1) / workers;
+
+	let results = thread::scope(|scope| {
+		let handles = simulations.chunks(chunk_size).map(|chunk| {
+			scope.spawn(move || {
+				chunk.iter()
+					.map(|simulation| simulate(backend, config, simulation))
+					.collect::<Vec<_>>()
+			})
+		}).collect::<Vec<_>>();
+
+		handles.into_iter()
+			.flat_map(|handle| handle.join().expect("simulation thread panicked"))
+			.collect()
+	});
+
+	ParallelSimulation::merge(results)
+}
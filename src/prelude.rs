@@ -0,0 +1,26 @@
+//! Commonly used types, re-exported under a single path.
+//!
+//! ```ignore
+//! use evm::prelude::*;
+//! ```
+
+pub use crate::{
+	Config, Opcode, Machine, Runtime, Handler, Context, Transfer, CreateScheme, CallScheme,
+	Capture, Trap, ExitReason, ExitSucceed, ExitError, ExitRevert, ExitFatal,
+	ForkSchedule, ForkActivation, RevertReason, revert_reason,
+};
+pub use crate::gasometer::Gasometer;
+pub use crate::backend::{
+	Backend, ApplyBackend, Apply, Basic, Log, MemoryBackend, MemoryVicinity, MemoryAccount,
+};
+pub use crate::executor::{
+	StackExecutor, MemoryStackExecutor, StackState, MemoryStackState, StackSubstateMetadata,
+	PrecompileOutput, CallArgs, CreateArgs,
+};
+
+#[cfg(feature = "tracing")]
+pub use crate::tracing::{
+	EventListener,
+	runtime::EventListener as RuntimeEventListener,
+	gasometer::EventListener as GasometerEventListener,
+};
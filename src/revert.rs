@@ -0,0 +1,104 @@
+//! Decoding of Solidity revert data.
+
+use alloc::{string::String, vec::Vec};
+use primitive_types::U256;
+
+/// Selector of `Error(string)`.
+const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+/// Selector of `Panic(uint256)`.
+const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+
+/// Reason of a revert, decoded from its return data.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum RevertReason {
+	/// No return data, as with `revert()` or a failed `require` without
+	/// message.
+	Empty,
+	/// `Error(string)`, as with `revert("message")`.
+	Error(String),
+	/// `Panic(uint256)`, raised by the Solidity compiler on assertion
+	/// failures, arithmetic overflows and similar.
+	Panic(U256),
+	/// Custom error, with its selector and ABI-encoded arguments.
+	Custom {
+		/// Error selector.
+		selector: [u8; 4],
+		/// ABI-encoded arguments.
+		data: Vec<u8>,
+	},
+	/// Data too short for a selector, or an `Error(string)` payload that is
+	/// not valid ABI encoding.
+	Unknown(Vec<u8>),
+}
+
+/// Decode the return data of a revert.
+pub fn revert_reason(data: &[u8]) -> RevertReason {
+	if data.is_empty() {
+		return RevertReason::Empty
+	}
+	if data.len() < 4 {
+		return RevertReason::Unknown(data.to_vec())
+	}
+
+	let mut selector = [0u8; 4];
+	selector.copy_from_slice(&data[..4]);
+	let args = &data[4..];
+
+	match selector {
+		ERROR_SELECTOR => match decode_string(args) {
+			Some(message) => RevertReason::Error(message),
+			None => RevertReason::Unknown(data.to_vec()),
+		},
+		PANIC_SELECTOR if args.len() == 32 => RevertReason::Panic(U256::from_big_endian(args)),
+		_ => RevertReason::Custom { selector, data: args.to_vec() },
+	}
+}
+
+fn decode_word(data: &[u8], offset: usize) -> Option<usize> {
+	let word = data.get(offset..offset.checked_add(32)?)?;
+	let value = U256::from_big_endian(word);
+	if value > U256::from(usize::MAX) {
+		None
+	} else {
+		Some(value.as_usize())
+	}
+}
+
+fn decode_string(data: &[u8]) -> Option<String> {
+	let offset = decode_word(data, 0)?;
+	let len = decode_word(data, offset)?;
+	let start = offset.checked_add(32)?;
+	let bytes = data.get(start..start.checked_add(len)?)?;
+
+	String::from_utf8(bytes.to_vec()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn decode_revert_reasons() {
+		let error = hex::decode(
+			"08c379a0\
+			 0000000000000000000000000000000000000000000000000000000000000020\
+			 000000000000000000000000000000000000000000000000000000000000000c\
+			 696e73756666696369656e740000000000000000000000000000000000000000"
+		).unwrap();
+		let panic = hex::decode(
+			"4e487b71\
+			 0000000000000000000000000000000000000000000000000000000000000011"
+		).unwrap();
+		let custom = hex::decode("cafebabe01").unwrap();
+
+		assert_eq!(revert_reason(&[]), RevertReason::Empty);
+		assert_eq!(revert_reason(&error), RevertReason::Error("insufficient".into()));
+		assert_eq!(revert_reason(&error[..40]), RevertReason::Unknown(error[..40].to_vec()));
+		assert_eq!(revert_reason(&panic), RevertReason::Panic(U256::from(0x11)));
+		assert_eq!(
+			revert_reason(&custom),
+			RevertReason::Custom { selector: [0xca, 0xfe, 0xba, 0xbe], data: vec![0x01] },
+		);
+		assert_eq!(revert_reason(&[0x01]), RevertReason::Unknown(vec![0x01]));
+	}
+}
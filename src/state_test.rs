@@ -0,0 +1,225 @@
+//! Generator of `GeneralStateTest` fillers from programmatic scenarios.
+//!
+//! A scenario is a pre-state, a block environment and a single transaction.
+//! The transaction is executed with the stack executor, and the resulting
+//! post-state is recorded as the `expect` section of the filler, so that
+//! regression tests can be written in the format consumed by other clients.
+
+use core::fmt::{self, Write};
+use alloc::{string::String, vec::Vec, collections::BTreeMap};
+use primitive_types::{H160, H256, U256};
+use crate::{Config, ExitReason};
+use crate::backend::{MemoryBackend, MemoryVicinity, MemoryAccount, ApplyBackend};
+use crate::executor::{StackExecutor, MemoryStackState, StackSubstateMetadata};
+
+/// Transaction of a state test.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StateTestTransaction {
+	/// Secret key signing the transaction. It must correspond to `sender`.
+	pub secret_key: H256,
+	/// Sender of the transaction.
+	pub sender: H160,
+	/// Transaction target. `None` means a create transaction.
+	pub to: Option<H160>,
+	/// Transaction nonce.
+	pub nonce: U256,
+	/// Transaction value.
+	pub value: U256,
+	/// Transaction data.
+	pub data: Vec<u8>,
+	/// Transaction gas limit.
+	pub gas_limit: u64,
+}
+
+/// Result of executing a state test transaction.
+#[derive(Clone, Debug)]
+pub struct StateTestOutcome {
+	/// Exit reason. `None` if the sender could not pay for the gas limit, in
+	/// which case the transaction is not executed.
+	pub exit_reason: Option<ExitReason>,
+	/// Gas used after refunds.
+	pub used_gas: u64,
+	/// State after the transaction.
+	pub post: BTreeMap<H160, MemoryAccount>,
+}
+
+impl StateTestTransaction {
+	/// Execute the transaction on top of `pre`. The sender pays the gas price
+	/// of the vicinity, and fees are credited to the block coinbase.
+	pub fn execute(
+		&self,
+		config: &Config,
+		vicinity: &MemoryVicinity,
+		pre: BTreeMap<H160, MemoryAccount>,
+	) -> StateTestOutcome {
+		let mut backend = MemoryBackend::new(vicinity, pre);
+		let metadata = StackSubstateMetadata::new(self.gas_limit, config);
+		let state = MemoryStackState::new(metadata, &backend);
+		let mut executor = StackExecutor::new(state, config);
+
+		let total_fee = vicinity.gas_price * U256::from(self.gas_limit);
+		if executor.state_mut().withdraw(self.sender, total_fee).is_err() {
+			return StateTestOutcome {
+				exit_reason: None,
+				used_gas: 0,
+				post: backend.state().clone(),
+			}
+		}
+
+		let exit_reason = match self.to {
+			Some(to) => executor.transact_call(
+				self.sender,
+				to,
+				self.value,
+				self.data.clone(),
+				self.gas_limit,
+			).0,
+			None => executor.transact_create(
+				self.sender,
+				self.value,
+				self.data.clone(),
+				self.gas_limit,
+			),
+		};
+
+		let used_gas = executor.used_gas();
+		let actual_fee = executor.fee(vicinity.gas_price);
+		executor.state_mut().deposit(vicinity.block_coinbase, actual_fee);
+		executor.state_mut().deposit(self.sender, total_fee - actual_fee);
+
+		let (values, logs) = executor.into_state().deconstruct();
+		backend.apply(values, logs, config.clear_empty_touched_accounts);
+
+		StateTestOutcome {
+			exit_reason: Some(exit_reason),
+			used_gas,
+			post: backend.state().clone(),
+		}
+	}
+}
+
+/// A filled state test.
+#[derive(Clone, Debug)]
+pub struct StateTest {
+	/// Test name.
+	pub name: String,
+	/// Network name the test is filled for, such as `Istanbul`.
+	pub network: String,
+	/// Block environment. Its gas price is used as the transaction gas price.
+	pub vicinity: MemoryVicinity,
+	/// State before the transaction.
+	pub pre: BTreeMap<H160, MemoryAccount>,
+	/// The transaction.
+	pub transaction: StateTestTransaction,
+	/// State after the transaction, computed by the executor.
+	pub post: BTreeMap<H160, MemoryAccount>,
+}
+
+impl StateTest {
+	/// Run the transaction on top of `pre` and record the post-state.
+	pub fn fill(
+		name: String,
+		network: String,
+		config: &Config,
+		vicinity: MemoryVicinity,
+		pre: BTreeMap<H160, MemoryAccount>,
+		transaction: StateTestTransaction,
+	) -> Self {
+		let post = transaction.execute(config, &vicinity, pre.clone()).post;
+
+		Self { name, network, vicinity, pre, transaction, post }
+	}
+
+	/// Serialize the test as a `GeneralStateTest` filler JSON.
+	pub fn to_json(&self) -> String {
+		let mut out = String::new();
+		self.write_json(&mut out).expect("Writing to a String cannot fail");
+		out
+	}
+
+	fn write_json<W: Write>(&self, out: &mut W) -> fmt::Result {
+		let tx = &self.transaction;
+
+		write!(out, "{{\"{}\":{{", self.name)?;
+		write!(
+			out,
+			"\"env\":{{\"currentCoinbase\":\"{:#x}\",\"currentDifficulty\":\"{:#x}\",\
+			 \"currentGasLimit\":\"{:#x}\",\"currentNumber\":\"{:#x}\",\"currentTimestamp\":\"{:#x}\"}},",
+			self.vicinity.block_coinbase,
+			self.vicinity.block_difficulty,
+			self.vicinity.block_gas_limit,
+			self.vicinity.block_number,
+			self.vicinity.block_timestamp,
+		)?;
+
+		write!(out, "\"pre\":")?;
+		write_accounts(out, &self.pre)?;
+
+		write!(out, ",\"transaction\":{{\"data\":[\"")?;
+		write_bytes(out, &tx.data)?;
+		write!(
+			out,
+			"\"],\"gasLimit\":[\"{:#x}\"],\"gasPrice\":\"{:#x}\",\"nonce\":\"{:#x}\",\
+			 \"secretKey\":\"{:#x}\",\"to\":\"",
+			tx.gas_limit,
+			self.vicinity.gas_price,
+			tx.nonce,
+			tx.secret_key,
+		)?;
+		if let Some(to) = tx.to {
+			write!(out, "{:#x}", to)?;
+		}
+		write!(out, "\",\"value\":[\"{:#x}\"]}},", tx.value)?;
+
+		write!(
+			out,
+			"\"expect\":[{{\"indexes\":{{\"data\":-1,\"gas\":-1,\"value\":-1}},\
+			 \"network\":[\"{}\"],\"result\":",
+			self.network,
+		)?;
+		write_accounts(out, &self.post)?;
+		write!(out, "}}]}}}}")
+	}
+}
+
+fn write_bytes<W: Write>(out: &mut W, bytes: &[u8]) -> fmt::Result {
+	write!(out, "0x")?;
+	for byte in bytes {
+		write!(out, "{:02x}", byte)?;
+	}
+	Ok(())
+}
+
+fn write_accounts<W: Write>(
+	out: &mut W,
+	accounts: &BTreeMap<H160, MemoryAccount>,
+) -> fmt::Result {
+	write!(out, "{{")?;
+	for (i, (address, account)) in accounts.iter().enumerate() {
+		if i != 0 {
+			write!(out, ",")?;
+		}
+
+		write!(
+			out,
+			"\"{:#x}\":{{\"balance\":\"{:#x}\",\"code\":\"",
+			address,
+			account.balance,
+		)?;
+		write_bytes(out, &account.code)?;
+		write!(out, "\",\"nonce\":\"{:#x}\",\"storage\":{{", account.nonce)?;
+		for (j, (key, value)) in account.storage.iter().enumerate() {
+			if j != 0 {
+				write!(out, ",")?;
+			}
+			write!(
+				out,
+				"\"{:#x}\":\"{:#x}\"",
+				U256::from_big_endian(&key[..]),
+				U256::from_big_endian(&value[..]),
+			)?;
+		}
+		write!(out, "}}}}")?;
+	}
+	write!(out, "}}")
+}
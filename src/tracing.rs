@@ -1,8 +1,15 @@
 //! Allows to listen to runtime events.
 
-use crate::Context;
+use alloc::boxed::Box;
+use crate::{Context, Opcode};
+use crate::executor::StackExitKind;
 use evm_runtime::{CreateScheme, Transfer};
 use primitive_types::{H160, U256};
+#[cfg(feature = "l1sload")]
+use primitive_types::H256;
+
+pub use evm_runtime::tracing as runtime;
+pub use evm_gasometer::tracing as gasometer;
 
 environmental::environmental!(listener: dyn EventListener + 'static);
 
@@ -36,6 +43,78 @@ pub enum Event<'a> {
 		target: H160,
         balance: U256,
     },
+    /// Precompile executed. The cost and output length are zero if it
+    /// failed.
+    PrecompileCall {
+        address: H160,
+        input_length: usize,
+        cost: u64,
+        output_length: usize,
+        success: bool,
+    },
+    /// Create failed because the target address already has code or a
+    /// non-zero nonce.
+    CreateCollision {
+        address: H160,
+        code_size: U256,
+        nonce: U256,
+    },
+    /// Substate entered for a call or create.
+    EnterFrame {
+        depth: usize,
+        gas_limit: u64,
+        is_static: bool,
+    },
+    /// Substate about to be exited. `used_gas` includes the gas not
+    /// returned by sub-calls, and `refunded_gas` the refunds committed by
+    /// them. On failure the gas is not returned to the parent, and on revert
+    /// the refund is dropped. `logs` is the number of logs emitted by the
+    /// frame and its committed sub-calls, which are discarded unless it
+    /// succeeded, and `burnt_gas` the gas not returned to the parent.
+    ExitFrame {
+        depth: usize,
+        kind: StackExitKind,
+        gas_limit: u64,
+        used_gas: u64,
+        refunded_gas: i64,
+        logs: usize,
+        burnt_gas: u64,
+    },
+    /// Opcode ran out of gas. The gas needed is `gas_cost + memory_cost`.
+    OutOfGas {
+        address: H160,
+        opcode: Opcode,
+        gas_cost: u64,
+        memory_cost: u64,
+        gas_left: u64,
+    },
+    /// `BLOCKHASH` queried a block outside of the configured history window.
+    /// The hash returned is zero.
+    BlockHashOutOfWindow {
+        number: U256,
+        block_number: U256,
+    },
+    /// Suicide with the contract itself as beneficiary. The balance is burnt.
+    SuicideBurn {
+        address: H160,
+        balance: U256,
+    },
+    /// Suicide of a contract already suicided in the same transaction. The
+    /// balance received since then is moved again, or burnt if the target
+    /// is the contract itself.
+    SuicideRepeated {
+        address: H160,
+        target: H160,
+        balance: U256,
+    },
+    /// L1 storage slot queried by L1SLOAD. The value is `None` if L1 state
+    /// was unavailable.
+    #[cfg(feature = "l1sload")]
+    L1Sload {
+        address: H160,
+        key: H256,
+        value: Option<H256>,
+    },
 }
 
 impl<'a> Event<'a> {
@@ -51,3 +130,55 @@ pub fn using<R, F: FnOnce() -> R>(
 ) -> R {
     listener::using(new, f)
 }
+
+/// Listener ignoring all events.
+struct NoopListener;
+
+impl EventListener for NoopListener {
+    fn event(&mut self, _event: Event) { }
+}
+
+impl runtime::EventListener for NoopListener {
+    fn event(&mut self, _event: runtime::Event) { }
+}
+
+impl gasometer::EventListener for NoopListener {
+    fn event(&mut self, _event: gasometer::Event) { }
+}
+
+/// Listeners attached to an executor instance rather than installed on the
+/// thread, see `StackExecutor::set_listeners`.
+#[derive(Default)]
+pub struct Listeners {
+    /// Listener of executor events.
+    pub executor: Option<Box<dyn EventListener>>,
+    /// Listener of runtime events.
+    pub runtime: Option<Box<dyn runtime::EventListener>>,
+    /// Listener of gasometer events.
+    pub gasometer: Option<Box<dyn gasometer::EventListener>>,
+}
+
+impl Listeners {
+    /// Run closure with these listeners. They shadow the listeners installed
+    /// with `using` on this thread, and missing ones ignore events, so that
+    /// nothing leaks to or from other executions.
+    pub fn using<R, F: FnOnce() -> R>(&mut self, f: F) -> R {
+        let (mut noop_executor, mut noop_runtime, mut noop_gasometer) =
+            (NoopListener, NoopListener, NoopListener);
+
+        let executor: &mut (dyn EventListener + 'static) = match self.executor.as_deref_mut() {
+            Some(listener) => listener,
+            None => &mut noop_executor,
+        };
+        let runtime: &mut (dyn runtime::EventListener + 'static) = match self.runtime.as_deref_mut() {
+            Some(listener) => listener,
+            None => &mut noop_runtime,
+        };
+        let gasometer: &mut (dyn gasometer::EventListener + 'static) = match self.gasometer.as_deref_mut() {
+            Some(listener) => listener,
+            None => &mut noop_gasometer,
+        };
+
+        using(executor, || runtime::using(runtime, || gasometer::using(gasometer, f)))
+    }
+}
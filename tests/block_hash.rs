@@ -0,0 +1,95 @@
+//! `BLOCKHASH` window of the executor, over backends holding more history.
+
+use std::collections::BTreeMap;
+use primitive_types::{H256, U256};
+use evm::{Config, Handler};
+use evm::backend::{Backend, LayeredBackend, MemoryBackend, MemoryVicinity};
+
+mod common;
+
+/// Hash of block `number` in the tests.
+fn hash(number: u64) -> H256 {
+	H256::from_low_u64_be(number + 1)
+}
+
+/// Vicinity at block 1000, with the hashes of blocks 0 to 999, keeping 300
+/// hashes when pushing blocks.
+fn vicinity() -> MemoryVicinity {
+	MemoryVicinity {
+		block_hashes: (0..1000).rev().map(hash).collect(),
+		max_block_hashes: 300,
+		block_number: U256::from(1000),
+		..common::vicinity()
+	}
+}
+
+/// Hash of block `number` as seen by `BLOCKHASH`.
+fn block_hash<B: Backend>(backend: &B, config: &Config, number: u64) -> H256 {
+	let executor = common::executor(backend, config, u64::MAX);
+	executor.block_hash(U256::from(number))
+}
+
+#[test]
+fn backend_keeps_the_supplied_history() {
+	let vicinity = vicinity();
+	let backend = MemoryBackend::new(&vicinity, BTreeMap::new());
+
+	assert_eq!(backend.block_hash(U256::from(999)), hash(999));
+	assert_eq!(backend.block_hash(U256::from(743)), hash(743));
+	assert_eq!(backend.block_hash(U256::zero()), hash(0));
+	assert_eq!(backend.block_hash(U256::from(1000)), H256::default());
+}
+
+#[test]
+fn executor_window_boundary() {
+	let config = Config::istanbul();
+	let vicinity = vicinity();
+	let backend = MemoryBackend::new(&vicinity, BTreeMap::new());
+
+	assert_eq!(block_hash(&backend, &config, 999), hash(999));
+	assert_eq!(block_hash(&backend, &config, 744), hash(744));
+	assert_eq!(block_hash(&backend, &config, 743), H256::default());
+	assert_eq!(block_hash(&backend, &config, 1000), H256::default());
+	assert_eq!(block_hash(&backend, &config, 1001), H256::default());
+}
+
+#[test]
+fn executor_window_longer_than_256() {
+	let config = Config {
+		block_hash_history: 300,
+		..Config::istanbul()
+	};
+	let vicinity = vicinity();
+	let backend = MemoryBackend::new(&vicinity, BTreeMap::new());
+
+	assert_eq!(block_hash(&backend, &config, 743), hash(743));
+	assert_eq!(block_hash(&backend, &config, 700), hash(700));
+	assert_eq!(block_hash(&backend, &config, 699), H256::default());
+}
+
+#[test]
+fn pushed_blocks_roll_the_history() {
+	let config = Config::istanbul();
+	let vicinity = vicinity();
+	let mut memory = MemoryBackend::new(&vicinity, BTreeMap::new());
+	let mut layered = LayeredBackend::new(vicinity.clone(), BTreeMap::new());
+
+	for number in 1000..1300 {
+		memory.push_block(hash(number));
+		layered.begin_block();
+		layered.commit_block(hash(number));
+	}
+
+	for backend in [&memory as &dyn Backend, &layered] {
+		assert_eq!(backend.block_number(), U256::from(1300));
+		assert_eq!(backend.block_hash(U256::from(1299)), hash(1299));
+		assert_eq!(backend.block_hash(U256::from(1000)), hash(1000));
+		assert_eq!(backend.block_hash(U256::from(999)), H256::default());
+	}
+	assert_eq!(memory.vicinity().block_hashes.len(), 300);
+	assert_eq!(layered.vicinity().block_hashes.len(), 300);
+	assert_eq!(block_hash(&memory, &config, 1044), hash(1044));
+	assert_eq!(block_hash(&memory, &config, 1043), H256::default());
+	assert_eq!(block_hash(&layered, &config, 1044), hash(1044));
+	assert_eq!(block_hash(&layered, &config, 1043), H256::default());
+}
@@ -0,0 +1,43 @@
+//! Runtimes borrowing code and input for less than the config lifetime.
+
+use primitive_types::{H160, U256};
+use evm::{Capture, Config, Context, ExitReason, ExitSucceed, Runtime};
+use evm::backend::MemoryBackend;
+
+mod common;
+use common::vicinity;
+
+/// Runtime over `code` and `data`, using a config that outlives both.
+fn runtime<'code>(
+	code: &'code [u8],
+	data: &'code [u8],
+	config: &'static Config,
+) -> Runtime<'code, 'static> {
+	let context = Context {
+		address: H160::repeat_byte(0xc0),
+		caller: H160::repeat_byte(0xca),
+		apparent_value: U256::zero(),
+	};
+	Runtime::new_borrowed(code, data, context, config)
+}
+
+#[test]
+fn short_lived_code() {
+	let config: &'static Config = Box::leak(Box::new(Config::istanbul()));
+	let vicinity = vicinity();
+	let backend = MemoryBackend::new(&vicinity, Default::default());
+	let mut executor = common::executor(&backend, config, u64::MAX);
+
+	for value in 0..3u8 {
+		// PUSH1 32; PUSH1 0; PUSH1 0; CALLDATACOPY; PUSH1 32; PUSH1 0; RETURN
+		let code = hex::decode("6020600060003760206000f3").unwrap();
+		let data = vec![value; 32];
+		let mut runtime = runtime(&code, &data, config);
+
+		match runtime.run(&mut executor) {
+			Capture::Exit(reason) => assert_eq!(reason, ExitReason::Succeed(ExitSucceed::Returned)),
+			Capture::Trap(_) => panic!("unexpected trap"),
+		}
+		assert_eq!(runtime.machine().return_value(), data);
+	}
+}
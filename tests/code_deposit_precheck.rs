@@ -0,0 +1,87 @@
+//! Creates failing early when their gas cannot cover the largest code
+//! deposit, with `Config::precheck_code_deposit`.
+
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+use primitive_types::{H160, U256};
+use evm::{Config, ExitError, ExitReason};
+use evm::backend::{MemoryAccount, MemoryBackend};
+use evm::executor::Watchdog;
+use evm::gasometer::{code_deposit_cost, max_code_deposit_cost};
+
+mod common;
+use common::{vicinity, caller};
+
+/// Intrinsic gas of the create, see `create`.
+const INTRINSIC_GAS: u64 = 53000 + 2 * 16 + 2 * 4;
+/// Deposit of the largest code under Istanbul.
+const MAX_DEPOSIT: u64 = 0x6000 * 200;
+
+/// Watchdog counting the frames entered at depth 0.
+struct Entered(Rc<Cell<usize>>);
+
+impl Watchdog for Entered {
+	fn depth_thresholds(&self) -> &[usize] {
+		&[0]
+	}
+
+	fn on_depth(&mut self, _depth: usize) -> Result<(), ExitError> {
+		self.0.set(self.0.get() + 1);
+		Ok(())
+	}
+}
+
+/// Deploy empty code with `gas_limit`. Return the exit reason, the number of
+/// executed instructions and of frames entered.
+fn create(precheck: bool, gas_limit: u64) -> (ExitReason, u64, usize) {
+	let config = Config { precheck_code_deposit: precheck, ..Config::istanbul() };
+	let vicinity = vicinity();
+	let backend = MemoryBackend::new(&vicinity, BTreeMap::<H160, MemoryAccount>::new());
+	let mut executor = common::executor(&backend, &config, gas_limit);
+	executor.set_instruction_limit(Some(u64::MAX));
+	let entered = Rc::new(Cell::new(0));
+	executor.set_watchdog(Box::new(Entered(entered.clone())));
+
+	// PUSH1 0; POP; STOP
+	let reason = executor.transact_create(caller(), U256::zero(), vec![0x60, 0x00, 0x50, 0x00], gas_limit);
+	(reason, executor.instructions(), entered.get())
+}
+
+#[test]
+fn deposit_costs() {
+	assert_eq!(code_deposit_cost(0), 0);
+	assert_eq!(code_deposit_cost(10), 2000);
+	assert_eq!(code_deposit_cost(usize::MAX), u64::MAX);
+	assert_eq!(max_code_deposit_cost(&Config::istanbul()), Some(MAX_DEPOSIT));
+	assert_eq!(max_code_deposit_cost(&Config::frontier()), None);
+}
+
+#[test]
+fn precheck_fails_before_entering_the_frame() {
+	let (reason, instructions, entered) = create(true, 1_000_000);
+	assert_eq!(reason, ExitReason::Error(ExitError::OutOfGas));
+	assert_eq!(instructions, 0);
+	assert_eq!(entered, 0);
+}
+
+#[test]
+fn precheck_passes() {
+	// Enough for the intrinsic gas and the largest deposit.
+	let (reason, instructions, entered) = create(true, INTRINSIC_GAS + MAX_DEPOSIT);
+	assert!(reason.is_succeed());
+	assert_eq!(instructions, 3);
+	assert_eq!(entered, 1);
+
+	let (reason, instructions, _) = create(true, INTRINSIC_GAS + MAX_DEPOSIT - 1);
+	assert_eq!(reason, ExitReason::Error(ExitError::OutOfGas));
+	assert_eq!(instructions, 0);
+}
+
+#[test]
+fn no_precheck() {
+	let (reason, instructions, entered) = create(false, 1_000_000);
+	assert!(reason.is_succeed());
+	assert_eq!(instructions, 3);
+	assert_eq!(entered, 1);
+}
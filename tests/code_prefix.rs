@@ -0,0 +1,34 @@
+//! Rejection of deployed code starting with 0xEF (EIP-3541).
+
+use std::collections::BTreeMap;
+use primitive_types::U256;
+use evm::{Config, ExitError, ExitReason};
+use evm::backend::{MemoryAccount, MemoryBackend};
+
+mod common;
+use common::{vicinity, caller};
+
+/// Create a contract deploying the single byte `byte`.
+fn deploy(config: &Config, byte: u8) -> ExitReason {
+	let vicinity = vicinity();
+	let mut accounts = BTreeMap::new();
+	accounts.insert(caller(), MemoryAccount { balance: U256::from(10_000_000), ..Default::default() });
+	let backend = MemoryBackend::new(&vicinity, accounts);
+	let mut executor = common::executor(&backend, config, u64::MAX);
+
+	// PUSH1 byte; PUSH1 0; MSTORE8; PUSH1 1; PUSH1 0; RETURN
+	let init_code = vec![0x60, byte, 0x60, 0x00, 0x53, 0x60, 0x01, 0x60, 0x00, 0xf3];
+	executor.transact_create(caller(), U256::zero(), init_code, 100_000)
+}
+
+#[test]
+fn prefix_is_rejected_when_disallowed() {
+	let config = Config { disallow_executable_format: true, ..Config::istanbul() };
+	assert_eq!(deploy(&config, 0xef), ExitReason::Error(ExitError::InvalidCodePrefix));
+	assert!(deploy(&config, 0xfe).is_succeed());
+}
+
+#[test]
+fn prefix_is_allowed_by_default() {
+	assert!(deploy(&Config::istanbul(), 0xef).is_succeed());
+}
@@ -0,0 +1,77 @@
+//! Commitments maintained by `CommittingBackend` while applying changes are
+//! the ones computed from scratch on the resulting state.
+
+use primitive_types::{H256, U256};
+use evm::backend::{Apply, ApplyBackend, Basic, CommittingBackend, AccountMismatch, MemoryBackend};
+use evm::generator::Generator;
+
+mod common;
+use common::vicinity;
+
+#[test]
+fn incremental_commitments_match_recomputed() {
+	let vicinity = vicinity();
+
+	for seed in 0..64 {
+		let mut generator = Generator::new(seed);
+		let state = generator.state(16, 8, 32);
+		let delete_empty = generator.below(2) == 0;
+
+		let mut backend = CommittingBackend::new(MemoryBackend::new(&vicinity, state.clone()), &state).unwrap();
+
+		for _ in 0..4 {
+			let changes = generator.changes(backend.inner().state(), 16);
+			backend.apply(changes, Vec::new(), delete_empty);
+
+			let post = backend.inner().state().clone();
+			let recomputed = CommittingBackend::from_memory(MemoryBackend::new(&vicinity, post.clone()));
+			assert_eq!(backend.state_commitment(), recomputed.state_commitment(), "seed {}", seed);
+			for address in post.keys() {
+				assert_eq!(
+					backend.storage_commitment(*address),
+					recomputed.storage_commitment(*address),
+					"seed {}", seed,
+				);
+			}
+		}
+	}
+}
+
+#[test]
+fn accounts_must_match_inner() {
+	let vicinity = vicinity();
+	let mut generator = Generator::new(0);
+	let state = generator.state(4, 2, 8);
+	let mut other = state.clone();
+	let (address, account) = other.iter_mut().next().unwrap();
+	account.balance += U256::one();
+	let address = *address;
+
+	assert_eq!(
+		CommittingBackend::new(MemoryBackend::new(&vicinity, state), &other).err(),
+		Some(AccountMismatch { address }),
+	);
+}
+
+#[test]
+fn single_slot_changes_commitments() {
+	let vicinity = vicinity();
+	let mut generator = Generator::new(0);
+	let state = generator.state(4, 2, 8);
+	let (address, account) = state.iter().next().map(|(a, b)| (*a, b.clone())).unwrap();
+
+	let mut backend = CommittingBackend::from_memory(MemoryBackend::new(&vicinity, state));
+	let state_commitment = backend.state_commitment();
+	let storage_commitment = backend.storage_commitment(address);
+
+	backend.apply(vec![Apply::Modify {
+		address,
+		basic: Basic { balance: account.balance, nonce: account.nonce },
+		code: None,
+		storage: vec![(H256::repeat_byte(0x42), H256::from_low_u64_be(1))],
+		reset_storage: false,
+	}], Vec::new(), false);
+
+	assert_ne!(backend.state_commitment(), state_commitment);
+	assert_ne!(backend.storage_commitment(address), storage_commitment);
+}
@@ -0,0 +1,48 @@
+//! Fixtures shared by the integration tests.
+
+#![allow(dead_code)]
+
+use primitive_types::{H160, U256};
+use evm::Config;
+use evm::backend::{Backend, MemoryVicinity};
+use evm::executor::{StackExecutor, MemoryStackState, StackSubstateMetadata};
+
+/// Gas limit of the transactions of the tests.
+pub const GAS_LIMIT: u64 = 100_000;
+
+/// Sender of the transactions of the tests.
+pub fn caller() -> H160 { H160::repeat_byte(0xca) }
+/// Contract called by the transactions of the tests.
+pub fn contract() -> H160 { H160::repeat_byte(0xc0) }
+
+/// Vicinity at block zero of chain 1, with `caller()` as origin and no block
+/// hashes.
+pub fn vicinity() -> MemoryVicinity {
+	MemoryVicinity {
+		gas_price: U256::zero(),
+		origin: caller(),
+		block_hashes: Vec::new(),
+		max_block_hashes: 256,
+		block_number: Default::default(),
+		block_coinbase: Default::default(),
+		block_timestamp: Default::default(),
+		block_difficulty: Default::default(),
+		block_gas_limit: Default::default(),
+		chain_id: U256::one(),
+	}
+}
+
+/// Executor over a memory stack state on top of `backend`.
+pub type Executor<'backend, 'config, B> = StackExecutor<'config, MemoryStackState<'backend, 'config, B>>;
+
+/// Executor over a fresh memory stack state on top of `backend`, whose
+/// substate allows `gas_limit`.
+pub fn executor<'backend, 'config, B: Backend>(
+	backend: &'backend B,
+	config: &'config Config,
+	gas_limit: u64,
+) -> Executor<'backend, 'config, B> {
+	let metadata = StackSubstateMetadata::new(gas_limit, config);
+	let state = MemoryStackState::new(metadata, backend);
+	StackExecutor::new(state, config)
+}
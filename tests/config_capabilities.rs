@@ -0,0 +1,45 @@
+//! Fork names and EIPs reported by configs.
+
+use primitive_types::H256;
+use evm::Config;
+
+/// Keccak override of a host.
+fn keccak(data: &[u8]) -> H256 {
+	H256::from_low_u64_be(data.len() as u64)
+}
+
+#[test]
+fn presets_are_named() {
+	assert_eq!(Config::frontier().fork_name(), "frontier");
+	assert_eq!(Config::istanbul().fork_name(), "istanbul");
+
+	assert_eq!(Config { keccak: Some(keccak), ..Config::istanbul() }.fork_name(), "istanbul");
+	assert_eq!(Config { estimate: true, ..Config::frontier() }.fork_name(), "frontier");
+}
+
+#[test]
+fn any_differing_field_makes_a_custom_config() {
+	// Fields not tied to any EIP.
+	assert_eq!(Config { gas_sload: 2100, ..Config::istanbul() }.fork_name(), "custom");
+	assert_eq!(Config { block_hash_history: 300, ..Config::istanbul() }.fork_name(), "custom");
+	assert_eq!(Config { disable_refunds: true, ..Config::frontier() }.fork_name(), "custom");
+	assert_eq!(Config { call_stack_limit: 512, ..Config::frontier() }.fork_name(), "custom");
+
+	// Same EIPs as a preset, with a different cost.
+	let config = Config { refund_suicide: 0, ..Config::istanbul() };
+	assert_eq!(config.enabled_eips(), Config::istanbul().enabled_eips());
+	assert_eq!(config.fork_name(), "custom");
+	assert_eq!(config.capabilities().fork_name, "custom");
+}
+
+#[test]
+fn enabled_eips() {
+	assert_eq!(Config::frontier().enabled_eips(), Vec::<u32>::new());
+	assert_eq!(Config::istanbul().enabled_eips(), vec![
+		2, 7, 140, 145, 150, 160, 161, 170, 211, 1014, 1052, 1344, 1884, 2028, 2200,
+	]);
+
+	let config = Config { has_chain_id: true, ..Config::frontier() };
+	assert_eq!(config.enabled_eips(), vec![1344]);
+	assert_eq!(config.fork_name(), "custom");
+}
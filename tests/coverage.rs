@@ -0,0 +1,65 @@
+//! Coverage collected over calls between contracts.
+
+#![cfg(feature = "tracing")]
+
+use std::collections::BTreeMap;
+use primitive_types::{H160, H256, U256};
+use sha3::{Digest, Keccak256};
+use evm::Config;
+use evm::backend::{MemoryAccount, MemoryBackend};
+use evm::coverage::CoverageListener;
+use evm::tracing::runtime;
+
+mod common;
+use common::{vicinity, caller};
+
+fn outer() -> H160 { H160::repeat_byte(0x0a) }
+fn inner() -> H160 { H160::repeat_byte(0x0b) }
+
+/// Call `inner` with all the gas left, then pop the result.
+fn call_inner() -> Vec<u8> {
+	let mut code = hex::decode("60006000600060006000").unwrap();
+	code.push(0x73);
+	code.extend_from_slice(&inner()[..]);
+	code.extend_from_slice(&[0x5a, 0xf1, 0x50]);
+	code
+}
+
+fn hash(code: &[u8]) -> H256 {
+	H256::from_slice(Keccak256::digest(code).as_slice())
+}
+
+#[test]
+fn hits_are_counted_per_code() {
+	let mut outer_code = call_inner();
+	outer_code.extend(call_inner());
+	outer_code.push(0x00);
+	// PUSH1 1; POP; STOP
+	let inner_code = vec![0x60, 0x01, 0x50, 0x00];
+
+	let config = Config::istanbul();
+	let vicinity = vicinity();
+	let mut accounts = BTreeMap::new();
+	accounts.insert(outer(), MemoryAccount { code: outer_code.clone(), ..Default::default() });
+	accounts.insert(inner(), MemoryAccount { code: inner_code.clone(), ..Default::default() });
+	let backend = MemoryBackend::new(&vicinity, accounts);
+	let mut executor = common::executor(&backend, &config, u64::MAX);
+
+	let mut listener = CoverageListener::new();
+	let (reason, _) = runtime::using(&mut listener, || {
+		executor.transact_call(caller(), outer(), U256::zero(), Vec::new(), 1_000_000)
+	});
+	assert!(reason.is_succeed());
+
+	let coverage = listener.coverage();
+	assert_eq!(coverage.len(), 2);
+
+	let outer_coverage = &coverage[&hash(&outer_code)];
+	assert_eq!(outer_coverage.code(), &outer_code[..]);
+	assert!(outer_coverage.hits().values().all(|hits| *hits == 1));
+	assert_eq!(outer_coverage.executed_ranges(), vec![0..outer_code.len()]);
+
+	let inner_coverage = &coverage[&hash(&inner_code)];
+	assert_eq!(inner_coverage.hits(), &BTreeMap::from([(0, 2), (2, 2), (3, 2)]));
+	assert_eq!(inner_coverage.executed_ranges(), vec![0..4]);
+}
@@ -0,0 +1,185 @@
+//! Host-defined opcodes executed by `StackExecutor`.
+
+use std::collections::BTreeMap;
+use primitive_types::{H160, H256, U256};
+use evm::{Config, Context, CustomOpcode, ExitError, ExitReason, Opcode};
+use evm::backend::{Backend, MemoryAccount, MemoryBackend};
+use evm::executor::CustomOpcodes;
+
+mod common;
+use common::{vicinity, caller, contract, GAS_LIMIT};
+
+/// Pop two values and push their sum.
+const SUM: Opcode = Opcode(0xc0);
+/// Push one value, but return two.
+const WRONG: Opcode = Opcode(0xc1);
+/// Push one value.
+const ONE: Opcode = Opcode(0xc2);
+
+fn storer() -> H160 { H160::repeat_byte(0x5e) }
+
+struct Host;
+
+impl<S> CustomOpcodes<S> for Host {
+	fn signature(&self, opcode: Opcode) -> Option<CustomOpcode> {
+		match opcode {
+			SUM => Some(CustomOpcode { inputs: 2, outputs: 1, gas: 100 }),
+			WRONG | ONE => Some(CustomOpcode { inputs: 0, outputs: 1, gas: 1 }),
+			_ => None,
+		}
+	}
+
+	fn execute(
+		&mut self,
+		opcode: Opcode,
+		_context: &Context,
+		inputs: Vec<H256>,
+		_state: &mut S,
+	) -> Result<Vec<H256>, ExitError> {
+		let word = |value: U256| {
+			let mut word = H256::default();
+			value.to_big_endian(&mut word[..]);
+			word
+		};
+
+		Ok(match opcode {
+			SUM => {
+				let sum = inputs.iter().map(|input| U256::from_big_endian(&input[..]))
+					.fold(U256::zero(), |sum, input| sum + input);
+				vec![word(sum)]
+			},
+			WRONG => vec![H256::default(); 2],
+			_ => vec![word(U256::one())],
+		})
+	}
+}
+
+/// Host claiming opcodes the EVM already defines.
+struct Hijack;
+
+impl<S> CustomOpcodes<S> for Hijack {
+	fn signature(&self, opcode: Opcode) -> Option<CustomOpcode> {
+		match opcode {
+			Opcode::SSTORE => Some(CustomOpcode { inputs: 2, outputs: 0, gas: 1 }),
+			Opcode::LOG0 => Some(CustomOpcode { inputs: 2, outputs: 0, gas: 1 }),
+			_ => None,
+		}
+	}
+
+	fn execute(
+		&mut self,
+		_opcode: Opcode,
+		_context: &Context,
+		_inputs: Vec<H256>,
+		_state: &mut S,
+	) -> Result<Vec<H256>, ExitError> {
+		panic!("defined opcodes are never host-defined")
+	}
+}
+
+/// Call a contract with `code`, with or without the host opcodes. Return the
+/// exit reason, the return value and the gas used.
+fn call(code: Vec<u8>, host: bool) -> (ExitReason, Vec<u8>, u64) {
+	let config = Config::istanbul();
+	let vicinity = vicinity();
+	let mut accounts = BTreeMap::new();
+	accounts.insert(contract(), MemoryAccount { code, ..Default::default() });
+	let backend = MemoryBackend::new(&vicinity, accounts);
+	let mut executor = common::executor(&backend, &config, GAS_LIMIT);
+	if host {
+		executor.set_custom_opcodes(Box::new(Host));
+	}
+
+	let (reason, output) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), GAS_LIMIT);
+	(reason, output, executor.used_gas())
+}
+
+/// Run `opcode` on 2 and 3, returning the result.
+fn binary(opcode: Opcode) -> Vec<u8> {
+	let mut code = vec![0x60, 0x02, 0x60, 0x03, opcode.as_u8()];
+	// PUSH1 0; MSTORE; PUSH1 32; PUSH1 0; RETURN
+	code.extend_from_slice(&hex::decode("60005260206000f3").unwrap());
+	code
+}
+
+#[test]
+fn outputs_are_pushed_and_gas_charged() {
+	let (reason, output, used_gas) = call(binary(SUM), true);
+	assert!(reason.is_succeed());
+	assert_eq!(U256::from_big_endian(&output), U256::from(5));
+
+	// The same with ADD, which costs 3 gas.
+	let (reason, output, add_gas) = call(binary(Opcode::ADD), true);
+	assert!(reason.is_succeed());
+	assert_eq!(U256::from_big_endian(&output), U256::from(5));
+	assert_eq!(used_gas, add_gas - 3 + 100);
+}
+
+#[test]
+fn unknown_without_host() {
+	let (reason, _, used_gas) = call(binary(SUM), false);
+	assert_eq!(reason, ExitReason::Error(ExitError::OutOfGas));
+	assert_eq!(used_gas, GAS_LIMIT);
+}
+
+#[test]
+fn stack_underflow() {
+	// PUSH1 2; SUM
+	let (reason, _, _) = call(vec![0x60, 0x02, SUM.as_u8()], true);
+	assert_eq!(reason, ExitReason::Error(ExitError::StackUnderflow));
+}
+
+#[test]
+fn stack_overflow() {
+	// 1024 times PUSH1 0, then ONE.
+	let mut code = [0x60, 0x00].repeat(1024);
+	code.push(ONE.as_u8());
+	let (reason, _, _) = call(code.clone(), true);
+	assert_eq!(reason, ExitReason::Error(ExitError::StackOverflow));
+
+	// One slot left.
+	code.drain(..2);
+	code.push(0x00);
+	let (reason, _, _) = call(code, true);
+	assert!(reason.is_succeed());
+}
+
+#[test]
+fn wrong_output_count() {
+	let (reason, _, _) = call(vec![WRONG.as_u8(), 0x00], true);
+	assert_eq!(reason, ExitReason::Error(ExitError::Other("custom opcode output count mismatch".into())));
+}
+
+#[test]
+fn defined_opcodes_are_not_host_defined() {
+	let config = Config::istanbul();
+	let vicinity = vicinity();
+	let mut accounts = BTreeMap::new();
+	// PUSH1 0 (4 times); PUSH20 storer; GAS; STATICCALL;
+	// PUSH1 0; MSTORE; PUSH1 32; PUSH1 0; RETURN
+	let mut code = hex::decode("600060006000600073").unwrap();
+	code.extend_from_slice(&storer()[..]);
+	code.extend_from_slice(&hex::decode("5afa60005260206000f3").unwrap());
+	accounts.insert(contract(), MemoryAccount { code, ..Default::default() });
+	// PUSH1 1; PUSH1 0; SSTORE; PUSH1 0; PUSH1 0; LOG0; STOP
+	accounts.insert(storer(), MemoryAccount {
+		code: hex::decode("600160005560006000a000").unwrap(),
+		..Default::default()
+	});
+	let backend = MemoryBackend::new(&vicinity, accounts);
+	let mut executor = common::executor(&backend, &config, u64::MAX);
+	executor.set_custom_opcodes(Box::new(Hijack));
+
+	// Still forbidden in a static context.
+	let (reason, output) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), GAS_LIMIT);
+	assert!(reason.is_succeed());
+	assert_eq!(U256::from_big_endian(&output), U256::zero());
+	assert_eq!(executor.state().storage(storer(), H256::zero()), H256::zero());
+
+	// Executed natively and charged their usual cost otherwise.
+	let before = executor.used_gas();
+	let (reason, _) = executor.transact_call(caller(), storer(), U256::zero(), Vec::new(), GAS_LIMIT);
+	assert!(reason.is_succeed());
+	assert_eq!(executor.used_gas() - before, 21000 + 4 * 3 + 20000 + 375);
+	assert_eq!(executor.state().storage(storer(), H256::zero()), H256::from_low_u64_be(1));
+}
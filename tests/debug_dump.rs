@@ -0,0 +1,61 @@
+//! Canonical dump of the changes of a substate.
+
+use std::collections::BTreeMap;
+use primitive_types::{H160, H256, U256};
+use evm::Config;
+use evm::backend::{MemoryAccount, MemoryBackend};
+use evm::executor::{MemoryStackState, StackState, StackSubstateMetadata};
+
+mod common;
+use common::vicinity;
+
+#[test]
+fn dump_of_substates() {
+	let config = Config::istanbul();
+	let vicinity = vicinity();
+	let mut accounts = BTreeMap::new();
+	accounts.insert(H160::repeat_byte(1), MemoryAccount {
+		nonce: U256::one(),
+		balance: U256::from(0x100),
+		..Default::default()
+	});
+	let backend = MemoryBackend::new(&vicinity, accounts);
+	let metadata = StackSubstateMetadata::new(u64::MAX, &config);
+	let mut state = MemoryStackState::new(metadata, &backend);
+
+	state.set_storage(H160::repeat_byte(2), H256::from_low_u64_be(2), H256::from_low_u64_be(1));
+	state.set_storage(H160::repeat_byte(2), H256::from_low_u64_be(1), H256::from_low_u64_be(3));
+	state.inc_nonce(H160::repeat_byte(1));
+	state.set_code(H160::repeat_byte(2), vec![0x60, 0x00]);
+	state.log(H160::repeat_byte(2), vec![H256::repeat_byte(0xaa)], vec![0xbe, 0xef]);
+
+	// A child substate only dumps its own changes.
+	state.enter(0, false);
+	state.set_deleted(H160::repeat_byte(3));
+	assert_eq!(
+		state.substate().debug_dump(),
+		concat!(
+			r#"{"accounts":{},"storages":{},"#,
+			r#""deletes":["0x0303030303030303030303030303030303030303"],"touched":[],"logs":[]}"#,
+		),
+	);
+
+	state.exit_commit().unwrap();
+	assert_eq!(
+		state.substate().debug_dump(),
+		concat!(
+			r#"{"accounts":{"#,
+			r#""0x0101010101010101010101010101010101010101":{"balance":"0x100","nonce":"0x2","code":null,"reset":false},"#,
+			r#""0x0202020202020202020202020202020202020202":{"balance":"0x0","nonce":"0x0","code":"0x6000","reset":false}"#,
+			r#"},"storages":{"0x0202020202020202020202020202020202020202":{"#,
+			r#""0x0000000000000000000000000000000000000000000000000000000000000001":"#,
+			r#""0x0000000000000000000000000000000000000000000000000000000000000003","#,
+			r#""0x0000000000000000000000000000000000000000000000000000000000000002":"#,
+			r#""0x0000000000000000000000000000000000000000000000000000000000000001""#,
+			r#"}},"deletes":["0x0303030303030303030303030303030303030303"],"#,
+			r#""touched":["0x0101010101010101010101010101010101010101","0x0202020202020202020202020202020202020202"],"#,
+			r#""logs":[{"address":"0x0202020202020202020202020202020202020202","#,
+			r#""topics":["0xaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa"],"data":"0xbeef"}]}"#,
+		),
+	);
+}
@@ -0,0 +1,76 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Identifiers whose use makes execution depend on iteration order, on the
+/// platform, or on the environment of the process.
+const FORBIDDEN: &[&str] = &[
+	"HashMap",
+	"HashSet",
+	"RandomState",
+	"std::time",
+	"Instant",
+	"SystemTime",
+	"thread_rng",
+	"target_pointer_width",
+	"target_arch",
+	"target_os",
+	"f32",
+	"f64",
+];
+
+/// Files of the modules of `source` declared under `not(feature =
+/// "deterministic")`, which that feature compiles out.
+fn excluded(dir: &Path, source: &str, excluded: &mut BTreeSet<PathBuf>) {
+	let mut lines = source.lines().map(str::trim);
+	while let Some(line) = lines.next() {
+		if !(line.starts_with("#[cfg(") && line.contains("not(feature = \"deterministic\")")) {
+			continue
+		}
+
+		let item = lines.by_ref().find(|line| !line.starts_with("#["));
+		if let Some(name) = item
+			.and_then(|item| item.strip_prefix("mod ").or_else(|| item.strip_prefix("pub mod ")))
+			.and_then(|item| item.strip_suffix(';'))
+		{
+			excluded.insert(dir.join(format!("{}.rs", name)));
+		}
+	}
+}
+
+fn audit(dir: &Path, violations: &mut Vec<String>) {
+	let mut skipped = BTreeSet::new();
+	for entry in fs::read_dir(dir).unwrap() {
+		let path = entry.unwrap().path();
+		if path.extension().map_or(false, |e| e == "rs") {
+			excluded(dir, &fs::read_to_string(&path).unwrap(), &mut skipped);
+		}
+	}
+
+	for entry in fs::read_dir(dir).unwrap() {
+		let path = entry.unwrap().path();
+		if path.is_dir() {
+			audit(&path, violations);
+		} else if path.extension().map_or(false, |e| e == "rs") && !skipped.contains(&path) {
+			let source = fs::read_to_string(&path).unwrap();
+			for (number, line) in source.lines().enumerate() {
+				let code = line.split("//").next().unwrap_or("");
+				for forbidden in FORBIDDEN {
+					if code.contains(forbidden) {
+						violations.push(format!("{}:{}: {}", path.display(), number + 1, forbidden));
+					}
+				}
+			}
+		}
+	}
+}
+
+#[test]
+fn sources_are_deterministic() {
+	let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+	let mut violations = Vec::new();
+	for dir in &["src", "core/src", "gasometer/src", "runtime/src"] {
+		audit(&root.join(dir), &mut violations);
+	}
+	assert!(violations.is_empty(), "non-deterministic code found:\n{}", violations.join("\n"));
+}
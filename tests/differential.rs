@@ -0,0 +1,85 @@
+//! Transactions executed under two configs with `execute_differential`.
+
+use std::collections::BTreeMap;
+use primitive_types::{H160, H256, U256};
+use evm::{Config, ExitReason, ExitSucceed};
+use evm::backend::{MemoryAccount, MemoryVicinity};
+use evm::differential::execute_differential;
+use evm::state_test::StateTestTransaction;
+
+mod common;
+use common::{caller, contract, GAS_LIMIT};
+
+fn coinbase() -> H160 { H160::repeat_byte(0xcb) }
+
+fn vicinity() -> MemoryVicinity {
+	MemoryVicinity {
+		gas_price: U256::one(),
+		block_coinbase: coinbase(),
+		..common::vicinity()
+	}
+}
+
+fn pre() -> BTreeMap<H160, MemoryAccount> {
+	let mut accounts = BTreeMap::new();
+	accounts.insert(caller(), MemoryAccount {
+		balance: U256::from(1_000_000),
+		..Default::default()
+	});
+	// CHAINID; PUSH1 0; SSTORE; STOP
+	accounts.insert(contract(), MemoryAccount {
+		code: hex::decode("4660005500").unwrap(),
+		..Default::default()
+	});
+	accounts
+}
+
+fn transaction(to: H160) -> StateTestTransaction {
+	StateTestTransaction {
+		secret_key: H256::zero(),
+		sender: caller(),
+		to: Some(to),
+		nonce: U256::zero(),
+		value: U256::from(10),
+		data: Vec::new(),
+		gas_limit: GAS_LIMIT,
+	}
+}
+
+#[test]
+fn transfer_does_not_diverge() {
+	let report = execute_differential(
+		&Config::frontier(), &Config::istanbul(), &vicinity(), &pre(),
+		&transaction(H160::repeat_byte(0x01)),
+	);
+	assert_eq!(report.left.exit_reason, Some(ExitReason::Succeed(ExitSucceed::Stopped)));
+	assert_eq!(report.left.used_gas, 21000);
+	assert!(!report.diverges());
+	assert!(report.state_diff.is_empty());
+}
+
+#[test]
+fn chain_id_diverges() {
+	let report = execute_differential(
+		&Config::frontier(), &Config::istanbul(), &vicinity(), &pre(),
+		&transaction(contract()),
+	);
+
+	// CHAINID does not exist before Istanbul, and its failure takes all gas.
+	assert!(matches!(report.left.exit_reason, Some(ExitReason::Error(_))));
+	assert_eq!(report.left.used_gas, GAS_LIMIT);
+	assert_eq!(report.right.exit_reason, Some(ExitReason::Succeed(ExitSucceed::Stopped)));
+	assert!(report.right.used_gas < GAS_LIMIT);
+	assert!(report.exit_diverges());
+	assert!(report.gas_diverges());
+	assert!(report.diverges());
+
+	// Sender and coinbase pay and receive different fees, and the value
+	// transfer and the store only happen on the right.
+	let addresses = report.state_diff.accounts.keys().copied().collect::<Vec<_>>();
+	assert_eq!(addresses, vec![contract(), caller(), coinbase()]);
+	let (left, right) = &report.state_diff.accounts[&contract()];
+	assert!(left.as_ref().unwrap().storage.is_empty());
+	assert_eq!(right.as_ref().unwrap().storage[&H256::zero()], H256::from_low_u64_be(1));
+	assert_eq!(right.as_ref().unwrap().balance, U256::from(10));
+}
@@ -0,0 +1,169 @@
+//! Validation rules of user operation simulation.
+
+#![cfg(feature = "tracing")]
+
+use std::collections::BTreeMap;
+use primitive_types::{H160, H256, U256};
+use evm::{Config, Opcode};
+use evm::backend::{MemoryAccount, MemoryBackend, MemoryVicinity};
+use evm::erc4337::{simulate_user_operation, UserOperation, UserOperationSimulation, ValidationData, Violation};
+
+fn entry_point() -> H160 { H160::repeat_byte(0xee) }
+fn sender() -> H160 { H160::repeat_byte(0x5e) }
+fn other() -> H160 { H160::repeat_byte(0x07) }
+
+/// Return the word `word`.
+fn ret(word: H256) -> Vec<u8> {
+	let mut code = vec![0x7f];
+	code.extend_from_slice(&word[..]);
+	code.extend_from_slice(&hex::decode("60005260206000f3").unwrap());
+	code
+}
+
+/// Return a successful `validationData` valid from `valid_after` until
+/// `valid_until`.
+fn validation_data(aggregator: H160, valid_until: u64, valid_after: u64) -> H256 {
+	let mut word = H256::default();
+	word[..6].copy_from_slice(&valid_after.to_be_bytes()[2..]);
+	word[6..12].copy_from_slice(&valid_until.to_be_bytes()[2..]);
+	word[12..].copy_from_slice(&aggregator[..]);
+	word
+}
+
+/// Call `other` with all the gas left, then pop the result.
+fn call_other() -> Vec<u8> {
+	let mut code = hex::decode("60006000600060006000").unwrap();
+	code.push(0x73);
+	code.extend_from_slice(&other()[..]);
+	code.extend_from_slice(&[0x5a, 0xf1, 0x50]);
+	code
+}
+
+/// Simulate an operation of `sender` with code `sender_code`, `other` having
+/// `other_code`, at timestamp 1000.
+fn simulate(sender_code: Vec<u8>, other_code: Vec<u8>) -> UserOperationSimulation {
+	let config = Config::istanbul();
+	let vicinity = MemoryVicinity {
+		gas_price: U256::zero(),
+		origin: entry_point(),
+		block_hashes: Vec::new(),
+		max_block_hashes: 256,
+		block_number: U256::from(100),
+		block_coinbase: Default::default(),
+		block_timestamp: U256::from(1000),
+		block_difficulty: Default::default(),
+		block_gas_limit: Default::default(),
+		chain_id: U256::one(),
+	};
+	let mut accounts = BTreeMap::new();
+	accounts.insert(sender(), MemoryAccount { code: sender_code, ..Default::default() });
+	accounts.insert(other(), MemoryAccount { code: other_code, ..Default::default() });
+	let mut backend = MemoryBackend::new(&vicinity, accounts);
+
+	let operation = UserOperation {
+		sender: sender(),
+		entry_point: entry_point(),
+		validation_data: Vec::new(),
+		verification_gas_limit: 100_000,
+		call_data: Vec::new(),
+		call_gas_limit: 100_000,
+	};
+	simulate_user_operation(&mut backend, &config, &operation)
+}
+
+/// Simulate with a sender running `prefix` and returning `word`.
+fn simulate_returning(prefix: &[u8], word: H256, other_code: Vec<u8>) -> UserOperationSimulation {
+	let mut code = prefix.to_vec();
+	code.extend(ret(word));
+	simulate(code, other_code)
+}
+
+#[test]
+fn returned_validation_data() {
+	let simulation = simulate_returning(&[], H256::default(), Vec::new());
+	assert!(simulation.is_valid(), "{:?}", simulation.violations);
+	assert!(simulation.execution.unwrap().exit_reason.is_succeed());
+
+	let word = validation_data(H160::zero(), 1500, 500);
+	assert_eq!(ValidationData::decode(&word[..]), Some(ValidationData {
+		aggregator: H160::zero(),
+		valid_until: 1500,
+		valid_after: 500,
+	}));
+	assert!(simulate_returning(&[], word, Vec::new()).is_valid());
+
+	let simulation = simulate_returning(&[], validation_data(H160::from_low_u64_be(1), 0, 0), Vec::new());
+	assert_eq!(simulation.violations, vec![Violation::SignatureFailure]);
+	assert!(!simulation.is_valid());
+	assert!(simulation.execution.is_none());
+
+	let aggregator = H160::repeat_byte(0xa9);
+	let simulation = simulate_returning(&[], validation_data(aggregator, 0, 0), Vec::new());
+	assert_eq!(simulation.violations, vec![Violation::Aggregator(aggregator)]);
+
+	let simulation = simulate_returning(&[], validation_data(H160::zero(), 999, 0), Vec::new());
+	assert_eq!(simulation.violations, vec![Violation::Expired { valid_after: 0, valid_until: 999 }]);
+	let simulation = simulate_returning(&[], validation_data(H160::zero(), 0, 1001), Vec::new());
+	assert_eq!(simulation.violations, vec![Violation::Expired { valid_after: 1001, valid_until: 0 }]);
+
+	let simulation = simulate(vec![0x00], Vec::new());
+	assert_eq!(simulation.violations, vec![Violation::InvalidReturnData]);
+}
+
+#[test]
+fn banned_opcodes() {
+	// TIMESTAMP; POP
+	let simulation = simulate_returning(&[0x42, 0x50], H256::default(), Vec::new());
+	assert_eq!(simulation.violations, vec![
+		Violation::BannedOpcode { address: sender(), opcode: Opcode::TIMESTAMP },
+	]);
+
+	// GAS; POP
+	let simulation = simulate_returning(&[0x5a, 0x50], H256::default(), Vec::new());
+	assert_eq!(simulation.violations, vec![
+		Violation::BannedOpcode { address: sender(), opcode: Opcode::GAS },
+	]);
+
+	// GAS immediately followed by CALL is allowed.
+	let simulation = simulate_returning(&call_other(), H256::default(), vec![0x00]);
+	assert!(simulation.is_valid(), "{:?}", simulation.violations);
+
+	// Banned opcodes of other entities are reported with their address.
+	// NUMBER; POP; STOP
+	let simulation = simulate_returning(&call_other(), H256::default(), vec![0x43, 0x50, 0x00]);
+	assert_eq!(simulation.violations, vec![
+		Violation::BannedOpcode { address: other(), opcode: Opcode::NUMBER },
+	]);
+}
+
+#[test]
+fn storage_access() {
+	// The sender's own storage. PUSH1 5; SLOAD; POP
+	let simulation = simulate_returning(&[0x60, 0x05, 0x54, 0x50], H256::default(), Vec::new());
+	assert!(simulation.is_valid(), "{:?}", simulation.violations);
+
+	// Storage of another entity, not associated with the sender.
+	// PUSH1 5; SLOAD; POP; STOP
+	let simulation = simulate_returning(&call_other(), H256::default(), vec![0x60, 0x05, 0x54, 0x50, 0x00]);
+	assert_eq!(simulation.violations, vec![
+		Violation::StorageAccess { address: other(), index: H256::from_low_u64_be(5) },
+	]);
+
+	// Slots of a mapping keyed by the sender in another entity, and the
+	// slots following them.
+	// PUSH20 sender; PUSH1 0; MSTORE; PUSH1 64; PUSH1 0; SHA3; DUP1; SLOAD; POP
+	let mut code = vec![0x73];
+	code.extend_from_slice(&sender()[..]);
+	code.extend_from_slice(&hex::decode("60005260406000208054").unwrap());
+	let mut associated = code.clone();
+	// POP; PUSH1 128; ADD; SLOAD; POP; STOP
+	associated.extend_from_slice(&hex::decode("50608001545000").unwrap());
+	let simulation = simulate_returning(&call_other(), H256::default(), associated);
+	assert!(simulation.is_valid(), "{:?}", simulation.violations);
+
+	// POP; PUSH1 129; ADD; SLOAD; POP; STOP
+	code.extend_from_slice(&hex::decode("50608101545000").unwrap());
+	let simulation = simulate_returning(&call_other(), H256::default(), code);
+	assert_eq!(simulation.violations.len(), 1);
+	assert!(matches!(simulation.violations[0], Violation::StorageAccess { address, .. } if address == other()));
+}
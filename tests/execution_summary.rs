@@ -0,0 +1,51 @@
+//! Gas used and refunded as reported by `StackExecutor::execution_summary`.
+
+use std::collections::BTreeMap;
+use primitive_types::{H256, U256};
+use evm::Config;
+use evm::backend::{MemoryAccount, MemoryBackend};
+use evm::executor::ExecutionSummary;
+
+mod common;
+use common::{vicinity, caller, contract, GAS_LIMIT};
+
+/// Call a contract with `code`, whose slot 0 holds 1.
+fn call(code: Vec<u8>) -> (ExecutionSummary, u64) {
+	let config = Config::istanbul();
+	let vicinity = vicinity();
+	let mut storage = BTreeMap::new();
+	storage.insert(H256::zero(), H256::from_low_u64_be(1));
+	let mut accounts = BTreeMap::new();
+	accounts.insert(contract(), MemoryAccount { code, storage, ..Default::default() });
+	let backend = MemoryBackend::new(&vicinity, accounts);
+	let mut executor = common::executor(&backend, &config, GAS_LIMIT);
+
+	let (reason, _) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), GAS_LIMIT);
+	assert!(reason.is_succeed());
+	(executor.execution_summary(), executor.used_gas())
+}
+
+#[test]
+fn refund_below_cap() {
+	// PUSH1 2; PUSH1 0; SSTORE; PUSH1 1; PUSH1 0; SSTORE; STOP
+	let (summary, used_gas) = call(hex::decode("6002600055600160005500").unwrap());
+	assert_eq!(summary.gas_limit, GAS_LIMIT);
+	assert_eq!(summary.gas_used_pre_refund, 21000 + 4 * 3 + 5000 + 800);
+	// Restoring the original value refunds the reset cost less an SLOAD.
+	assert_eq!(summary.refund_requested, 4200);
+	assert_eq!(summary.refund_applied, 4200);
+	assert_eq!(summary.effective_gas_used, summary.gas_used_pre_refund - 4200);
+	assert_eq!(used_gas, summary.effective_gas_used);
+}
+
+#[test]
+fn refund_above_cap() {
+	// PUSH1 0; PUSH1 0; SSTORE
+	let (summary, used_gas) = call(hex::decode("6000600055").unwrap());
+	assert_eq!(summary.gas_used_pre_refund, 21000 + 2 * 3 + 5000);
+	assert_eq!(summary.refund_requested, 15000);
+	// Half of the gas used.
+	assert_eq!(summary.refund_applied, 13003);
+	assert_eq!(summary.effective_gas_used, 13003);
+	assert_eq!(used_gas, summary.effective_gas_used);
+}
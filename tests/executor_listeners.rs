@@ -0,0 +1,102 @@
+//! Event listeners attached to executor instances rather than to the thread.
+
+#![cfg(feature = "tracing")]
+
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+use primitive_types::{H160, U256};
+use evm::Config;
+use evm::backend::{MemoryAccount, MemoryBackend};
+use evm::executor::{StackExecutor, MemoryStackState, StackSubstateMetadata};
+use evm::tracing::{self, Listeners, gasometer, runtime};
+
+mod common;
+use common::{vicinity, caller, contract, GAS_LIMIT};
+
+/// Counter of events of any kind.
+#[derive(Clone, Default)]
+struct Counter(Rc<Cell<usize>>);
+
+impl Counter {
+	fn get(&self) -> usize { self.0.get() }
+	fn bump(&self) { self.0.set(self.0.get() + 1) }
+}
+
+impl tracing::EventListener for Counter {
+	fn event(&mut self, _event: tracing::Event) { self.bump() }
+}
+
+impl runtime::EventListener for Counter {
+	fn event(&mut self, event: runtime::Event) {
+		if let runtime::Event::Step { .. } = event {
+			self.bump()
+		}
+	}
+}
+
+impl gasometer::EventListener for Counter {
+	fn event(&mut self, _event: gasometer::Event) { self.bump() }
+}
+
+fn accounts() -> BTreeMap<H160, MemoryAccount> {
+	let mut accounts = BTreeMap::new();
+	// PUSH1 1; PUSH1 2; ADD; POP; STOP
+	accounts.insert(contract(), MemoryAccount {
+		code: vec![0x60, 0x01, 0x60, 0x02, 0x01, 0x50, 0x00],
+		..Default::default()
+	});
+	accounts
+}
+
+#[test]
+fn executors_are_traced_independently() {
+	let config = Config::istanbul();
+	let vicinity = vicinity();
+	let backend = MemoryBackend::new(&vicinity, accounts());
+	let mut executors = (0..2).map(|_| {
+		let metadata = StackSubstateMetadata::new(u64::MAX, &config);
+		StackExecutor::new(MemoryStackState::new(metadata, &backend), &config)
+	}).collect::<Vec<_>>();
+
+	// Executor, runtime and gasometer listeners on the first executor, only
+	// a runtime one on the second.
+	let first = (Counter::default(), Counter::default(), Counter::default());
+	let second = Counter::default();
+	executors[0].set_listeners(Listeners {
+		executor: Some(Box::new(first.0.clone())),
+		runtime: Some(Box::new(first.1.clone())),
+		gasometer: Some(Box::new(first.2.clone())),
+	});
+	executors[1].set_listeners(Listeners {
+		runtime: Some(Box::new(second.clone())),
+		..Default::default()
+	});
+
+	// The listener of the thread is shadowed.
+	let mut thread = Counter::default();
+	runtime::using(&mut thread, || {
+		for _ in 0..2 {
+			for executor in executors.iter_mut() {
+				let (reason, _) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), GAS_LIMIT);
+				assert!(reason.is_succeed());
+			}
+		}
+	});
+	assert_eq!(thread.get(), 0);
+
+	assert_eq!(first.1.get(), 2 * 5);
+	assert_eq!(second.get(), 2 * 5);
+	assert!(first.0.get() > 0);
+	assert!(first.2.get() > 0);
+
+	// Once detached, the listener of the thread gets the events again.
+	assert!(executors[1].take_listeners().is_some());
+	assert!(executors[1].take_listeners().is_none());
+	runtime::using(&mut thread, || {
+		let (reason, _) = executors[1].transact_call(caller(), contract(), U256::zero(), Vec::new(), GAS_LIMIT);
+		assert!(reason.is_succeed());
+	});
+	assert_eq!(thread.get(), 5);
+	assert_eq!(second.get(), 2 * 5);
+}
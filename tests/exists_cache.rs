@@ -0,0 +1,128 @@
+//! Backend answers cached by `MemoryStackState`.
+
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use primitive_types::{H160, H256, U256};
+use evm::{Config, CreateScheme};
+use evm::backend::{Backend, Basic, MemoryAccount, MemoryBackend, MemoryVicinity};
+use evm::executor::{StackState, MemoryStackState, StackSubstateMetadata};
+
+mod common;
+use common::{vicinity, caller};
+
+const GAS_LIMIT: u64 = 1_000_000;
+
+fn suicidal() -> H160 { H160::repeat_byte(0x5d) }
+fn beneficiary() -> H160 { H160::repeat_byte(0xbe) }
+
+/// Backend counting the queries of `exists` and `basic`.
+struct CountingBackend<'vicinity> {
+	inner: MemoryBackend<'vicinity>,
+	exists: Cell<usize>,
+	basic: Cell<usize>,
+}
+
+impl<'vicinity> Backend for CountingBackend<'vicinity> {
+	fn gas_price(&self) -> U256 { self.inner.gas_price() }
+	fn origin(&self) -> H160 { self.inner.origin() }
+	fn block_hash(&self, number: U256) -> H256 { self.inner.block_hash(number) }
+	fn block_number(&self) -> U256 { self.inner.block_number() }
+	fn block_coinbase(&self) -> H160 { self.inner.block_coinbase() }
+	fn block_timestamp(&self) -> U256 { self.inner.block_timestamp() }
+	fn block_difficulty(&self) -> U256 { self.inner.block_difficulty() }
+	fn block_gas_limit(&self) -> U256 { self.inner.block_gas_limit() }
+	fn chain_id(&self) -> U256 { self.inner.chain_id() }
+
+	fn exists(&self, address: H160) -> bool {
+		self.exists.set(self.exists.get() + 1);
+		self.inner.exists(address)
+	}
+
+	fn basic(&self, address: H160) -> Basic {
+		self.basic.set(self.basic.get() + 1);
+		self.inner.basic(address)
+	}
+
+	fn code(&self, address: H160) -> Vec<u8> { self.inner.code(address) }
+	fn storage(&self, address: H160, index: H256) -> H256 { self.inner.storage(address, index) }
+	fn original_storage(&self, address: H160, index: H256) -> Option<H256> {
+		self.inner.original_storage(address, index)
+	}
+}
+
+fn backend(vicinity: &MemoryVicinity) -> CountingBackend<'_> {
+	let mut accounts = BTreeMap::new();
+	accounts.insert(caller(), MemoryAccount {
+		balance: U256::from(10_000_000),
+		..Default::default()
+	});
+	// PUSH20 beneficiary; SUICIDE
+	let mut code = vec![0x73];
+	code.extend_from_slice(&beneficiary()[..]);
+	code.push(0xff);
+	accounts.insert(suicidal(), MemoryAccount {
+		balance: U256::from(100),
+		nonce: U256::one(),
+		code,
+		..Default::default()
+	});
+
+	CountingBackend {
+		inner: MemoryBackend::new(vicinity, accounts),
+		exists: Cell::new(0),
+		basic: Cell::new(0),
+	}
+}
+
+#[test]
+fn backend_is_queried_once_per_address() {
+	let config = Config::istanbul();
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+	let metadata = StackSubstateMetadata::new(GAS_LIMIT, &config);
+	let state = MemoryStackState::new(metadata, &backend);
+
+	for _ in 0..3 {
+		assert!(!state.exists(beneficiary()));
+		assert!(state.is_empty(beneficiary()));
+		assert!(state.exists(suicidal()));
+		assert!(!state.is_empty(suicidal()));
+	}
+	assert_eq!(backend.exists.get(), 2);
+	assert_eq!(backend.basic.get(), 2);
+
+	// Forks share the answers cached so far.
+	let mut state = state;
+	let fork = state.fork();
+	assert!(!fork.exists(beneficiary()));
+	assert!(fork.is_empty(beneficiary()));
+	assert_eq!(backend.exists.get(), 2);
+	assert_eq!(backend.basic.get(), 2);
+}
+
+#[test]
+fn changes_shadow_cached_answers() {
+	let config = Config::istanbul();
+	let vicinity = vicinity();
+	let backend = backend(&vicinity);
+	let mut executor = common::executor(&backend, &config, u64::MAX);
+
+	// Created account.
+	let created = executor.create_address(CreateScheme::Legacy { caller: caller() });
+	assert!(!executor.state().exists(created));
+	assert!(executor.state().is_empty(created));
+	let reason = executor.transact_create(caller(), U256::zero(), vec![0x00], GAS_LIMIT);
+	assert!(reason.is_succeed());
+	assert!(executor.state().exists(created));
+	assert!(!executor.state().is_empty(created));
+
+	// Beneficiary of a suicide.
+	assert!(!executor.state().exists(beneficiary()));
+	assert!(executor.state().is_empty(beneficiary()));
+	let (reason, _) = executor.transact_call(caller(), suicidal(), U256::zero(), Vec::new(), GAS_LIMIT);
+	assert!(reason.is_succeed());
+	assert!(executor.state().deleted(suicidal()));
+	assert!(executor.state().exists(beneficiary()));
+	assert!(!executor.state().is_empty(beneficiary()));
+	assert_eq!(executor.state().basic(beneficiary()).balance, U256::from(100));
+}
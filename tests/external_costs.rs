@@ -0,0 +1,81 @@
+//! Surcharges of an external cost oracle, added on top of opcode costs.
+
+use std::collections::BTreeMap;
+use primitive_types::{H160, U256};
+use evm::{Config, ExitError};
+use evm::backend::{MemoryAccount, MemoryBackend};
+use evm::executor::{StackExecutor, MemoryStackState, StackSubstateMetadata};
+use evm::gasometer::{ExternalCostOracle, GasCost, Gasometer};
+
+mod common;
+use common::{vicinity, caller, GAS_LIMIT};
+
+fn outer() -> H160 { H160::repeat_byte(0x0a) }
+fn inner() -> H160 { H160::repeat_byte(0x0b) }
+
+/// Oracle charging 100 gas per storage read.
+struct StorageRent;
+
+impl ExternalCostOracle for StorageRent {
+	fn surcharge(&self, cost: &GasCost) -> u64 {
+		match cost {
+			GasCost::SLoad => 100,
+			_ => 0,
+		}
+	}
+}
+
+#[test]
+fn surcharge_is_recorded_separately() {
+	let oracle = StorageRent;
+	let config = Config::istanbul();
+	let mut gasometer = Gasometer::new(10_000, &config);
+	gasometer.set_external_cost_oracle(Some(&oracle));
+
+	gasometer.record_dynamic_cost(GasCost::SLoad, None).unwrap();
+	gasometer.record_dynamic_cost(GasCost::Balance, None).unwrap();
+	let snapshot = gasometer.snapshot().unwrap();
+	assert_eq!(snapshot.used_gas, 800 + 100 + 700);
+	assert_eq!(snapshot.surcharge_gas, 100);
+}
+
+#[test]
+fn surcharge_can_run_out_of_gas() {
+	let oracle = StorageRent;
+	let config = Config::istanbul();
+	let mut gasometer = Gasometer::new(850, &config);
+	gasometer.set_external_cost_oracle(Some(&oracle));
+
+	assert_eq!(gasometer.record_dynamic_cost(GasCost::SLoad, None), Err(ExitError::OutOfGas));
+	assert_eq!(gasometer.total_used_gas(), 850);
+}
+
+/// Gas used by a call to `outer`, which reads a slot and calls `inner`,
+/// reading another slot.
+fn used_gas(oracle: Option<&dyn ExternalCostOracle>) -> u64 {
+	let config = Config::istanbul();
+	let vicinity = vicinity();
+	let mut accounts = BTreeMap::new();
+	// PUSH1 0; SLOAD; POP; PUSH1 0 (5 times); PUSH20 inner; GAS; CALL; STOP
+	let mut code = hex::decode("600054506000600060006000600073").unwrap();
+	code.extend_from_slice(&inner()[..]);
+	code.extend_from_slice(&[0x5a, 0xf1, 0x00]);
+	accounts.insert(outer(), MemoryAccount { code, ..Default::default() });
+	// PUSH1 1; SLOAD; STOP
+	accounts.insert(inner(), MemoryAccount { code: vec![0x60, 0x01, 0x54, 0x00], ..Default::default() });
+	let backend = MemoryBackend::new(&vicinity, accounts);
+	let mut metadata = StackSubstateMetadata::new(GAS_LIMIT, &config);
+	metadata.gasometer_mut().set_external_cost_oracle(oracle);
+	let state = MemoryStackState::new(metadata, &backend);
+	let mut executor = StackExecutor::new(state, &config);
+
+	let (reason, _) = executor.transact_call(caller(), outer(), U256::zero(), Vec::new(), GAS_LIMIT);
+	assert!(reason.is_succeed());
+	executor.used_gas()
+}
+
+#[test]
+fn call_frames_inherit_the_oracle() {
+	let oracle = StorageRent;
+	assert_eq!(used_gas(Some(&oracle)), used_gas(None) + 2 * 100);
+}
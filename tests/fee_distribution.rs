@@ -0,0 +1,104 @@
+//! Balances after settling the fees of an executed transaction.
+
+use std::collections::BTreeMap;
+use primitive_types::{H160, U256};
+use evm::Config;
+use evm::backend::{Backend, MemoryAccount, MemoryBackend, MemoryVicinity};
+use evm::executor::{FeeDistribution, FeeSettlement, BaseFeeRecipient, FeeOverflow};
+
+mod common;
+use common::{caller, contract, GAS_LIMIT};
+
+const BASE_FEE: u64 = 7;
+const PRIORITY_FEE: u64 = 2;
+
+fn coinbase() -> H160 { H160::repeat_byte(0xcb) }
+fn treasury() -> H160 { H160::repeat_byte(0x7e) }
+
+fn vicinity() -> MemoryVicinity {
+	MemoryVicinity {
+		gas_price: U256::from(BASE_FEE + PRIORITY_FEE),
+		block_coinbase: coinbase(),
+		..common::vicinity()
+	}
+}
+
+fn accounts(coinbase_balance: U256) -> BTreeMap<H160, MemoryAccount> {
+	let mut accounts = BTreeMap::new();
+	accounts.insert(caller(), MemoryAccount {
+		balance: U256::from(10_000_000),
+		..Default::default()
+	});
+	// SSTORE(0, 1); STOP
+	accounts.insert(contract(), MemoryAccount {
+		code: hex::decode("600160005500").unwrap(),
+		..Default::default()
+	});
+	accounts.insert(coinbase(), MemoryAccount {
+		balance: coinbase_balance,
+		..Default::default()
+	});
+	accounts
+}
+
+/// Prepay, execute a call and settle its fees with `distribution`. Return
+/// the gas used, the settlement and the balances of the caller, coinbase
+/// and treasury.
+fn settle(
+	distribution: &FeeDistribution,
+	coinbase_balance: U256,
+) -> (u64, Result<FeeSettlement, evm::ExitError>, [U256; 3]) {
+	let config = Config::istanbul();
+	let vicinity = vicinity();
+	let backend = MemoryBackend::new(&vicinity, accounts(coinbase_balance));
+	let mut executor = common::executor(&backend, &config, GAS_LIMIT);
+
+	let prepaid = U256::from(GAS_LIMIT) * (BASE_FEE + PRIORITY_FEE);
+	executor.state_mut().withdraw(caller(), prepaid).unwrap();
+	let (reason, _) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), GAS_LIMIT);
+	assert!(reason.is_succeed());
+
+	let used_gas = executor.used_gas();
+	let settlement = distribution.settle(executor.state_mut(), caller(), coinbase(), GAS_LIMIT, used_gas);
+	let state = executor.state();
+	let balances = [caller(), coinbase(), treasury()].map(|address| state.basic(address).balance);
+	(used_gas, settlement, balances)
+}
+
+#[test]
+fn base_fee_is_burnt() {
+	let distribution = FeeDistribution::new(U256::from(BASE_FEE), U256::from(PRIORITY_FEE));
+	let (used_gas, settlement, [caller, coinbase, treasury]) = settle(&distribution, U256::zero());
+	let used_gas = U256::from(used_gas);
+
+	assert_eq!(caller, U256::from(10_000_000) - used_gas * (BASE_FEE + PRIORITY_FEE));
+	assert_eq!(coinbase, used_gas * PRIORITY_FEE);
+	assert_eq!(treasury, U256::zero());
+	assert_eq!(settlement.unwrap().base_fee, used_gas * BASE_FEE);
+}
+
+#[test]
+fn base_fee_is_credited_to_recipient() {
+	let distribution = FeeDistribution::new(U256::from(BASE_FEE), U256::from(PRIORITY_FEE))
+		.base_fee_recipient(BaseFeeRecipient::Address(treasury()));
+	let (used_gas, _, [caller, coinbase, treasury]) = settle(&distribution, U256::zero());
+	let used_gas = U256::from(used_gas);
+
+	assert_eq!(caller, U256::from(10_000_000) - used_gas * (BASE_FEE + PRIORITY_FEE));
+	assert_eq!(coinbase, used_gas * PRIORITY_FEE);
+	assert_eq!(treasury, used_gas * BASE_FEE);
+}
+
+#[test]
+fn coinbase_overflow() {
+	let distribution = FeeDistribution::new(U256::from(BASE_FEE), U256::from(PRIORITY_FEE));
+	let (_, settlement, [_, coinbase, _]) = settle(&distribution, U256::MAX - 1);
+	assert!(settlement.is_ok());
+	assert_eq!(coinbase, U256::MAX);
+
+	let distribution = distribution.overflow(FeeOverflow::Fail);
+	let (_, settlement, [caller, coinbase, _]) = settle(&distribution, U256::MAX - 1);
+	assert!(settlement.is_err());
+	assert_eq!(coinbase, U256::MAX - 1);
+	assert_eq!(caller, U256::from(10_000_000 - GAS_LIMIT * (BASE_FEE + PRIORITY_FEE)));
+}
@@ -0,0 +1,93 @@
+//! Outcomes of transactions returned by `StackExecutor::finalize`, leaving
+//! the executor usable.
+
+use std::collections::BTreeMap;
+use primitive_types::{H160, H256, U256};
+use evm::{Config, ExitReason, ExitSucceed};
+use evm::backend::{Apply, Backend, MemoryAccount, MemoryBackend};
+
+mod common;
+use common::{vicinity, caller, contract, GAS_LIMIT};
+
+fn accounts() -> BTreeMap<H160, MemoryAccount> {
+	let mut accounts = BTreeMap::new();
+	accounts.insert(caller(), MemoryAccount {
+		balance: U256::from(1000),
+		..Default::default()
+	});
+	// Increment slot 0, log its new value, and return it.
+	// PUSH1 0; SLOAD; PUSH1 1; ADD; DUP1; PUSH1 0; SSTORE; PUSH1 0; MSTORE;
+	// PUSH1 32; PUSH1 0; LOG0; PUSH1 32; PUSH1 0; RETURN
+	accounts.insert(contract(), MemoryAccount {
+		code: hex::decode("6000546001018060005560005260206000a060206000f3").unwrap(),
+		..Default::default()
+	});
+	accounts
+}
+
+fn word(value: u64) -> Vec<u8> {
+	H256::from_low_u64_be(value).as_bytes().to_vec()
+}
+
+#[test]
+fn outcome_of_last_transaction() {
+	let config = Config::istanbul();
+	let vicinity = vicinity();
+	let backend = MemoryBackend::new(&vicinity, accounts());
+	let mut executor = common::executor(&backend, &config, u64::MAX);
+	assert!(executor.finalize().is_none());
+
+	let (reason, output) = executor.transact_call(caller(), contract(), U256::from(10), Vec::new(), GAS_LIMIT);
+	assert_eq!(reason, ExitReason::Succeed(ExitSucceed::Returned));
+	assert_eq!(output, word(1));
+
+	let outcome = executor.finalize().unwrap();
+	assert_eq!(outcome.exit_reason, reason);
+	assert_eq!(outcome.return_data, output);
+	assert_eq!(outcome.logs.len(), 1);
+	assert_eq!(outcome.logs[0].data, word(1));
+
+	// The post-state can still be queried, and built upon.
+	assert_eq!(executor.state().basic(contract()).balance, U256::from(10));
+	let (_, output) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), GAS_LIMIT);
+	assert_eq!(output, word(2));
+
+	let outcome = executor.finalize().unwrap();
+	assert_eq!(outcome.return_data, word(2));
+	assert_eq!(outcome.logs.len(), 2);
+	let storage = outcome.changes.iter().find_map(|apply| match apply {
+		Apply::Modify { address, storage, .. } if *address == contract() => Some(storage.clone()),
+		_ => None,
+	}).unwrap();
+	assert_eq!(storage, vec![(H256::zero(), H256::from_low_u64_be(2))].into_iter().collect());
+
+	// The same changes as when consuming the executor.
+	let (values, logs) = executor.into_state().deconstruct();
+	let values = values.into_iter().map(|apply| match apply {
+		Apply::Modify { address, basic, code, storage, reset_storage } => Apply::Modify {
+			address, basic, code, storage: storage.into_iter().collect(), reset_storage,
+		},
+		Apply::Delete { address } => Apply::Delete { address },
+	}).collect::<Vec<Apply<BTreeMap<H256, H256>>>>();
+	assert_eq!(format!("{:?}", values), format!("{:?}", outcome.changes));
+	assert_eq!(logs.into_iter().collect::<Vec<_>>(), outcome.logs);
+}
+
+#[test]
+fn outcome_of_create() {
+	let config = Config::istanbul();
+	let vicinity = vicinity();
+	let backend = MemoryBackend::new(&vicinity, accounts());
+	let mut executor = common::executor(&backend, &config, u64::MAX);
+
+	let (_, output) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), GAS_LIMIT);
+	assert_eq!(executor.finalize().unwrap().return_data, output);
+
+	// Creates return no data, even after a call returning some.
+	let reason = executor.transact_create(caller(), U256::zero(), vec![0x00], GAS_LIMIT);
+	assert!(reason.is_succeed());
+	let outcome = executor.finalize().unwrap();
+	assert_eq!(outcome.exit_reason, reason);
+	assert!(outcome.return_data.is_empty());
+	assert_eq!(outcome.logs.len(), 1);
+}
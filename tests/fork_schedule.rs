@@ -0,0 +1,62 @@
+//! Configs picked by block from a hard fork schedule.
+
+use std::collections::BTreeMap;
+use primitive_types::U256;
+use evm::{Config, ForkActivation, ForkSchedule};
+use evm::backend::{MemoryAccount, MemoryBackend};
+use evm::executor::{StackExecutor, MemoryStackState, StackSubstateMetadata};
+
+mod common;
+use common::{vicinity, caller, contract};
+
+/// Frontier, Istanbul from block 10, and a smaller call stack from
+/// timestamp 1000.
+fn schedule() -> ForkSchedule {
+	ForkSchedule::new(Config::frontier())
+		.with_fork(ForkActivation::Block(U256::from(10)), Config::istanbul())
+		.with_fork(
+			ForkActivation::Timestamp(U256::from(1000)),
+			Config { call_stack_limit: 512, ..Config::istanbul() },
+		)
+}
+
+#[test]
+fn latest_active_fork() {
+	let schedule = schedule();
+	let fork_name = |block: u64, timestamp: u64| {
+		schedule.config_at(U256::from(block), U256::from(timestamp)).fork_name()
+	};
+
+	assert_eq!(fork_name(0, 0), "frontier");
+	assert_eq!(fork_name(9, 999), "frontier");
+	assert_eq!(fork_name(10, 0), "istanbul");
+	assert_eq!(fork_name(10, 999), "istanbul");
+	assert_eq!(fork_name(10, 1000), "custom");
+	// Activations are checked independently of each other.
+	assert_eq!(fork_name(0, 1000), "custom");
+
+	assert!(ForkActivation::Block(U256::from(5)).is_active(U256::from(5), U256::zero()));
+	assert!(!ForkActivation::Timestamp(U256::from(5)).is_active(U256::from(5), U256::from(4)));
+}
+
+#[test]
+fn executor_for_block() {
+	let schedule = schedule();
+	let vicinity = vicinity();
+	let mut accounts = BTreeMap::new();
+	// CHAINID; STOP
+	accounts.insert(contract(), MemoryAccount { code: vec![0x46, 0x00], ..Default::default() });
+	let backend = MemoryBackend::new(&vicinity, accounts);
+
+	let run = |block: u64| {
+		let mut executor = StackExecutor::for_block(&schedule, U256::from(block), U256::zero(), |config| {
+			MemoryStackState::new(StackSubstateMetadata::new(u64::MAX, config), &backend)
+		});
+		let (reason, _) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 100_000);
+		(executor.config().fork_name(), reason.is_succeed())
+	};
+
+	// CHAINID is only defined from Istanbul.
+	assert_eq!(run(9), ("frontier", false));
+	assert_eq!(run(10), ("istanbul", true));
+}
@@ -0,0 +1,91 @@
+//! Runtime and gasometer events attributed to the call frame they happened
+//! in.
+
+#![cfg(feature = "tracing")]
+
+use std::collections::{BTreeMap, BTreeSet};
+use primitive_types::{H160, U256};
+use evm::{Config, Frame};
+use evm::backend::{MemoryAccount, MemoryBackend};
+use evm::tracing::{gasometer, runtime};
+
+mod common;
+use common::{vicinity, caller};
+
+fn outer() -> H160 { H160::repeat_byte(0x0a) }
+fn inner() -> H160 { H160::repeat_byte(0x0b) }
+
+/// Frames of the steps of each contract, and of the SLOAD events.
+#[derive(Default)]
+struct RuntimeFrames {
+	steps: BTreeMap<H160, BTreeSet<(Option<usize>, bool)>>,
+	sloads: Vec<Frame>,
+}
+
+impl runtime::EventListener for RuntimeFrames {
+	fn event(&mut self, event: runtime::Event) {
+		match event {
+			runtime::Event::Step { frame, context, .. } => {
+				self.steps.entry(context.address).or_default().insert((frame.depth, frame.is_static));
+			},
+			runtime::Event::SLoad { frame, .. } => self.sloads.push(frame),
+			_ => (),
+		}
+	}
+}
+
+/// Frames of the gasometer snapshots.
+#[derive(Default)]
+struct GasometerFrames(BTreeSet<(Option<usize>, bool)>);
+
+impl gasometer::EventListener for GasometerFrames {
+	fn event(&mut self, event: gasometer::Event) {
+		let snapshot = match event {
+			gasometer::Event::RecordCost { snapshot, .. } |
+			gasometer::Event::RecordRefund { snapshot, .. } |
+			gasometer::Event::RecordStipend { snapshot, .. } |
+			gasometer::Event::RecordDynamicCost { snapshot, .. } |
+			gasometer::Event::RecordTransaction { snapshot, .. } => snapshot,
+		};
+		self.0.insert((snapshot.frame.depth, snapshot.frame.is_static));
+	}
+}
+
+#[test]
+fn nested_static_call() {
+	// PUSH1 0 (4 times); PUSH20 inner; GAS; STATICCALL; POP; STOP
+	let mut outer_code = hex::decode("6000600060006000").unwrap();
+	outer_code.push(0x73);
+	outer_code.extend_from_slice(&inner()[..]);
+	outer_code.extend_from_slice(&[0x5a, 0xfa, 0x50, 0x00]);
+	// PUSH1 0; SLOAD; POP; STOP
+	let inner_code = vec![0x60, 0x00, 0x54, 0x50, 0x00];
+
+	let config = Config::istanbul();
+	let vicinity = vicinity();
+	let mut accounts = BTreeMap::new();
+	accounts.insert(outer(), MemoryAccount { code: outer_code, ..Default::default() });
+	accounts.insert(inner(), MemoryAccount { code: inner_code, ..Default::default() });
+	let backend = MemoryBackend::new(&vicinity, accounts);
+	let mut executor = common::executor(&backend, &config, u64::MAX);
+
+	let mut runtime_frames = RuntimeFrames::default();
+	let mut gasometer_frames = GasometerFrames::default();
+	let (reason, _) = runtime::using(&mut runtime_frames, || {
+		gasometer::using(&mut gasometer_frames, || {
+			executor.transact_call(caller(), outer(), U256::zero(), Vec::new(), 1_000_000)
+		})
+	});
+	assert!(reason.is_succeed());
+
+	assert_eq!(runtime_frames.steps[&outer()], BTreeSet::from([(Some(0), false)]));
+	assert_eq!(runtime_frames.steps[&inner()], BTreeSet::from([(Some(1), true)]));
+	assert_eq!(runtime_frames.sloads, vec![Frame { depth: Some(1), is_static: true }]);
+
+	// The transaction gasometer has no depth.
+	assert_eq!(gasometer_frames.0, BTreeSet::from([
+		(None, false),
+		(Some(0), false),
+		(Some(1), true),
+	]));
+}
@@ -0,0 +1,143 @@
+//! Gas accounting of call frames reported by enter and exit events.
+
+#![cfg(feature = "tracing")]
+
+use std::collections::BTreeMap;
+use primitive_types::{H160, H256, U256};
+use evm::Config;
+use evm::backend::{MemoryAccount, MemoryBackend};
+use evm::executor::StackExitKind;
+use evm::tracing::{self, Event, EventListener};
+
+mod common;
+use common::{vicinity, caller, GAS_LIMIT};
+
+/// Gas given to the inner call.
+const INNER_GAS: u64 = 10_000;
+/// Gas used by the outer contract itself, see `accounts`.
+const OUTER_GAS: u64 = 5 * 3 + 3 + 3 + 700 + 2;
+/// PUSH1 0; PUSH1 0; SSTORE, clearing a slot.
+const SSTORE_GAS: u64 = 3 + 3 + 5000;
+
+fn outer() -> H160 { H160::repeat_byte(0x0a) }
+fn inner() -> H160 { H160::repeat_byte(0x0b) }
+
+#[derive(Debug, Eq, PartialEq)]
+enum Frame {
+	Enter { depth: usize, gas_limit: u64 },
+	Exit { depth: usize, kind: StackExitKind, used_gas: u64, refunded_gas: i64, burnt_gas: u64 },
+}
+
+#[derive(Default)]
+struct Frames(Vec<Frame>);
+
+impl EventListener for Frames {
+	fn event(&mut self, event: Event) {
+		match event {
+			Event::EnterFrame { depth, gas_limit, .. } =>
+				self.0.push(Frame::Enter { depth, gas_limit }),
+			Event::ExitFrame { depth, kind, used_gas, refunded_gas, burnt_gas, .. } =>
+				self.0.push(Frame::Exit { depth, kind, used_gas, refunded_gas, burnt_gas }),
+			_ => (),
+		}
+	}
+}
+
+/// The outer contract calls the inner one, which clears its slot 0 and then
+/// runs `tail`.
+fn accounts(tail: &[u8]) -> BTreeMap<H160, MemoryAccount> {
+	let mut accounts = BTreeMap::new();
+
+	// PUSH1 0 (5 times); PUSH20 inner; PUSH2 INNER_GAS; CALL; POP; STOP
+	let mut code = hex::decode("60006000600060006000").unwrap();
+	code.push(0x73);
+	code.extend_from_slice(&inner()[..]);
+	code.push(0x61);
+	code.extend_from_slice(&(INNER_GAS as u16).to_be_bytes());
+	code.extend_from_slice(&[0xf1, 0x50, 0x00]);
+	accounts.insert(outer(), MemoryAccount { code, ..Default::default() });
+
+	let mut code = hex::decode("6000600055").unwrap();
+	code.extend_from_slice(tail);
+	let mut storage = BTreeMap::new();
+	storage.insert(H256::zero(), H256::from_low_u64_be(1));
+	accounts.insert(inner(), MemoryAccount { code, storage, ..Default::default() });
+
+	accounts
+}
+
+fn frames(tail: &[u8]) -> Vec<Frame> {
+	let config = Config::istanbul();
+	let vicinity = vicinity();
+	let backend = MemoryBackend::new(&vicinity, accounts(tail));
+	let mut executor = common::executor(&backend, &config, GAS_LIMIT);
+
+	let mut frames = Frames::default();
+	let (reason, _) = tracing::using(&mut frames, || {
+		executor.transact_call(caller(), outer(), U256::zero(), Vec::new(), GAS_LIMIT)
+	});
+	assert!(reason.is_succeed());
+	frames.0
+}
+
+#[test]
+fn succeeded() {
+	// STOP
+	assert_eq!(frames(&[0x00]), vec![
+		Frame::Enter { depth: 0, gas_limit: GAS_LIMIT - 21000 },
+		Frame::Enter { depth: 1, gas_limit: INNER_GAS },
+		Frame::Exit {
+			depth: 1,
+			kind: StackExitKind::Succeeded,
+			used_gas: SSTORE_GAS,
+			refunded_gas: 15000,
+			burnt_gas: SSTORE_GAS,
+		},
+		Frame::Exit {
+			depth: 0,
+			kind: StackExitKind::Succeeded,
+			used_gas: OUTER_GAS + SSTORE_GAS,
+			refunded_gas: 15000,
+			burnt_gas: OUTER_GAS + SSTORE_GAS,
+		},
+	]);
+}
+
+#[test]
+fn reverted() {
+	// PUSH1 0; PUSH1 0; REVERT
+	let frames = frames(&[0x60, 0x00, 0x60, 0x00, 0xfd]);
+	assert_eq!(frames[2], Frame::Exit {
+		depth: 1,
+		kind: StackExitKind::Reverted,
+		used_gas: SSTORE_GAS + 6,
+		refunded_gas: 15000,
+		burnt_gas: SSTORE_GAS + 6,
+	});
+	// The refund is dropped.
+	assert_eq!(frames[3], Frame::Exit {
+		depth: 0,
+		kind: StackExitKind::Succeeded,
+		used_gas: OUTER_GAS + SSTORE_GAS + 6,
+		refunded_gas: 0,
+		burnt_gas: OUTER_GAS + SSTORE_GAS + 6,
+	});
+}
+
+#[test]
+fn failed() {
+	// INVALID
+	let frames = frames(&[0xfe]);
+	assert!(matches!(
+		frames[2],
+		Frame::Exit { depth: 1, kind: StackExitKind::Failed, burnt_gas: INNER_GAS, .. }
+	));
+	// All the gas given to the inner call is burnt.
+	assert_eq!(frames[3], Frame::Exit {
+		depth: 0,
+		kind: StackExitKind::Succeeded,
+		used_gas: OUTER_GAS + INNER_GAS,
+		refunded_gas: 0,
+		burnt_gas: OUTER_GAS + INNER_GAS,
+	});
+}
@@ -0,0 +1,100 @@
+//! Gas estimates of `StandardGasOracle` against the executor.
+
+#![cfg(feature = "tracing")]
+
+use std::collections::BTreeMap;
+use primitive_types::{H160, H256, U256};
+use evm::{Config, ExitReason};
+use evm::backend::{MemoryAccount, MemoryBackend};
+use evm::gasometer::oracle::{GasEstimate, GasOracle, StandardGasOracle};
+use evm::tracing::gasometer;
+
+mod common;
+use common::{vicinity, caller};
+
+const GAS_LIMIT: u64 = 200_000;
+
+fn outer() -> H160 { H160::repeat_byte(0x0a) }
+fn inner() -> H160 { H160::repeat_byte(0x0b) }
+
+/// Call `inner` with all the gas left, then pop the result.
+fn call_inner() -> Vec<u8> {
+	let mut code = hex::decode("60006000600060006000").unwrap();
+	code.push(0x73);
+	code.extend_from_slice(&inner()[..]);
+	code.extend_from_slice(&[0x5a, 0xf1, 0x50]);
+	code
+}
+
+/// Call `outer` with `input`, `outer` having `outer_code` and `inner`
+/// having `inner_code` and slot 0 set to 1. Return the exit reason, the gas
+/// used according to the executor and the estimate of the oracle.
+fn estimate(outer_code: Vec<u8>, inner_code: Vec<u8>, input: Vec<u8>) -> (ExitReason, u64, GasEstimate) {
+	let config = Config::istanbul();
+	let vicinity = vicinity();
+	let mut accounts = BTreeMap::new();
+	accounts.insert(outer(), MemoryAccount { code: outer_code, ..Default::default() });
+	let mut storage = BTreeMap::new();
+	storage.insert(H256::zero(), H256::from_low_u64_be(1));
+	accounts.insert(inner(), MemoryAccount { code: inner_code, storage, ..Default::default() });
+	let backend = MemoryBackend::new(&vicinity, accounts);
+	let mut executor = common::executor(&backend, &config, GAS_LIMIT);
+
+	let mut oracle = StandardGasOracle::new();
+	let (reason, _) = gasometer::using(&mut oracle, || {
+		executor.transact_call(caller(), outer(), U256::zero(), input, GAS_LIMIT)
+	});
+	(reason, executor.used_gas(), oracle.estimate())
+}
+
+#[test]
+fn itemized_intrinsic_cost() {
+	let (reason, used_gas, estimate) = estimate(vec![0x00], Vec::new(), vec![0, 0, 1]);
+	assert!(reason.is_succeed());
+	assert_eq!(estimate, GasEstimate {
+		base_cost: 21_000,
+		calldata_cost: 4 + 4 + 16,
+		execution_cost: 0,
+		refund_requested: 0,
+		refund_applied: 0,
+	});
+	assert_eq!(estimate.used_gas(), used_gas);
+}
+
+#[test]
+fn nested_calls_are_accounted_in_their_parent() {
+	// PUSH1 0; PUSH1 0; SSTORE; STOP, clearing slot 0.
+	let inner_code = hex::decode("6000600055").unwrap();
+	let mut outer_code = call_inner();
+	outer_code.extend(call_inner());
+	outer_code.push(0x00);
+
+	let (reason, used_gas, estimate) = estimate(outer_code, inner_code, Vec::new());
+	assert!(reason.is_succeed());
+	assert_eq!(estimate.base_cost, 21_000);
+	assert!(estimate.execution_cost > 0);
+	// Clearing the slot is refunded once, the second call finding it empty.
+	assert_eq!(estimate.refund_requested, 15_000);
+	assert!(estimate.refund_applied > 0);
+	assert_eq!(estimate.used_gas(), used_gas);
+}
+
+#[test]
+fn failed_nested_call_burns_its_gas() {
+	// PUSH1 0; INVALID
+	let mut outer_code = call_inner();
+	outer_code.push(0x00);
+
+	let (reason, used_gas, estimate) = estimate(outer_code, vec![0x60, 0x00, 0xfe], Vec::new());
+	assert!(reason.is_succeed());
+	assert_eq!(estimate.refund_requested, 0);
+	assert_eq!(estimate.used_gas(), used_gas);
+}
+
+#[test]
+fn failed_transaction_uses_all_its_gas() {
+	let (reason, used_gas, estimate) = estimate(vec![0x60, 0x00, 0xfe], Vec::new(), Vec::new());
+	assert!(reason.is_error());
+	assert_eq!(estimate.total_cost(), GAS_LIMIT);
+	assert_eq!(estimate.used_gas(), used_gas);
+}
@@ -0,0 +1,164 @@
+//! Gas snapshot tests.
+//!
+//! Every case in the corpus is executed and its exit reason and used gas
+//! compared against `tests/gas_snapshots.txt`. Any change that alters them
+//! makes the test fail with a diff. Run with `UPDATE_GAS_SNAPSHOTS=1` to rewrite the snapshot file
+//! after an intended gas change.
+
+use std::{collections::BTreeMap, env, fs, path::PathBuf, str::FromStr};
+use primitive_types::{H160, U256};
+use evm::{Config, ExitReason};
+use evm::backend::{MemoryAccount, MemoryVicinity, MemoryBackend};
+use evm::gasometer::OpcodeCostTable;
+
+mod common;
+
+const FIBONACCI: &str = "60e060020a6000350480632839e92814601e57806361047ff414603457005b602a6004356024356047565b8060005260206000f35b603d6004356099565b8060005260206000f35b600082600014605457605e565b8160010190506093565b81600014606957607b565b60756001840360016047565b90506093565b609060018403608c85600186036047565b6047565b90505b92915050565b6000816000148060a95750816001145b60b05760b7565b81905060cf565b60c1600283036099565b60cb600184036099565b0190505b91905056";
+const LOOP: &str = "6080604052348015600f57600080fd5b506004361060285760003560e01c80630f14a40614602d575b600080fd5b605660048036036020811015604157600080fd5b8101908080359060200190929190505050606c565b6040518082815260200191505060405180910390f35b6000806000905060005b83811015608f5760018201915080806001019150506076565b508091505091905056fea26469706673582212202bc9ec597249a9700278fe4ce78da83273cb236e76d4d6797b441454784f901d64736f6c63430007040033";
+// sstore(0, 1); sstore(1, 2); sstore(0, 0); sstore(1, 2)
+const SSTORE: &str = "600160005560026001556000600055600260015500";
+// mstore(0, 0xdeadbeef); revert(0, 32)
+const REVERT: &str = "63deadbeef60005260206000fd";
+// mstore8(0, 0); return(0, 1), deploying a contract whose code is `STOP`.
+const CREATE: &str = "600060005360016000f3";
+
+enum Case {
+	Call { code: &'static str, data: &'static str },
+	Create { init_code: &'static str },
+}
+
+fn corpus() -> Vec<(&'static str, Case)> {
+	vec![
+		("fibonacci10", Case::Call {
+			code: FIBONACCI,
+			data: "61047ff4000000000000000000000000000000000000000000000000000000000000000a",
+		}),
+		("ackermann32", Case::Call {
+			code: FIBONACCI,
+			data: "2839e92800000000000000000000000000000000000000000000000000000000000000030000000000000000000000000000000000000000000000000000000000000002",
+		}),
+		("loop1000", Case::Call {
+			code: LOOP,
+			data: "0f14a40600000000000000000000000000000000000000000000000000000000000003e8",
+		}),
+		("sstore_set_reset_clear", Case::Call { code: SSTORE, data: "" }),
+		("revert", Case::Call { code: REVERT, data: "" }),
+		("create_stop", Case::Create { init_code: CREATE }),
+	]
+}
+
+/// Run `case`, with the opcode costs of `table` if any.
+fn run(config: &Config, table: Option<&OpcodeCostTable>, case: &Case) -> (ExitReason, u64) {
+	let caller = H160::from_str("0xf000000000000000000000000000000000000000").unwrap();
+	let contract = H160::from_str("0x1000000000000000000000000000000000000000").unwrap();
+
+	let vicinity = MemoryVicinity {
+		gas_price: U256::zero(),
+		origin: caller,
+		block_hashes: Vec::new(),
+		max_block_hashes: 256,
+		block_number: Default::default(),
+		block_coinbase: Default::default(),
+		block_timestamp: Default::default(),
+		block_difficulty: Default::default(),
+		block_gas_limit: Default::default(),
+		chain_id: U256::one(),
+	};
+
+	let mut state = BTreeMap::new();
+	state.insert(caller, MemoryAccount {
+		nonce: U256::one(),
+		balance: U256::from(10000000),
+		storage: BTreeMap::new(),
+		code: Vec::new(),
+	});
+	if let Case::Call { code, .. } = case {
+		state.insert(contract, MemoryAccount {
+			nonce: U256::one(),
+			balance: U256::zero(),
+			storage: BTreeMap::new(),
+			code: hex::decode(code).unwrap(),
+		});
+	}
+
+	let gas_limit = 10_000_000;
+	let backend = MemoryBackend::new(&vicinity, state);
+	let mut executor = common::executor(&backend, config, gas_limit);
+	executor.set_opcode_cost_table(table);
+
+	let reason = match case {
+		Case::Call { data, .. } => executor.transact_call(
+			caller, contract, U256::zero(), hex::decode(data).unwrap(), gas_limit,
+		).0,
+		Case::Create { init_code } => executor.transact_create(
+			caller, U256::zero(), hex::decode(init_code).unwrap(), gas_limit,
+		),
+	};
+
+	(reason, executor.used_gas())
+}
+
+#[test]
+fn gas_snapshots() {
+	let configs = [("frontier", Config::frontier()), ("istanbul", Config::istanbul())];
+
+	let mut actual = String::new();
+	for (name, case) in corpus() {
+		for (fork, config) in &configs {
+			let (reason, gas) = run(config, None, &case);
+			actual.push_str(&format!("{} {} {:?} {}\n", name, fork, reason, gas));
+		}
+	}
+
+	let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/gas_snapshots.txt");
+	if env::var("UPDATE_GAS_SNAPSHOTS").is_ok() {
+		fs::write(&path, &actual).unwrap();
+		return
+	}
+
+	let expected = fs::read_to_string(&path).unwrap_or_default();
+	if expected != actual {
+		let expected_lines = expected.lines().collect::<Vec<_>>();
+		let mut diff = String::new();
+		for line in actual.lines() {
+			if !expected_lines.contains(&line) {
+				diff.push_str(&format!("+ {}\n", line));
+			}
+		}
+		for line in &expected_lines {
+			if !actual.lines().any(|l| l == *line) {
+				diff.push_str(&format!("- {}\n", line));
+			}
+		}
+
+		panic!(
+			"gas snapshots changed, rerun with UPDATE_GAS_SNAPSHOTS=1 if intended:\n{}",
+			diff,
+		);
+	}
+}
+
+#[test]
+fn disabled_refunds() {
+	let config = Config::istanbul();
+	let no_refunds = Config { disable_refunds: true, ..Config::istanbul() };
+
+	let sstore = Case::Call { code: SSTORE, data: "" };
+	assert!(run(&no_refunds, None, &sstore).1 > run(&config, None, &sstore).1);
+
+	let fibonacci = Case::Call {
+		code: FIBONACCI,
+		data: "61047ff4000000000000000000000000000000000000000000000000000000000000000a",
+	};
+	assert_eq!(run(&no_refunds, None, &fibonacci).1, run(&config, None, &fibonacci).1);
+}
+
+#[test]
+fn opcode_cost_table() {
+	for config in &[Config::frontier(), Config::istanbul()] {
+		let table = OpcodeCostTable::new(config);
+		for (name, case) in corpus() {
+			assert_eq!(run(config, Some(&table), &case), run(config, None, &case), "{}", name);
+		}
+	}
+}
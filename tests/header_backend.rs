@@ -0,0 +1,154 @@
+//! Backend and vicinity built from an `ethereum` block header.
+
+use std::collections::BTreeMap;
+use primitive_types::{H160, H256, U256};
+use ethereum::Header;
+use evm::Config;
+use evm::backend::{Backend, Basic, HeaderBackend, MemoryAccount, MemoryVicinity, StateProvider};
+
+mod common;
+use common::{caller, contract, GAS_LIMIT};
+
+fn coinbase() -> H160 { H160::repeat_byte(0xcb) }
+
+fn header() -> Header {
+	Header {
+		parent_hash: H256::repeat_byte(0x99),
+		ommers_hash: H256::default(),
+		beneficiary: coinbase(),
+		state_root: H256::default(),
+		transactions_root: H256::default(),
+		receipts_root: H256::default(),
+		logs_bloom: Default::default(),
+		difficulty: U256::from(131_072),
+		number: U256::from(1000),
+		gas_limit: U256::from(8_000_000),
+		gas_used: U256::zero(),
+		timestamp: 1_600_000_000,
+		extra_data: Vec::new(),
+		mix_hash: H256::default(),
+		nonce: Default::default(),
+	}
+}
+
+/// Accounts in memory, with the hash of block `n` being `n` itself.
+struct State(BTreeMap<H160, MemoryAccount>);
+
+impl StateProvider for State {
+	fn exists(&self, address: H160) -> bool { self.0.contains_key(&address) }
+	fn basic(&self, address: H160) -> Basic {
+		self.0.get(&address).map(|account| Basic {
+			balance: account.balance,
+			nonce: account.nonce,
+		}).unwrap_or_default()
+	}
+	fn code(&self, address: H160) -> Vec<u8> {
+		self.0.get(&address).map(|account| account.code.clone()).unwrap_or_default()
+	}
+	fn storage(&self, address: H160, index: H256) -> H256 {
+		self.0.get(&address)
+			.and_then(|account| account.storage.get(&index).copied())
+			.unwrap_or_default()
+	}
+	fn block_hash(&self, number: U256) -> H256 {
+		H256::from_low_u64_be(number.as_u64())
+	}
+}
+
+fn state() -> State {
+	let mut accounts = BTreeMap::new();
+	let mut storage = BTreeMap::new();
+	storage.insert(H256::zero(), H256::from_low_u64_be(7));
+	// PUSH1 0; SLOAD; NUMBER; ADD; PUSH1 0; MSTORE; PUSH1 32; PUSH1 0; RETURN
+	accounts.insert(contract(), MemoryAccount {
+		code: hex::decode("600054430160005260206000f3").unwrap(),
+		storage,
+		..Default::default()
+	});
+	State(accounts)
+}
+
+#[test]
+fn block_environment_from_header() {
+	let header = header();
+	let backend = HeaderBackend::new(&header, U256::one(), U256::from(3), caller(), state());
+
+	assert_eq!(backend.gas_price(), U256::from(3));
+	assert_eq!(backend.origin(), caller());
+	assert_eq!(backend.block_number(), U256::from(1000));
+	assert_eq!(backend.block_coinbase(), coinbase());
+	assert_eq!(backend.block_timestamp(), U256::from(1_600_000_000u64));
+	assert_eq!(backend.block_difficulty(), U256::from(131_072));
+	assert_eq!(backend.block_gas_limit(), U256::from(8_000_000));
+	assert_eq!(backend.chain_id(), U256::one());
+
+	// Only preceding blocks have a hash, the window being left to the
+	// executor.
+	assert_eq!(backend.block_hash(U256::from(1000)), H256::default());
+	assert_eq!(backend.block_hash(U256::from(999)), H256::from_low_u64_be(999));
+	assert_eq!(backend.block_hash(U256::from(743)), H256::from_low_u64_be(743));
+
+	let mut backend = backend;
+	backend.set_transaction(U256::from(5), contract());
+	assert_eq!(backend.gas_price(), U256::from(5));
+	assert_eq!(backend.origin(), contract());
+}
+
+#[test]
+fn vicinity_from_header() {
+	let header = header();
+	let vicinity = MemoryVicinity::from_header(
+		&header, U256::one(), U256::from(3), caller(), vec![header.parent_hash],
+	);
+
+	assert_eq!(vicinity.gas_price, U256::from(3));
+	assert_eq!(vicinity.origin, caller());
+	assert_eq!(vicinity.chain_id, U256::one());
+	assert_eq!(vicinity.block_number, header.number);
+	assert_eq!(vicinity.block_coinbase, coinbase());
+	assert_eq!(vicinity.block_timestamp, U256::from(header.timestamp));
+	assert_eq!(vicinity.block_difficulty, header.difficulty);
+	assert_eq!(vicinity.block_gas_limit, header.gas_limit);
+	assert_eq!(vicinity.block_hash(U256::from(999)), header.parent_hash);
+}
+
+#[test]
+fn executes_against_provider() {
+	let config = Config::istanbul();
+	let header = header();
+	let backend = HeaderBackend::new(&header, U256::one(), U256::zero(), caller(), state());
+	let mut executor = common::executor(&backend, &config, GAS_LIMIT);
+
+	let (reason, output) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), GAS_LIMIT);
+	assert!(reason.is_succeed());
+	assert_eq!(U256::from_big_endian(&output), U256::from(1007));
+}
+
+/// BLOCKHASH of `number`, run against the provider with the given history.
+fn blockhash(block_hash_history: u64, number: u16) -> H256 {
+	let config = Config { block_hash_history, ..Config::istanbul() };
+	let header = header();
+	let mut state = state();
+	// PUSH2 number; BLOCKHASH; PUSH1 0; MSTORE; PUSH1 32; PUSH1 0; RETURN
+	let mut code = vec![0x61];
+	code.extend_from_slice(&number.to_be_bytes());
+	code.extend_from_slice(&[0x40, 0x60, 0x00, 0x52, 0x60, 0x20, 0x60, 0x00, 0xf3]);
+	state.0.insert(contract(), MemoryAccount { code, ..Default::default() });
+	let backend = HeaderBackend::new(&header, U256::one(), U256::zero(), caller(), state);
+	let mut executor = common::executor(&backend, &config, GAS_LIMIT);
+
+	let (reason, output) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), GAS_LIMIT);
+	assert!(reason.is_succeed());
+	H256::from_slice(&output)
+}
+
+#[test]
+fn block_hash_history_is_configurable() {
+	assert_eq!(blockhash(256, 744), H256::from_low_u64_be(744));
+	assert_eq!(blockhash(256, 743), H256::default());
+
+	assert_eq!(blockhash(300, 743), H256::from_low_u64_be(743));
+	assert_eq!(blockhash(300, 700), H256::from_low_u64_be(700));
+	assert_eq!(blockhash(300, 699), H256::default());
+	assert_eq!(blockhash(300, 1000), H256::default());
+}
@@ -0,0 +1,84 @@
+//! Instruction limit of `StackExecutor`, per transaction.
+
+use std::collections::BTreeMap;
+use primitive_types::{H160, H256, U256};
+use evm::{Config, ExitFatal, ExitReason, ExitSucceed};
+use evm::backend::{Backend, MemoryAccount, MemoryBackend};
+
+mod common;
+use common::{vicinity, caller, contract, GAS_LIMIT};
+
+fn outer() -> H160 { H160::repeat_byte(0x0e) }
+
+/// The contract executes 10 instructions, and the outer contract calls it
+/// after 8 instructions, then executes 4 more to store 1 in slot 0.
+fn accounts() -> BTreeMap<H160, MemoryAccount> {
+	let mut accounts = BTreeMap::new();
+	// Nine JUMPDEST and a STOP.
+	let mut code = vec![0x5b; 9];
+	code.push(0x00);
+	accounts.insert(contract(), MemoryAccount { code, ..Default::default() });
+	// PUSH1 0 (five times); PUSH20 contract; GAS; CALL; PUSH1 1; PUSH1 0;
+	// SSTORE; STOP
+	let mut code = vec![0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x73];
+	code.extend_from_slice(&contract()[..]);
+	code.extend_from_slice(&[0x5a, 0xf1, 0x60, 0x01, 0x60, 0x00, 0x55, 0x00]);
+	accounts.insert(outer(), MemoryAccount { code, ..Default::default() });
+	accounts
+}
+
+/// Run `transactions` calls to a contract executing 10 instructions, with
+/// an instruction limit of `limit`. Return the exit reasons and the count of
+/// instructions after the last call.
+fn run(limit: u64, transactions: usize) -> (Vec<ExitReason>, u64) {
+	let config = Config::istanbul();
+	let vicinity = vicinity();
+	let backend = MemoryBackend::new(&vicinity, accounts());
+	let mut executor = common::executor(&backend, &config, u64::MAX);
+	executor.set_instruction_limit(Some(limit));
+
+	let reasons = (0..transactions).map(|_| {
+		executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), GAS_LIMIT).0
+	}).collect();
+	(reasons, executor.instructions())
+}
+
+#[test]
+fn limit_is_inclusive() {
+	let (reasons, instructions) = run(10, 1);
+	assert!(reasons[0].is_succeed());
+	assert_eq!(instructions, 10);
+
+	let (reasons, _) = run(9, 1);
+	assert_eq!(reasons[0], ExitReason::Fatal(ExitFatal::ExecutionTimeout));
+}
+
+#[test]
+fn limit_applies_to_each_transaction() {
+	let (reasons, instructions) = run(10, 3);
+	assert!(reasons.iter().all(ExitReason::is_succeed), "{:?}", reasons);
+	assert_eq!(instructions, 10);
+}
+
+#[test]
+fn limit_applies_across_frames() {
+	let config = Config::istanbul();
+	let vicinity = vicinity();
+	let backend = MemoryBackend::new(&vicinity, accounts());
+
+	let run = |limit: u64| {
+		let mut executor = common::executor(&backend, &config, u64::MAX);
+		executor.set_instruction_limit(Some(limit));
+		let (reason, _) = executor.transact_call(caller(), outer(), U256::zero(), Vec::new(), GAS_LIMIT);
+		(reason, executor.instructions(), executor.state().storage(outer(), H256::zero()))
+	};
+
+	assert_eq!(run(22), (ExitReason::Succeed(ExitSucceed::Stopped), 22, H256::from_low_u64_be(1)));
+
+	// Exceeded inside the nested call, the outer call aborting as well rather
+	// than seeing a failed call.
+	let (reason, instructions, stored) = run(12);
+	assert_eq!(reason, ExitReason::Fatal(ExitFatal::ExecutionTimeout));
+	assert_eq!(instructions, 13);
+	assert_eq!(stored, H256::zero());
+}
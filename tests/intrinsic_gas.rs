@@ -0,0 +1,77 @@
+//! Intrinsic gas overridden through `Config::intrinsic_gas`.
+
+use std::collections::BTreeMap;
+use primitive_types::{H160, U256};
+use evm::{Config, ExitError, ExitReason};
+use evm::backend::{MemoryAccount, MemoryBackend};
+
+mod common;
+use common::{vicinity, caller, GAS_LIMIT};
+
+fn target() -> H160 { H160::repeat_byte(0x01) }
+
+/// Free calldata, with a cheaper base cost for calls.
+fn free_calldata(config: &Config, is_create: bool, _zero: usize, _non_zero: usize) -> (u64, u64) {
+	if is_create {
+		(config.gas_transaction_create, 0)
+	} else {
+		(1000, 0)
+	}
+}
+
+/// Calldata priced per byte, whatever its value.
+fn flat_calldata(config: &Config, _is_create: bool, zero: usize, non_zero: usize) -> (u64, u64) {
+	(config.gas_transaction_call, 10 * (zero + non_zero) as u64)
+}
+
+/// Call an empty account with 2 zero and 3 non-zero data bytes, or deploy
+/// them as init code. Return the exit reason and the gas used.
+fn transact(config: &Config, create: bool, gas_limit: u64) -> (ExitReason, u64) {
+	let vicinity = vicinity();
+	let backend = MemoryBackend::new(&vicinity, BTreeMap::<H160, MemoryAccount>::new());
+	let mut executor = common::executor(&backend, config, gas_limit);
+
+	let data = vec![0x00, 0x00, 0x01, 0x02, 0x03];
+	let reason = if create {
+		// Init code starting with STOP.
+		executor.transact_create(caller(), U256::zero(), data, gas_limit)
+	} else {
+		executor.transact_call(caller(), target(), U256::zero(), data, gas_limit).0
+	};
+	(reason, executor.used_gas())
+}
+
+#[test]
+fn default_pricing() {
+	let (reason, used_gas) = transact(&Config::istanbul(), false, GAS_LIMIT);
+	assert!(reason.is_succeed());
+	assert_eq!(used_gas, 21000 + 2 * 4 + 3 * 16);
+}
+
+#[test]
+fn overridden_pricing() {
+	let config = Config { intrinsic_gas: Some(free_calldata), ..Config::istanbul() };
+	let (reason, used_gas) = transact(&config, false, GAS_LIMIT);
+	assert!(reason.is_succeed());
+	assert_eq!(used_gas, 1000);
+
+	let (reason, used_gas) = transact(&config, true, GAS_LIMIT);
+	assert!(reason.is_succeed());
+	assert_eq!(used_gas, 53000);
+
+	let config = Config { intrinsic_gas: Some(flat_calldata), ..Config::istanbul() };
+	let (reason, used_gas) = transact(&config, false, GAS_LIMIT);
+	assert!(reason.is_succeed());
+	assert_eq!(used_gas, 21000 + 5 * 10);
+}
+
+#[test]
+fn overridden_pricing_is_checked_against_the_limit() {
+	let config = Config { intrinsic_gas: Some(flat_calldata), ..Config::istanbul() };
+	let (reason, _) = transact(&config, false, 21049);
+	assert_eq!(reason, ExitReason::Error(ExitError::OutOfGas));
+
+	let (reason, used_gas) = transact(&config, false, 21050);
+	assert!(reason.is_succeed());
+	assert_eq!(used_gas, 21050);
+}
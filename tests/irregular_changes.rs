@@ -0,0 +1,74 @@
+//! Irregular state changes applied to a backend outside of transactions.
+
+use std::collections::BTreeMap;
+use primitive_types::{H160, H256, U256};
+use evm::ExitError;
+use evm::backend::{
+	Backend, MemoryAccount, MemoryBackend, IrregularChange, IrregularChangeError, apply_irregular_changes,
+};
+
+mod common;
+use common::vicinity;
+
+fn alice() -> H160 { H160::repeat_byte(0xa1) }
+fn bob() -> H160 { H160::repeat_byte(0xb0) }
+
+fn accounts() -> BTreeMap<H160, MemoryAccount> {
+	let mut accounts = BTreeMap::new();
+	accounts.insert(alice(), MemoryAccount {
+		balance: U256::from(100),
+		..Default::default()
+	});
+	accounts
+}
+
+#[test]
+fn changes_are_applied_in_order() {
+	let vicinity = vicinity();
+	let mut backend = MemoryBackend::new(&vicinity, accounts());
+
+	let result = apply_irregular_changes(&mut backend, &[
+		IrregularChange::Transfer { source: alice(), target: bob(), value: U256::from(30) },
+		IrregularChange::SetNonce { address: bob(), nonce: U256::from(7) },
+		IrregularChange::SetCode { address: bob(), code: vec![0x00] },
+		IrregularChange::SetStorage {
+			address: bob(),
+			index: H256::from_low_u64_be(1),
+			value: H256::from_low_u64_be(2),
+		},
+		IrregularChange::Drain { source: alice(), target: bob() },
+	]);
+	assert_eq!(result, Ok(()));
+
+	assert_eq!(backend.basic(alice()).balance, U256::zero());
+	let bob_basic = backend.basic(bob());
+	assert_eq!(bob_basic.balance, U256::from(100));
+	assert_eq!(bob_basic.nonce, U256::from(7));
+	assert_eq!(backend.code(bob()), vec![0x00]);
+	assert_eq!(backend.storage(bob(), H256::from_low_u64_be(1)), H256::from_low_u64_be(2));
+
+	let result = apply_irregular_changes(&mut backend, &[
+		IrregularChange::SetBalance { address: alice(), balance: U256::from(5) },
+	]);
+	assert_eq!(result, Ok(()));
+	assert_eq!(backend.basic(alice()).balance, U256::from(5));
+}
+
+#[test]
+fn transfer_shortfall_stops_applying() {
+	let vicinity = vicinity();
+	let mut backend = MemoryBackend::new(&vicinity, accounts());
+
+	let result = apply_irregular_changes(&mut backend, &[
+		IrregularChange::Transfer { source: alice(), target: bob(), value: U256::from(60) },
+		IrregularChange::Transfer { source: alice(), target: bob(), value: U256::from(60) },
+		IrregularChange::SetNonce { address: bob(), nonce: U256::from(7) },
+	]);
+	assert_eq!(result, Err(IrregularChangeError { index: 1, error: ExitError::OutOfFund }));
+
+	// The first transfer stays applied, the failing one and the ones after
+	// it have no effect.
+	assert_eq!(backend.basic(alice()).balance, U256::from(40));
+	assert_eq!(backend.basic(bob()).balance, U256::from(60));
+	assert_eq!(backend.basic(bob()).nonce, U256::zero());
+}
@@ -0,0 +1,94 @@
+//! L1SLOAD, as specified by RIP-7728, installed as a custom opcode.
+
+#![cfg(feature = "l1sload")]
+
+use std::collections::BTreeMap;
+use primitive_types::{H160, H256, U256};
+use evm::{Config, ExitError, ExitReason, Opcode};
+use evm::backend::{MemoryAccount, MemoryBackend};
+use evm::l1sload::{L1Sload, L1StateProvider, L1SLOAD_GAS};
+
+mod common;
+use common::{vicinity, caller, contract, GAS_LIMIT};
+
+const L1SLOAD: Opcode = Opcode(0xc1);
+
+fn l1_contract() -> H160 { H160::repeat_byte(0x11) }
+
+/// Provider without any L1 state.
+struct Unavailable;
+
+impl L1StateProvider for Unavailable {
+	fn storage(&self, _address: H160, _key: H256) -> Option<H256> {
+		None
+	}
+}
+
+/// Load slot `key` of the L1 contract with `opcode` and return the result.
+fn load(opcode: Opcode, key: u8) -> Vec<u8> {
+	// PUSH1 key; PUSH20 l1_contract
+	let mut code = vec![0x60, key, 0x73];
+	code.extend_from_slice(&l1_contract()[..]);
+	code.push(opcode.as_u8());
+	// PUSH1 0; MSTORE; PUSH1 32; PUSH1 0; RETURN
+	code.extend_from_slice(&hex::decode("60005260206000f3").unwrap());
+	code
+}
+
+/// Call a contract with `code`, with L1SLOAD served by `l1sload`. Return the
+/// exit reason, the return value and the gas used.
+fn call<P: L1StateProvider + 'static>(code: Vec<u8>, l1sload: L1Sload<P>) -> (ExitReason, Vec<u8>, u64) {
+	let config = Config::istanbul();
+	let vicinity = vicinity();
+	let mut accounts = BTreeMap::new();
+	accounts.insert(contract(), MemoryAccount { code, ..Default::default() });
+	let backend = MemoryBackend::new(&vicinity, accounts);
+	let mut executor = common::executor(&backend, &config, GAS_LIMIT);
+	executor.set_custom_opcodes(Box::new(l1sload));
+
+	let (reason, output) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), GAS_LIMIT);
+	(reason, output, executor.used_gas())
+}
+
+fn l1_state() -> BTreeMap<(H160, H256), H256> {
+	let mut state = BTreeMap::new();
+	state.insert((l1_contract(), H256::from_low_u64_be(1)), H256::repeat_byte(0xab));
+	state
+}
+
+#[test]
+fn loaded_value_is_pushed() {
+	let (reason, output, _) = call(load(L1SLOAD, 1), L1Sload::new(L1SLOAD, l1_state()));
+	assert!(reason.is_succeed());
+	assert_eq!(output, H256::repeat_byte(0xab).as_bytes());
+
+	// Missing slots are zero.
+	let (reason, output, _) = call(load(L1SLOAD, 2), L1Sload::new(L1SLOAD, l1_state()));
+	assert!(reason.is_succeed());
+	assert_eq!(output, H256::zero().as_bytes());
+}
+
+#[test]
+fn gas_is_charged() {
+	// The same with ADD, which costs 3 gas.
+	let (_, _, add_gas) = call(load(Opcode::ADD, 1), L1Sload::new(L1SLOAD, l1_state()));
+
+	let (reason, _, used_gas) = call(load(L1SLOAD, 1), L1Sload::new(L1SLOAD, l1_state()));
+	assert!(reason.is_succeed());
+	assert_eq!(used_gas, add_gas - 3 + L1SLOAD_GAS);
+
+	let (reason, _, used_gas) = call(load(L1SLOAD, 1), L1Sload::new(L1SLOAD, l1_state()).gas(2000));
+	assert!(reason.is_succeed());
+	assert_eq!(used_gas, add_gas - 3 + 2000);
+}
+
+#[test]
+fn fails_without_l1_state() {
+	let (reason, _, used_gas) = call(load(L1SLOAD, 1), L1Sload::new(L1SLOAD, Unavailable));
+	assert_eq!(reason, ExitReason::Error(ExitError::Other("L1 state unavailable".into())));
+	assert_eq!(used_gas, GAS_LIMIT);
+
+	// Only the configured opcode is served.
+	let (reason, _, _) = call(load(Opcode(0xc2), 1), L1Sload::new(L1SLOAD, l1_state()));
+	assert_eq!(reason, ExitReason::Error(ExitError::OutOfGas));
+}
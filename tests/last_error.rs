@@ -0,0 +1,152 @@
+//! Errors reported by `StackExecutor::last_error` on failing calls and
+//! creates.
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use primitive_types::{H160, U256};
+use evm::{Config, Context, ExitError, Opcode};
+use evm::backend::{MemoryAccount, MemoryBackend};
+use evm::executor::{
+	StackExecutor, MemoryStackState, StackSubstateMetadata, PrecompileOutput, ErrorContext,
+};
+
+mod common;
+use common::{vicinity, caller};
+
+const GAS_LIMIT: u64 = 1_000_000;
+
+fn invalid() -> H160 { H160::repeat_byte(0x01) }
+fn stop() -> H160 { H160::repeat_byte(0x02) }
+fn precompile() -> H160 { H160::from_low_u64_be(1) }
+
+/// Precompile at address 1 always failing.
+fn failing<S>(
+	address: H160,
+	_input: &[u8],
+	_target_gas: Option<u64>,
+	_context: &Context,
+	_state: &mut S,
+	_is_static: bool,
+) -> Option<Result<PrecompileOutput, ExitError>> {
+	if address != precompile() {
+		return None
+	}
+
+	Some(Err(ExitError::Other(Cow::Borrowed("failing"))))
+}
+
+fn accounts() -> BTreeMap<H160, MemoryAccount> {
+	let mut accounts = BTreeMap::new();
+	accounts.insert(caller(), MemoryAccount {
+		balance: U256::from(10_000_000),
+		..Default::default()
+	});
+	// PUSH1 0; INVALID
+	accounts.insert(invalid(), MemoryAccount {
+		code: vec![0x60, 0x00, 0xfe],
+		..Default::default()
+	});
+	accounts.insert(stop(), MemoryAccount {
+		code: vec![0x00],
+		..Default::default()
+	});
+	accounts
+}
+
+/// Run `f` on an executor over `accounts` with `gas`, shared by the
+/// transactions of `f`.
+fn with_executor(
+	gas: u64,
+	f: impl for<'config, 'backend, 'vicinity> FnOnce(
+		&mut StackExecutor<'config, MemoryStackState<'backend, 'config, MemoryBackend<'vicinity>>>,
+	),
+) {
+	let config = Config::istanbul();
+	let vicinity = vicinity();
+	let backend = MemoryBackend::new(&vicinity, accounts());
+	let metadata = StackSubstateMetadata::new(gas, &config);
+	let state = MemoryStackState::new(metadata, &backend);
+	let mut executor = StackExecutor::new_with_precompile(state, &config, failing);
+	f(&mut executor)
+}
+
+#[test]
+fn call_errors() {
+	with_executor(u64::MAX, |executor| {
+		let (reason, _) = executor.transact_call(caller(), invalid(), U256::zero(), Vec::new(), GAS_LIMIT);
+		assert!(reason.is_error());
+		assert_eq!(executor.last_error(), Some(&ErrorContext {
+			// The gasometer charges all the gas left for INVALID.
+			error: ExitError::OutOfGas,
+			address: invalid(),
+			position: Some(2),
+			opcode: Some(Opcode::INVALID),
+		}));
+
+		// Reset by the next transaction.
+		let (reason, _) = executor.transact_call(caller(), stop(), U256::zero(), Vec::new(), GAS_LIMIT);
+		assert!(reason.is_succeed());
+		assert_eq!(executor.last_error(), None);
+
+		let (reason, _) = executor.transact_call(caller(), precompile(), U256::zero(), Vec::new(), GAS_LIMIT);
+		assert!(reason.is_error());
+		assert_eq!(executor.last_error(), Some(&ErrorContext {
+			error: ExitError::Other(Cow::Borrowed("failing")),
+			address: precompile(),
+			position: None,
+			opcode: None,
+		}));
+	});
+
+	// Not enough gas for the intrinsic cost.
+	with_executor(20_000, |executor| {
+		let (reason, _) = executor.transact_call(caller(), stop(), U256::zero(), Vec::new(), 20_000);
+		assert!(reason.is_error());
+		assert_eq!(executor.last_error(), Some(&ErrorContext {
+			error: ExitError::OutOfGas,
+			address: stop(),
+			position: None,
+			opcode: None,
+		}));
+	});
+}
+
+#[test]
+fn create_errors() {
+	with_executor(u64::MAX, |executor| {
+		// PUSH1 0; INVALID
+		let reason = executor.transact_create(caller(), U256::zero(), vec![0x60, 0x00, 0xfe], GAS_LIMIT);
+		assert!(reason.is_error());
+		let error = executor.last_error().cloned().unwrap();
+		assert_eq!(error.error, ExitError::OutOfGas);
+		assert_eq!(error.position, Some(2));
+		assert_eq!(error.opcode, Some(Opcode::INVALID));
+
+		// PUSH2 0x6001; PUSH1 0; RETURN, one byte over the contract limit.
+		let reason = executor.transact_create(caller(), U256::zero(), hex::decode("6160016000f3").unwrap(), GAS_LIMIT);
+		assert!(reason.is_error());
+		let error = executor.last_error().cloned().unwrap();
+		assert_eq!(error.error, ExitError::CreateContractLimit);
+		assert_eq!((error.position, error.opcode), (None, None));
+
+		// PUSH1 100; PUSH1 0; RETURN, without the gas to deposit the code.
+		let reason = executor.transact_create(caller(), U256::zero(), hex::decode("60646000f3").unwrap(), 10_000);
+		assert!(reason.is_error());
+		let error = executor.last_error().cloned().unwrap();
+		assert_eq!(error.error, ExitError::OutOfGas);
+		assert_eq!((error.position, error.opcode), (None, None));
+
+		let reason = executor.transact_create(caller(), U256::zero(), vec![0x00], GAS_LIMIT);
+		assert!(reason.is_succeed());
+		assert_eq!(executor.last_error(), None);
+	});
+
+	// Not enough gas for the intrinsic cost.
+	with_executor(50_000, |executor| {
+		let reason = executor.transact_create(caller(), U256::zero(), vec![0x00], 50_000);
+		assert!(reason.is_error());
+		let error = executor.last_error().cloned().unwrap();
+		assert_eq!(error.error, ExitError::OutOfGas);
+		assert_eq!((error.position, error.opcode), (None, None));
+	});
+}
@@ -0,0 +1,157 @@
+//! Committing a block of `LayeredBackend` is equivalent to applying its
+//! changes to a `MemoryBackend`, on generated states and changes, and block
+//! boundaries behave as documented.
+
+use std::collections::BTreeMap;
+use primitive_types::{H160, H256, U256};
+use evm::backend::{Apply, ApplyBackend, Backend, Basic, LayeredBackend, Log, MemoryAccount, MemoryBackend};
+use evm::generator::Generator;
+
+mod common;
+use common::vicinity;
+
+/// Changes to existing and new accounts of `state`.
+fn changes(
+	generator: &mut Generator,
+	state: &BTreeMap<H160, MemoryAccount>,
+) -> Vec<Apply<Vec<(H256, H256)>>> {
+	let addresses = state.keys().cloned().collect::<Vec<_>>();
+	let mut changes = Vec::new();
+
+	for _ in 0..generator.below(16) {
+		let address = if addresses.is_empty() || generator.below(4) == 0 {
+			generator.address()
+		} else {
+			addresses[generator.below(addresses.len() as u64) as usize]
+		};
+
+		if generator.below(8) == 0 {
+			changes.push(Apply::Delete { address });
+			continue
+		}
+
+		let storage = (0..generator.below(4)).map(|_| {
+			let value = if generator.below(2) == 0 { H256::default() } else { generator.key() };
+			(generator.key(), value)
+		}).collect();
+		let empty = generator.below(8) == 0;
+		let code_len = generator.below(64) as usize;
+		changes.push(Apply::Modify {
+			address,
+			basic: if empty {
+				Basic::default()
+			} else {
+				Basic {
+					balance: generator.u256_below(U256::one() << 128),
+					nonce: U256::from(generator.below(1 << 32)),
+				}
+			},
+			code: Some(if empty { Vec::new() } else { generator.code(code_len) }),
+			storage,
+			reset_storage: generator.below(8) == 0,
+		});
+	}
+
+	changes
+}
+
+#[test]
+fn layered_commit_matches_memory_apply() {
+	let vicinity = vicinity();
+
+	for seed in 0..64 {
+		let mut generator = Generator::new(seed);
+		let state = generator.state(16, 8, 32);
+		let delete_empty = generator.below(2) == 0;
+
+		let mut memory = MemoryBackend::new(&vicinity, state.clone());
+		let mut layered = LayeredBackend::new(vicinity.clone(), state);
+		layered.begin_block();
+
+		for _ in 0..4 {
+			let changes = changes(&mut generator, memory.state());
+			memory.apply(changes.clone(), Vec::new(), delete_empty);
+			layered.apply(changes, Vec::new(), delete_empty);
+		}
+		layered.commit_block(H256::default());
+
+		assert_eq!(memory.state(), layered.state(), "seed {}", seed);
+	}
+}
+
+/// Credit `balance` to `address`, with a log.
+fn credit(backend: &mut LayeredBackend, address: H160, balance: u64) {
+	let log = Log { address, topics: Vec::new(), data: vec![1] };
+	backend.apply(vec![Apply::Modify {
+		address,
+		basic: Basic { balance: U256::from(balance), nonce: U256::zero() },
+		code: None,
+		storage: vec![(H256::from_low_u64_be(1), H256::from_low_u64_be(balance))],
+		reset_storage: false,
+	}], vec![log], false);
+}
+
+#[test]
+fn rollback_drops_the_open_block() {
+	let address = H160::repeat_byte(1);
+	let mut backend = LayeredBackend::new(vicinity(), BTreeMap::new());
+
+	backend.begin_block();
+	credit(&mut backend, address, 10);
+	backend.commit_block(H256::repeat_byte(0xb0));
+
+	backend.begin_block();
+	credit(&mut backend, address, 20);
+	assert!(backend.in_block());
+	assert_eq!(backend.basic(address).balance, U256::from(20));
+	assert_eq!(backend.state()[&address].balance, U256::from(10));
+	backend.rollback_block();
+
+	assert!(!backend.in_block());
+	assert_eq!(backend.basic(address).balance, U256::from(10));
+	assert_eq!(backend.storage(address, H256::from_low_u64_be(1)), H256::from_low_u64_be(10));
+	assert_eq!(backend.logs().len(), 1);
+	assert_eq!(backend.block_number(), U256::one());
+}
+
+#[test]
+fn committed_blocks_are_hashed() {
+	let mut backend = LayeredBackend::new(vicinity(), BTreeMap::new());
+	for number in 0..3 {
+		backend.begin_block();
+		backend.commit_block(H256::from_low_u64_be(number + 1));
+	}
+
+	assert_eq!(backend.block_number(), U256::from(3));
+	assert_eq!(backend.block_hash(U256::from(0)), H256::from_low_u64_be(1));
+	assert_eq!(backend.block_hash(U256::from(2)), H256::from_low_u64_be(3));
+	assert_eq!(backend.block_hash(U256::from(3)), H256::default());
+
+	// A rolled back block is not hashed.
+	backend.begin_block();
+	backend.rollback_block();
+	assert_eq!(backend.block_number(), U256::from(3));
+	assert_eq!(backend.block_hash(U256::from(3)), H256::default());
+}
+
+#[test]
+#[should_panic(expected = "Block already open")]
+fn nested_blocks_panic() {
+	let mut backend = LayeredBackend::new(vicinity(), BTreeMap::new());
+	backend.begin_block();
+	backend.begin_block();
+}
+
+#[test]
+#[should_panic(expected = "No open block to apply to")]
+fn apply_outside_of_a_block_panics() {
+	let mut backend = LayeredBackend::new(vicinity(), BTreeMap::new());
+	credit(&mut backend, H160::repeat_byte(1), 10);
+}
+
+#[test]
+#[should_panic(expected = "No open block to roll back")]
+fn rollback_outside_of_a_block_panics() {
+	let mut backend = LayeredBackend::new(vicinity(), BTreeMap::new());
+	backend.rollback_block();
+}
@@ -0,0 +1,95 @@
+//! Logs streamed to a listener and filtered after execution.
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+use primitive_types::{H160, H256, U256};
+use evm::Config;
+use evm::backend::{Log, MemoryAccount, MemoryBackend};
+use evm::executor::{ExecutionLogs, LogFilter};
+
+mod common;
+use common::{vicinity, caller};
+
+const GAS_LIMIT: u64 = 1_000_000;
+
+fn emitter() -> H160 { H160::repeat_byte(0xa0) }
+fn reverter() -> H160 { H160::repeat_byte(0xb0) }
+
+fn topic(byte: u8) -> H256 { H256::from_low_u64_be(byte as u64) }
+
+fn accounts() -> BTreeMap<H160, MemoryAccount> {
+	let mut accounts = BTreeMap::new();
+
+	// LOG1 0x11; CALL reverter; POP; LOG2 0x22 0x33; STOP
+	let mut code = hex::decode("601160006000a1").unwrap();
+	code.extend_from_slice(&hex::decode("60006000600060006000").unwrap());
+	code.push(0x73);
+	code.extend_from_slice(&reverter()[..]);
+	code.extend_from_slice(&hex::decode("5af150").unwrap());
+	code.extend_from_slice(&hex::decode("6033602260006000a200").unwrap());
+	accounts.insert(emitter(), MemoryAccount { code, ..Default::default() });
+
+	// LOG1 0x11; REVERT
+	accounts.insert(reverter(), MemoryAccount {
+		code: hex::decode("601160006000a160006000fd").unwrap(),
+		..Default::default()
+	});
+
+	accounts
+}
+
+/// Call the emitter, returning the logs seen by the listener and the logs
+/// kept in the state.
+fn call() -> (Vec<Log>, ExecutionLogs) {
+	let config = Config::istanbul();
+	let vicinity = vicinity();
+	let backend = MemoryBackend::new(&vicinity, accounts());
+	let mut executor = common::executor(&backend, &config, GAS_LIMIT);
+
+	let streamed = Rc::new(RefCell::new(Vec::new()));
+	let sink = streamed.clone();
+	executor.set_log_listener(Box::new(move |log| sink.borrow_mut().push(log.clone())));
+
+	let (reason, _) = executor.transact_call(caller(), emitter(), U256::zero(), Vec::new(), GAS_LIMIT);
+	assert!(reason.is_succeed());
+	assert!(executor.take_log_listener().is_some());
+
+	let (_, logs) = executor.into_state().deconstruct();
+	let streamed = streamed.borrow().clone();
+	(streamed, logs.into_iter().collect())
+}
+
+#[test]
+fn listener_sees_reverted_logs() {
+	let (streamed, logs) = call();
+
+	let emitted = streamed.iter().map(|log| (log.address, log.topics.clone())).collect::<Vec<_>>();
+	assert_eq!(emitted, vec![
+		(emitter(), vec![topic(0x11)]),
+		(reverter(), vec![topic(0x11)]),
+		(emitter(), vec![topic(0x22), topic(0x33)]),
+	]);
+
+	// The log of the reverted frame is dropped from the state.
+	assert_eq!(logs.0, vec![streamed[0].clone(), streamed[2].clone()]);
+}
+
+#[test]
+fn filters() {
+	let (_, logs) = call();
+
+	assert_eq!(logs.by_address(emitter()).count(), 2);
+	assert_eq!(logs.by_address(reverter()).count(), 0);
+	assert_eq!(logs.by_signature(topic(0x22)).collect::<Vec<_>>(), vec![&logs.0[1]]);
+	assert_eq!(logs.by_signature(topic(0x33)).count(), 0);
+
+	// Any topic at the first position, 0x33 at the second.
+	let filter = LogFilter { addresses: Vec::new(), topics: vec![None, Some(topic(0x33))] };
+	assert_eq!(logs.filter(&filter).collect::<Vec<_>>(), vec![&logs.0[1]]);
+
+	let filter = LogFilter { addresses: vec![reverter()], topics: Vec::new() };
+	assert_eq!(logs.filter(&filter).count(), 0);
+
+	assert_eq!(logs.filter(&LogFilter::default()).count(), 2);
+}
@@ -0,0 +1,133 @@
+//! Executor metrics recorded through the `metrics` facade.
+
+#![cfg(all(feature = "metrics", not(feature = "deterministic")))]
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use metrics::{
+	Counter, CounterFn, Gauge, Histogram, HistogramFn, Key, KeyName, Metadata, Recorder,
+	SharedString, Unit,
+};
+use primitive_types::{H160, U256};
+use evm::{Config, Context, ExitError, ExitSucceed};
+use evm::backend::{MemoryAccount, MemoryBackend};
+use evm::executor::{StackExecutor, MemoryStackState, StackSubstateMetadata, PrecompileOutput};
+
+mod common;
+use common::{vicinity, caller, contract, GAS_LIMIT};
+
+fn precompile() -> H160 { H160::from_low_u64_be(1) }
+
+/// Values recorded, keyed by metric name and labels.
+#[derive(Default)]
+struct Values {
+	counters: Mutex<BTreeMap<String, u64>>,
+	histograms: Mutex<BTreeMap<String, Vec<f64>>>,
+}
+
+struct Handle {
+	key: String,
+	values: Arc<Values>,
+}
+
+impl CounterFn for Handle {
+	fn increment(&self, value: u64) {
+		*self.values.counters.lock().unwrap().entry(self.key.clone()).or_default() += value;
+	}
+
+	fn absolute(&self, value: u64) {
+		self.values.counters.lock().unwrap().insert(self.key.clone(), value);
+	}
+}
+
+impl HistogramFn for Handle {
+	fn record(&self, value: f64) {
+		self.values.histograms.lock().unwrap().entry(self.key.clone()).or_default().push(value);
+	}
+}
+
+struct TestRecorder(Arc<Values>);
+
+impl TestRecorder {
+	fn handle(&self, key: &Key) -> Arc<Handle> {
+		let mut name = key.name().to_string();
+		for label in key.labels() {
+			name += &format!(",{}={}", label.key(), label.value());
+		}
+		Arc::new(Handle { key: name, values: self.0.clone() })
+	}
+}
+
+impl Recorder for TestRecorder {
+	fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+	fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+	fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+	fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+		Counter::from_arc(self.handle(key))
+	}
+
+	fn register_gauge(&self, _: &Key, _: &Metadata<'_>) -> Gauge {
+		Gauge::noop()
+	}
+
+	fn register_histogram(&self, key: &Key, _: &Metadata<'_>) -> Histogram {
+		Histogram::from_arc(self.handle(key))
+	}
+}
+
+/// Precompile at address 1, returning nothing.
+fn empty<S>(
+	address: H160,
+	_input: &[u8],
+	_target_gas: Option<u64>,
+	_context: &Context,
+	_state: &mut S,
+	_is_static: bool,
+) -> Option<Result<PrecompileOutput, ExitError>> {
+	if address != precompile() {
+		return None
+	}
+
+	Some(Ok(PrecompileOutput {
+		exit_status: ExitSucceed::Returned,
+		cost: 10,
+		output: Vec::new(),
+		logs: Vec::new(),
+	}))
+}
+
+#[test]
+fn transaction_metrics() {
+	let config = Config::istanbul();
+	let vicinity = vicinity();
+	let mut accounts = BTreeMap::new();
+	// PUSH1 0; SLOAD; POP; PUSH1 0 (5 times); PUSH1 1; GAS; CALL; STOP
+	accounts.insert(contract(), MemoryAccount {
+		code: hex::decode("600054506000600060006000600060015af100").unwrap(),
+		..Default::default()
+	});
+	let backend = MemoryBackend::new(&vicinity, accounts);
+	let metadata = StackSubstateMetadata::new(GAS_LIMIT, &config);
+	let state = MemoryStackState::new(metadata, &backend);
+	let mut executor = StackExecutor::new_with_precompile(state, &config, empty);
+
+	let values = Arc::new(Values::default());
+	let recorder = TestRecorder(values.clone());
+	let (reason, _) = metrics::with_local_recorder(&recorder, || {
+		executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), GAS_LIMIT)
+	});
+	assert!(reason.is_succeed());
+
+	let counters = values.counters.lock().unwrap();
+	assert_eq!(counters["evm_transactions_total"], 1);
+	assert_eq!(counters["evm_opcodes_total"], 12);
+	assert_eq!(counters["evm_precompile_calls_total"], 1);
+	assert_eq!(counters["evm_backend_reads_total,kind=storage"], 1);
+	// Code of the contract and of the precompile address.
+	assert_eq!(counters["evm_backend_reads_total,kind=code"], 2);
+
+	let histograms = values.histograms.lock().unwrap();
+	assert_eq!(histograms["evm_transaction_gas_used"], vec![executor.used_gas() as f64]);
+	assert_eq!(histograms["evm_substate_depth"], vec![0.0, 1.0]);
+}
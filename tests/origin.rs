@@ -0,0 +1,50 @@
+//! Transaction origin overridden with `StackExecutor::set_origin`.
+
+use std::collections::BTreeMap;
+use primitive_types::{H160, U256};
+use evm::Config;
+use evm::backend::{MemoryAccount, MemoryBackend, MemoryVicinity};
+use evm::executor::{StackExecutor, StackState};
+
+mod common;
+use common::{caller, contract, GAS_LIMIT};
+
+fn origin() -> H160 { H160::repeat_byte(0x0a) }
+fn bundler() -> H160 { H160::repeat_byte(0xb0) }
+
+fn vicinity() -> MemoryVicinity {
+	MemoryVicinity {
+		origin: origin(),
+		..common::vicinity()
+	}
+}
+
+/// Return the ORIGIN and CALLER seen by the contract.
+fn seen<'config, S: StackState<'config>>(executor: &mut StackExecutor<'config, S>) -> (H160, H160) {
+	let (reason, output) = executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), GAS_LIMIT);
+	assert!(reason.is_succeed());
+	(H160::from_slice(&output[12..32]), H160::from_slice(&output[44..64]))
+}
+
+#[test]
+fn origin_is_overridden() {
+	let config = Config::istanbul();
+	let vicinity = vicinity();
+	let mut accounts = BTreeMap::new();
+	// ORIGIN; PUSH1 0; MSTORE; CALLER; PUSH1 32; MSTORE; PUSH1 64; PUSH1 0; RETURN
+	accounts.insert(contract(), MemoryAccount {
+		code: hex::decode("326000523360205260406000f3").unwrap(),
+		..Default::default()
+	});
+	let backend = MemoryBackend::new(&vicinity, accounts);
+	let mut executor = common::executor(&backend, &config, u64::MAX);
+
+	assert_eq!(seen(&mut executor), (origin(), caller()));
+
+	executor.set_origin(Some(bundler()));
+	assert_eq!(seen(&mut executor), (bundler(), caller()));
+	assert_eq!(seen(&mut executor), (bundler(), caller()));
+
+	executor.set_origin(None);
+	assert_eq!(seen(&mut executor), (origin(), caller()));
+}
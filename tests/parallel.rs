@@ -0,0 +1,148 @@
+//! Transactions simulated on several threads over a shared backend.
+
+use std::collections::BTreeMap;
+use primitive_types::{H160, U256};
+use evm::Config;
+use evm::backend::{ApplyBackend, Backend, MemoryAccount, MemoryBackend};
+use evm::parallel::{Simulation, SimulationResult, simulate, simulate_parallel};
+
+mod common;
+use common::{vicinity, GAS_LIMIT};
+
+fn sender(index: u8) -> H160 { H160::repeat_byte(0x10 + index) }
+fn recipient() -> H160 { H160::repeat_byte(0xee) }
+fn logger() -> H160 { H160::repeat_byte(0x10) }
+fn balance_reader() -> H160 { H160::repeat_byte(0x20) }
+
+fn accounts() -> BTreeMap<H160, MemoryAccount> {
+	let mut accounts = BTreeMap::new();
+	for index in 1..=8 {
+		accounts.insert(sender(index), MemoryAccount {
+			balance: U256::from(1000),
+			..Default::default()
+		});
+	}
+	// LOG0 of empty data; STOP
+	accounts.insert(logger(), MemoryAccount {
+		code: vec![0x60, 0x00, 0x80, 0xa0, 0x00],
+		..Default::default()
+	});
+	// PUSH20 recipient; BALANCE; PUSH1 0; SSTORE; STOP
+	let mut code = vec![0x73];
+	code.extend_from_slice(&recipient()[..]);
+	code.extend_from_slice(&[0x31, 0x60, 0x00, 0x55, 0x00]);
+	accounts.insert(balance_reader(), MemoryAccount {
+		code,
+		..Default::default()
+	});
+	accounts
+}
+
+/// Transfer `value` from `sender(index)`, calling the logger for odd indices.
+fn transfer(index: u8, value: u64) -> Simulation {
+	Simulation {
+		caller: sender(index),
+		to: Some(if index % 2 == 1 { logger() } else { recipient() }),
+		value: U256::from(value),
+		data: Vec::new(),
+		gas_limit: GAS_LIMIT,
+	}
+}
+
+fn assert_same(left: &SimulationResult, right: &SimulationResult) {
+	assert_eq!(left.exit_reason, right.exit_reason);
+	assert_eq!(left.output, right.output);
+	assert_eq!(left.used_gas, right.used_gas);
+	assert_eq!(format!("{:?}", left.values), format!("{:?}", right.values));
+	assert_eq!(left.logs, right.logs);
+}
+
+#[test]
+fn results_match_sequential_simulation() {
+	let config = Config::istanbul();
+	let vicinity = vicinity();
+	let backend = MemoryBackend::new(&vicinity, accounts());
+	let simulations = (1..=8).map(|index| transfer(index, index as u64)).collect::<Vec<_>>();
+	let sequential = simulations.iter()
+		.map(|simulation| simulate(&backend, &config, simulation))
+		.collect::<Vec<_>>();
+
+	for workers in [0, 1, 3, 8, 16].iter() {
+		let parallel = simulate_parallel(&backend, &config, &simulations, *workers);
+		assert_eq!(parallel.results.len(), sequential.len());
+		for (left, right) in parallel.results.iter().zip(sequential.iter()) {
+			assert!(left.exit_reason.is_succeed());
+			assert_same(left, right);
+		}
+		assert_eq!(parallel.used_gas, sequential.iter().map(|result| result.used_gas).sum::<u64>());
+		// All transfers credit either the recipient or the logger.
+		assert_eq!(
+			parallel.conflicts.into_iter().collect::<Vec<_>>(),
+			vec![logger(), recipient()],
+		);
+	}
+
+	let parallel = simulate_parallel(&backend, &config, &[], 4);
+	assert!(parallel.results.is_empty());
+	assert_eq!(parallel.used_gas, 0);
+	assert!(parallel.conflicts.is_empty());
+}
+
+#[test]
+fn merged_changes_apply_in_order() {
+	let config = Config::istanbul();
+	let vicinity = vicinity();
+	let mut backend = MemoryBackend::new(&vicinity, accounts());
+	let simulations = vec![transfer(2, 100), transfer(3, 200)];
+
+	let parallel = simulate_parallel(&backend, &config, &simulations, 2);
+	assert!(parallel.conflicts.is_empty());
+	let (values, logs) = parallel.into_changes();
+	assert_eq!(logs.len(), 1);
+	assert_eq!(logs[0].address, logger());
+	backend.apply(values, logs, false);
+
+	assert_eq!(backend.basic(sender(2)).balance, U256::from(900));
+	assert_eq!(backend.basic(sender(3)).balance, U256::from(800));
+	assert_eq!(backend.basic(recipient()).balance, U256::from(100));
+	assert_eq!(backend.basic(logger()).balance, U256::from(200));
+}
+
+#[test]
+fn conflicting_changes_are_reported() {
+	let config = Config::istanbul();
+	let vicinity = vicinity();
+	let backend = MemoryBackend::new(&vicinity, accounts());
+
+	// The same sender twice, and two transfers to the recipient.
+	let simulations = vec![transfer(2, 100), transfer(2, 100), transfer(4, 100)];
+	let parallel = simulate_parallel(&backend, &config, &simulations, 3);
+	assert_eq!(
+		parallel.conflicts.into_iter().collect::<Vec<_>>(),
+		vec![sender(2), recipient()],
+	);
+}
+
+#[test]
+fn read_after_write_is_reported() {
+	let config = Config::istanbul();
+	let vicinity = vicinity();
+	let backend = MemoryBackend::new(&vicinity, accounts());
+
+	// The second simulation stores the balance of the recipient, credited by
+	// the first one.
+	let read = Simulation {
+		caller: sender(3),
+		to: Some(balance_reader()),
+		value: U256::zero(),
+		data: Vec::new(),
+		gas_limit: GAS_LIMIT,
+	};
+	let parallel = simulate_parallel(&backend, &config, &[transfer(2, 100), read.clone()], 2);
+	assert!(parallel.results[1].reads.contains(&recipient()));
+	assert_eq!(parallel.conflicts.into_iter().collect::<Vec<_>>(), vec![recipient()]);
+
+	// Reads before the write do not conflict.
+	let parallel = simulate_parallel(&backend, &config, &[read, transfer(2, 100)], 2);
+	assert!(parallel.conflicts.is_empty());
+}
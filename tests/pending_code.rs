@@ -0,0 +1,55 @@
+//! Calls against code not yet deployed with
+//! `StackExecutor::transact_call_with_pending_code`.
+
+use std::collections::BTreeMap;
+use primitive_types::{H160, U256};
+use evm::Config;
+use evm::backend::{Backend, MemoryAccount, MemoryBackend};
+
+mod common;
+use common::{vicinity, caller, GAS_LIMIT};
+
+fn wallet() -> H160 { H160::repeat_byte(0xc0) }
+fn pending() -> H160 { H160::repeat_byte(0xfa) }
+
+/// PUSH1 42; PUSH1 0; MSTORE; PUSH1 32; PUSH1 0; RETURN
+fn pending_code() -> Vec<u8> {
+	hex::decode("602a60005260206000f3").unwrap()
+}
+
+#[test]
+fn pending_code_is_used_and_dropped() {
+	let config = Config::istanbul();
+	let vicinity = vicinity();
+	let mut accounts = BTreeMap::new();
+	// PUSH20 pending; EXTCODESIZE; PUSH1 0; MSTORE; PUSH1 32; PUSH1 0; RETURN
+	let mut code = vec![0x73];
+	code.extend_from_slice(&pending()[..]);
+	code.extend_from_slice(&hex::decode("3b60005260206000f3").unwrap());
+	accounts.insert(wallet(), MemoryAccount { code, ..Default::default() });
+	let backend = MemoryBackend::new(&vicinity, accounts);
+	let mut executor = common::executor(&backend, &config, u64::MAX);
+
+	// Called directly.
+	let (reason, output) = executor.transact_call_with_pending_code(
+		pending(), pending_code(), caller(), pending(), U256::zero(), Vec::new(), GAS_LIMIT,
+	);
+	assert!(reason.is_succeed());
+	assert_eq!(U256::from_big_endian(&output), U256::from(42));
+
+	// Seen by another contract.
+	let (reason, output) = executor.transact_call_with_pending_code(
+		pending(), pending_code(), caller(), wallet(), U256::zero(), Vec::new(), GAS_LIMIT,
+	);
+	assert!(reason.is_succeed());
+	assert_eq!(U256::from_big_endian(&output), U256::from(pending_code().len()));
+
+	// Gone after the transaction.
+	let (reason, output) = executor.transact_call(caller(), pending(), U256::zero(), Vec::new(), GAS_LIMIT);
+	assert!(reason.is_succeed());
+	assert!(output.is_empty());
+	let (reason, output) = executor.transact_call(caller(), wallet(), U256::zero(), Vec::new(), GAS_LIMIT);
+	assert!(reason.is_succeed());
+	assert_eq!(U256::from_big_endian(&output), U256::zero());
+	assert!(executor.state().code(pending()).is_empty());
+}
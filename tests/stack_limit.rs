@@ -0,0 +1,78 @@
+//! `Config::stack_limit`, applied by `StackExecutor` to EVM and host-defined
+//! opcodes.
+
+use std::collections::BTreeMap;
+use primitive_types::{H256, U256};
+use evm::{Config, Context, CustomOpcode, ExitError, ExitReason, ExitSucceed, Opcode};
+use evm::backend::{MemoryAccount, MemoryBackend};
+use evm::executor::CustomOpcodes;
+
+mod common;
+use common::{vicinity, caller, contract, GAS_LIMIT};
+
+/// Pop one value and push it twice.
+const TWICE: Opcode = Opcode(0xc0);
+
+struct Host;
+
+impl<S> CustomOpcodes<S> for Host {
+	fn signature(&self, opcode: Opcode) -> Option<CustomOpcode> {
+		match opcode {
+			TWICE => Some(CustomOpcode { inputs: 1, outputs: 2, gas: 1 }),
+			_ => None,
+		}
+	}
+
+	fn execute(
+		&mut self,
+		_opcode: Opcode,
+		_context: &Context,
+		inputs: Vec<H256>,
+		_state: &mut S,
+	) -> Result<Vec<H256>, ExitError> {
+		Ok(vec![inputs[0], inputs[0]])
+	}
+}
+
+/// Call a contract with `code` under a stack limit of `stack_limit`.
+fn call(code: Vec<u8>, stack_limit: usize) -> ExitReason {
+	let config = Config { stack_limit, ..Config::istanbul() };
+	let vicinity = vicinity();
+	let mut accounts = BTreeMap::new();
+	accounts.insert(contract(), MemoryAccount { code, ..Default::default() });
+	let backend = MemoryBackend::new(&vicinity, accounts);
+	let mut executor = common::executor(&backend, &config, GAS_LIMIT);
+	executor.set_custom_opcodes(Box::new(Host));
+
+	executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), GAS_LIMIT).0
+}
+
+#[test]
+fn limit_reaches_the_runtime() {
+	let stopped = ExitReason::Succeed(ExitSucceed::Stopped);
+	let overflow = ExitReason::Error(ExitError::StackOverflow);
+
+	// PUSH1 1; DUP1; DUP1; STOP
+	let code = hex::decode("6001808000").unwrap();
+	assert_eq!(call(code.clone(), 3), stopped);
+	assert_eq!(call(code, 2), overflow);
+
+	// 1025 times PUSH1 1, over the mainnet limit.
+	let mut code = hex::decode("6001").unwrap().repeat(1025);
+	code.push(0x00);
+	assert_eq!(call(code.clone(), 1024), overflow);
+	assert_eq!(call(code, 2048), stopped);
+}
+
+#[test]
+fn limit_applies_to_host_outputs() {
+	let stopped = ExitReason::Succeed(ExitSucceed::Stopped);
+	let overflow = ExitReason::Error(ExitError::StackOverflow);
+
+	// PUSH1 1; TWICE; STOP
+	assert_eq!(call(vec![0x60, 0x01, 0xc0, 0x00], 2), stopped);
+	// PUSH1 1; PUSH1 1; TWICE; STOP
+	let code = vec![0x60, 0x01, 0x60, 0x01, 0xc0, 0x00];
+	assert_eq!(call(code.clone(), 3), stopped);
+	assert_eq!(call(code, 2), overflow);
+}
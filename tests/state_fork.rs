@@ -0,0 +1,159 @@
+//! Forked states read through the layers they share, diverge independently,
+//! and deconstruct to the changes of an unforked state.
+
+use std::collections::BTreeMap;
+use primitive_types::{H160, H256, U256};
+use evm::Config;
+use evm::backend::{Backend, MemoryAccount, MemoryBackend};
+use evm::executor::{DepositState, MemoryStackState, StackState, StackSubstateMetadata};
+
+mod common;
+use common::vicinity;
+
+fn address(n: u64) -> H160 {
+	H160::from_low_u64_be(n)
+}
+
+fn key(n: u64) -> H256 {
+	H256::from_low_u64_be(n)
+}
+
+/// Accounts 1 to 3, each with a balance of 100 and slots 1 and 2 set to 1.
+fn accounts() -> BTreeMap<H160, MemoryAccount> {
+	(1..=3).map(|n| {
+		let account = MemoryAccount {
+			nonce: U256::one(),
+			balance: U256::from(100),
+			storage: vec![(key(1), key(1)), (key(2), key(1))].into_iter().collect(),
+			code: vec![0x00],
+		};
+		(address(n), account)
+	}).collect()
+}
+
+/// Changes of a state, in a comparable form. `changes` deconstructs a copy
+/// of the state.
+fn changes<B: Backend>(state: &MemoryStackState<'_, '_, B>) -> String {
+	format!("{:?}", state.changes())
+}
+
+/// First half of the writes, covering storage, balances, resets and deletes.
+fn first<'config, S: DepositState<'config>>(state: &mut S) {
+	state.set_storage(address(1), key(1), key(7));
+	state.deposit(address(1), U256::from(5));
+	state.reset_storage(address(2));
+	state.set_storage(address(2), key(2), key(9));
+	state.set_deleted(address(3));
+	state.log(address(1), vec![key(1)], vec![1]);
+}
+
+/// Second half of the writes, on top of the accounts written by `first`.
+fn second<'config, S: StackState<'config>>(state: &mut S) {
+	state.set_storage(address(1), key(2), key(8));
+	state.inc_nonce(address(2));
+	state.set_storage(address(2), key(3), key(3));
+	state.set_code(address(4), vec![0x60, 0x00]);
+	state.log(address(2), Vec::new(), vec![2]);
+}
+
+#[test]
+fn reads_fall_through_base_layers() {
+	let config = Config::istanbul();
+	let vicinity = vicinity();
+	let backend = MemoryBackend::new(&vicinity, accounts());
+	let metadata = StackSubstateMetadata::new(u64::MAX, &config);
+
+	let mut state = MemoryStackState::new(metadata, &backend);
+	first(&mut state);
+	let _sibling = state.fork();
+	let mut fork = state.fork();
+	state.inc_nonce(address(1));
+
+	assert_eq!(fork.storage(address(1), key(1)), key(7));
+	assert_eq!(fork.storage(address(1), key(2)), key(1));
+	assert_eq!(fork.basic(address(1)).balance, U256::from(105));
+	assert_eq!(fork.basic(address(1)).nonce, U256::one());
+	assert_eq!(fork.storage(address(2), key(1)), H256::default());
+	assert_eq!(fork.storage(address(2), key(2)), key(9));
+	assert_eq!(fork.original_storage(address(2), key(1)), Some(H256::default()));
+	assert!(fork.deleted(address(3)));
+	assert!(!fork.deleted(address(1)));
+
+	// Modifying a reset account in the fork keeps its storage reset.
+	fork.inc_nonce(address(2));
+	assert_eq!(fork.storage(address(2), key(1)), H256::default());
+	assert_eq!(fork.storage(address(2), key(2)), key(9));
+
+	// Reads go through several layers of forks.
+	let nested = fork.fork();
+	assert_eq!(nested.basic(address(2)).nonce, U256::from(2));
+	assert_eq!(nested.storage(address(2), key(1)), H256::default());
+	assert_eq!(nested.storage(address(1), key(1)), key(7));
+	assert!(nested.deleted(address(3)));
+}
+
+#[test]
+fn divergent_writes_stay_isolated() {
+	let config = Config::istanbul();
+	let vicinity = vicinity();
+	let backend = MemoryBackend::new(&vicinity, accounts());
+	let metadata = StackSubstateMetadata::new(u64::MAX, &config);
+
+	let mut state = MemoryStackState::new(metadata, &backend);
+	first(&mut state);
+	let mut fork = state.fork();
+
+	state.set_storage(address(1), key(1), key(10));
+	state.deposit(address(2), U256::from(1));
+	state.set_deleted(address(1));
+	fork.set_storage(address(1), key(1), key(20));
+	fork.reset_storage(address(1));
+
+	assert_eq!(state.storage(address(1), key(1)), key(10));
+	assert_eq!(state.storage(address(1), key(2)), key(1));
+	assert_eq!(state.basic(address(2)).balance, U256::from(101));
+	assert!(state.deleted(address(1)));
+
+	assert_eq!(fork.storage(address(1), key(1)), H256::default());
+	assert_eq!(fork.storage(address(1), key(2)), H256::default());
+	assert_eq!(fork.basic(address(2)).balance, U256::from(100));
+	assert!(!fork.deleted(address(1)));
+}
+
+#[test]
+fn forked_changes_match_unforked() {
+	let config = Config::istanbul();
+	let vicinity = vicinity();
+	let backend = MemoryBackend::new(&vicinity, accounts());
+	let metadata = StackSubstateMetadata::new(u64::MAX, &config);
+
+	let mut unforked = MemoryStackState::new(metadata.clone(), &backend);
+	first(&mut unforked);
+	second(&mut unforked);
+	let expected = changes(&unforked);
+
+	// The original state and its fork, sharing the base layer.
+	let mut state = MemoryStackState::new(metadata.clone(), &backend);
+	first(&mut state);
+	let mut fork = state.fork();
+	second(&mut state);
+	second(&mut fork);
+	assert_eq!(changes(&state), expected);
+	assert_eq!(changes(&fork), expected);
+
+	// Several layers.
+	let mut state = MemoryStackState::new(metadata, &backend);
+	first(&mut state);
+	let _fork = state.fork();
+	state.set_storage(address(1), key(2), key(5));
+	let _fork = state.fork();
+	second(&mut state);
+	assert_eq!(state.substate().debug_dump(), {
+		let mut unforked = MemoryStackState::new(StackSubstateMetadata::new(u64::MAX, &config), &backend);
+		first(&mut unforked);
+		unforked.set_storage(address(1), key(2), key(5));
+		second(&mut unforked);
+		unforked.substate().debug_dump()
+	});
+	assert_eq!(changes(&state), expected);
+}
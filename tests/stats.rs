@@ -0,0 +1,62 @@
+//! Execution statistics collected over calls between contracts.
+
+#![cfg(feature = "tracing")]
+
+use std::collections::BTreeMap;
+use primitive_types::{H160, U256};
+use evm::{Config, Opcode};
+use evm::backend::{MemoryAccount, MemoryBackend};
+use evm::stats::{ExecutionStats, StatsListener};
+use evm::tracing::runtime;
+
+mod common;
+use common::{vicinity, caller};
+
+fn outer() -> H160 { H160::repeat_byte(0x0a) }
+fn inner() -> H160 { H160::repeat_byte(0x0b) }
+
+#[test]
+fn stats_of_a_transaction() {
+	// PUSH1 1; PUSH1 0; SSTORE; PUSH1 0; SLOAD; PUSH1 0; MSTORE;
+	// PUSH1 0 (5 times); PUSH20 inner; GAS; CALL; POP; STOP
+	let mut outer_code = hex::decode("60016000556000546000526000600060006000600073").unwrap();
+	outer_code.extend_from_slice(&inner()[..]);
+	outer_code.extend_from_slice(&[0x5a, 0xf1, 0x50, 0x00]);
+	// PUSH1 0; SLOAD; POP; STOP
+	let inner_code = vec![0x60, 0x00, 0x54, 0x50, 0x00];
+
+	let config = Config::istanbul();
+	let vicinity = vicinity();
+	let mut accounts = BTreeMap::new();
+	accounts.insert(outer(), MemoryAccount { code: outer_code, ..Default::default() });
+	accounts.insert(inner(), MemoryAccount { code: inner_code, ..Default::default() });
+	let backend = MemoryBackend::new(&vicinity, accounts);
+	let mut executor = common::executor(&backend, &config, u64::MAX);
+
+	let mut listener = StatsListener::new();
+	let (reason, _) = runtime::using(&mut listener, || {
+		executor.transact_call(caller(), outer(), U256::zero(), Vec::new(), 1_000_000)
+	});
+	assert!(reason.is_succeed());
+
+	let stats = listener.stats();
+	assert_eq!(stats.steps(), 17 + 4);
+	assert_eq!(stats.opcodes[Opcode::PUSH1.as_usize()], 10);
+	assert_eq!(stats.opcodes[Opcode::STOP.as_usize()], 2);
+	assert_eq!(stats.histogram()[0], (Opcode::PUSH1, 10));
+	// Ties are ordered by opcode.
+	assert_eq!(stats.histogram()[1], (Opcode::STOP, 2));
+	assert_eq!(stats.histogram()[2], (Opcode::POP, 2));
+	assert_eq!(stats.histogram()[3], (Opcode::SLOAD, 2));
+	// The 7 arguments of CALL.
+	assert_eq!(stats.max_stack, 7);
+	assert_eq!(stats.max_memory, 32);
+	assert_eq!(stats.external_calls, 1);
+	assert_eq!(stats.storage_reads, 2);
+	assert_eq!(stats.storage_writes, 1);
+
+	let taken = listener.take();
+	assert_eq!(taken.steps(), 21);
+	assert_eq!(listener.stats(), &ExecutionStats::default());
+	assert!(listener.stats().histogram().is_empty());
+}
@@ -0,0 +1,45 @@
+//! Errors of strict stack validation, carrying the offending opcode.
+
+use std::collections::BTreeMap;
+use primitive_types::U256;
+use evm::{Config, ExitError, ExitReason, Opcode};
+use evm::backend::{MemoryAccount, MemoryBackend};
+
+mod common;
+use common::{vicinity, caller, contract};
+
+/// Call a contract with `code` under `config`.
+fn call(config: &Config, code: Vec<u8>) -> ExitReason {
+	let vicinity = vicinity();
+	let mut accounts = BTreeMap::new();
+	accounts.insert(contract(), MemoryAccount { code, ..Default::default() });
+	let backend = MemoryBackend::new(&vicinity, accounts);
+	let mut executor = common::executor(&backend, config, u64::MAX);
+	executor.transact_call(caller(), contract(), U256::zero(), Vec::new(), 100_000).0
+}
+
+#[test]
+fn underflow_carries_the_opcode() {
+	let strict = Config { strict_stack_validation: true, ..Config::istanbul() };
+
+	// PUSH1 1; ADD
+	assert_eq!(
+		call(&strict, vec![0x60, 0x01, 0x01]),
+		ExitReason::Error(ExitError::OpcodeStackUnderflow(Opcode::ADD)),
+	);
+	// Three items for CALL, which pops seven.
+	assert_eq!(
+		call(&strict, vec![0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0xf1]),
+		ExitReason::Error(ExitError::OpcodeStackUnderflow(Opcode::CALL)),
+	);
+	// Enough items.
+	assert!(call(&strict, vec![0x60, 0x01, 0x60, 0x01, 0x01, 0x00]).is_succeed());
+}
+
+#[test]
+fn underflow_without_validation() {
+	assert_eq!(
+		call(&Config::istanbul(), vec![0x60, 0x01, 0x01]),
+		ExitReason::Error(ExitError::StackUnderflow),
+	);
+}
@@ -0,0 +1,66 @@
+//! Gas and refund of `SUICIDE` taken from the config.
+
+use std::collections::BTreeMap;
+use primitive_types::{H160, U256};
+use evm::{Config, SuicideGas};
+use evm::backend::{MemoryAccount, MemoryBackend};
+use evm::executor::{StackState, ExecutionSummary};
+
+mod common;
+use common::{vicinity, caller, GAS_LIMIT};
+
+fn suicidal() -> H160 { H160::repeat_byte(0x5d) }
+fn beneficiary() -> H160 { H160::repeat_byte(0xbe) }
+
+/// Call a contract destroying itself, and return the gas accounting.
+fn suicide(config: &Config) -> ExecutionSummary {
+	let vicinity = vicinity();
+	let mut accounts = BTreeMap::new();
+	// PUSH20 beneficiary; SUICIDE
+	let mut code = vec![0x73];
+	code.extend_from_slice(&beneficiary()[..]);
+	code.push(0xff);
+	accounts.insert(suicidal(), MemoryAccount { code, ..Default::default() });
+	let backend = MemoryBackend::new(&vicinity, accounts);
+	let mut executor = common::executor(&backend, config, GAS_LIMIT);
+
+	let (reason, _) = executor.transact_call(caller(), suicidal(), U256::zero(), Vec::new(), GAS_LIMIT);
+	assert!(reason.is_succeed());
+	assert!(executor.state().deleted(suicidal()));
+	executor.execution_summary()
+}
+
+#[test]
+fn presets() {
+	assert_eq!(Config::frontier().suicide_gas(), SuicideGas::FRONTIER);
+	assert_eq!(Config::istanbul().suicide_gas(), SuicideGas::EIP150);
+
+	let config = Config::istanbul().with_suicide_gas(SuicideGas::EIP3529);
+	assert_eq!(config.suicide_gas(), SuicideGas::EIP3529);
+	assert_eq!(config.refund_suicide, 0);
+	assert_eq!(config.gas_suicide, 5000);
+}
+
+#[test]
+fn refund_follows_config() {
+	let summary = suicide(&Config::istanbul());
+	assert_eq!(summary.refund_requested, 24000);
+	// Capped at half the gas used.
+	assert_eq!(summary.refund_applied, summary.gas_used_pre_refund / 2);
+
+	let without_refund = suicide(&Config { refund_suicide: 0, ..Config::istanbul() });
+	assert_eq!(without_refund.refund_requested, 0);
+	assert_eq!(without_refund.refund_applied, 0);
+	assert_eq!(without_refund.gas_used_pre_refund, summary.gas_used_pre_refund);
+	assert_eq!(without_refund.effective_gas_used, summary.gas_used_pre_refund);
+
+	let eip3529 = suicide(&Config::istanbul().with_suicide_gas(SuicideGas::EIP3529));
+	assert_eq!(eip3529, without_refund);
+}
+
+#[test]
+fn base_cost_follows_config() {
+	let istanbul = suicide(&Config::istanbul());
+	let cheaper = suicide(&Config { gas_suicide: 1000, ..Config::istanbul() });
+	assert_eq!(istanbul.gas_used_pre_refund - cheaper.gas_used_pre_refund, 4000);
+}
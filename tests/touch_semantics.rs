@@ -0,0 +1,143 @@
+//! Touch and empty account semantics of value transfers (EIP-161).
+//!
+//! A contract calls a target with a given value and gas. The post-state shows
+//! whether the target was touched, created or cleared.
+
+use std::{collections::BTreeMap, str::FromStr};
+use primitive_types::{H160, U256};
+use evm::{Config, Context, ExitError, ExitSucceed};
+use evm::backend::{MemoryAccount, MemoryVicinity, MemoryBackend, ApplyBackend};
+use evm::executor::{StackExecutor, MemoryStackState, StackSubstateMetadata, PrecompileOutput};
+
+/// Identity precompile at address 4.
+fn identity<S>(
+	address: H160,
+	input: &[u8],
+	target_gas: Option<u64>,
+	_context: &Context,
+	_state: &mut S,
+	_is_static: bool,
+) -> Option<Result<PrecompileOutput, ExitError>> {
+	if address != H160::from_low_u64_be(4) {
+		return None
+	}
+
+	let cost = 15 + 3 * input.chunks(32).count() as u64;
+	if target_gas.map(|gas| gas < cost).unwrap_or(false) {
+		return Some(Err(ExitError::OutOfGas))
+	}
+
+	Some(Ok(PrecompileOutput {
+		exit_status: ExitSucceed::Returned,
+		cost,
+		output: input.to_vec(),
+		logs: Vec::new(),
+	}))
+}
+
+fn empty() -> MemoryAccount {
+	MemoryAccount {
+		nonce: U256::zero(),
+		balance: U256::zero(),
+		storage: BTreeMap::new(),
+		code: Vec::new(),
+	}
+}
+
+/// Call `target` from a contract with `value` and `gas`, starting from `pre`,
+/// and return the post-state.
+fn call(
+	config: &Config,
+	mut pre: BTreeMap<H160, MemoryAccount>,
+	target: H160,
+	value: u8,
+	gas: u16,
+) -> BTreeMap<H160, MemoryAccount> {
+	let caller = H160::from_str("0xf000000000000000000000000000000000000000").unwrap();
+	let contract = H160::from_str("0x1000000000000000000000000000000000000000").unwrap();
+
+	// CALL(gas, target, value, 0, 0, 0, 0); STOP
+	let mut code = hex::decode("600060006000600060").unwrap();
+	code.push(value);
+	code.push(0x73);
+	code.extend_from_slice(&target[..]);
+	code.push(0x61);
+	code.extend_from_slice(&gas.to_be_bytes());
+	code.extend_from_slice(&[0xf1, 0x00]);
+
+	pre.insert(caller, MemoryAccount {
+		balance: U256::from(10000000),
+		nonce: U256::one(),
+		..empty()
+	});
+	pre.insert(contract, MemoryAccount {
+		balance: U256::from(100),
+		nonce: U256::one(),
+		code,
+		..empty()
+	});
+
+	let vicinity = MemoryVicinity {
+		gas_price: U256::zero(),
+		origin: caller,
+		block_hashes: Vec::new(),
+		max_block_hashes: 256,
+		block_number: Default::default(),
+		block_coinbase: Default::default(),
+		block_timestamp: Default::default(),
+		block_difficulty: Default::default(),
+		block_gas_limit: Default::default(),
+		chain_id: U256::one(),
+	};
+
+	let gas_limit = 1_000_000;
+	let mut backend = MemoryBackend::new(&vicinity, pre);
+	let metadata = StackSubstateMetadata::new(gas_limit, config);
+	let state = MemoryStackState::new(metadata, &backend);
+	let mut executor = StackExecutor::new_with_precompile(state, config, identity);
+
+	let (reason, _) = executor.transact_call(caller, contract, U256::zero(), Vec::new(), gas_limit);
+	assert!(reason.is_succeed());
+
+	let (values, logs) = executor.into_state().deconstruct();
+	backend.apply(values, logs, config.clear_empty_touched_accounts);
+	backend.state().clone()
+}
+
+#[test]
+fn zero_value_call_clears_empty_account() {
+	let target = H160::repeat_byte(0xee);
+	let pre = vec![(target, empty())].into_iter().collect::<BTreeMap<_, _>>();
+
+	assert!(call(&Config::frontier(), pre.clone(), target, 0, 10000).contains_key(&target));
+	assert!(!call(&Config::istanbul(), pre, target, 0, 10000).contains_key(&target));
+}
+
+#[test]
+fn value_call_creates_missing_precompile_account() {
+	let precompile = H160::from_low_u64_be(4);
+
+	for config in &[Config::frontier(), Config::istanbul()] {
+		let post = call(config, BTreeMap::new(), precompile, 1, 10000);
+		assert_eq!(post.get(&precompile).map(|account| account.balance), Some(U256::one()));
+	}
+}
+
+#[test]
+fn zero_value_call_clears_empty_precompile_account() {
+	let precompile = H160::from_low_u64_be(4);
+	let pre = vec![(precompile, empty())].into_iter().collect::<BTreeMap<_, _>>();
+
+	assert!(call(&Config::frontier(), pre.clone(), precompile, 0, 10000).contains_key(&precompile));
+	assert!(!call(&Config::istanbul(), pre, precompile, 0, 10000).contains_key(&precompile));
+}
+
+#[test]
+fn failed_precompile_call_reverts_touch() {
+	let precompile = H160::from_low_u64_be(4);
+	let pre = vec![(precompile, empty())].into_iter().collect::<BTreeMap<_, _>>();
+
+	// Not enough gas for the precompile: the call frame fails and its touch
+	// is reverted with it, so the empty account survives.
+	assert!(call(&Config::istanbul(), pre, precompile, 0, 1).contains_key(&precompile));
+}
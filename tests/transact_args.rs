@@ -0,0 +1,102 @@
+//! Transactions described with `CallArgs` and `CreateArgs`.
+
+use std::collections::BTreeMap;
+use primitive_types::{H160, H256, U256};
+use evm::{Config, CreateScheme};
+use evm::backend::{Backend, MemoryAccount, MemoryBackend, MemoryVicinity};
+use evm::executor::{CallArgs, CreateArgs};
+
+mod common;
+use common::{caller, contract, GAS_LIMIT};
+
+fn origin() -> H160 { H160::repeat_byte(0x0a) }
+fn bundler() -> H160 { H160::repeat_byte(0xb0) }
+fn pending() -> H160 { H160::repeat_byte(0xfa) }
+
+/// Return ORIGIN, CALLVALUE and the first word of the call data.
+fn echo_code() -> Vec<u8> {
+	hex::decode("326000523460205260003560405260606000f3").unwrap()
+}
+
+fn vicinity() -> MemoryVicinity {
+	MemoryVicinity {
+		origin: origin(),
+		..common::vicinity()
+	}
+}
+
+fn accounts() -> BTreeMap<H160, MemoryAccount> {
+	let mut accounts = BTreeMap::new();
+	accounts.insert(caller(), MemoryAccount { balance: U256::from(1000), ..Default::default() });
+	accounts.insert(contract(), MemoryAccount { code: echo_code(), ..Default::default() });
+	accounts
+}
+
+/// Split the output of the echo code into origin, value and data.
+fn echoed(output: &[u8]) -> (H160, U256, U256) {
+	(
+		H160::from_slice(&output[12..32]),
+		U256::from_big_endian(&output[32..64]),
+		U256::from_big_endian(&output[64..96]),
+	)
+}
+
+#[test]
+fn call_args() {
+	let config = Config::istanbul();
+	let vicinity = vicinity();
+	let backend = MemoryBackend::new(&vicinity, accounts());
+	let mut executor = common::executor(&backend, &config, u64::MAX);
+
+	let mut data = [0u8; 32];
+	data[31] = 7;
+	let args = CallArgs::new(caller(), contract(), GAS_LIMIT)
+		.value(U256::from(5))
+		.data(data.to_vec())
+		.origin(bundler());
+	let (reason, output) = executor.transact_call_with_args(args);
+	assert!(reason.is_succeed());
+	assert_eq!(echoed(&output), (bundler(), U256::from(5), U256::from(7)));
+	assert_eq!(executor.state().basic(contract()).balance, U256::from(5));
+
+	// The origin override only lasts for the transaction.
+	let (reason, output) = executor.transact_call_with_args(CallArgs::new(caller(), contract(), GAS_LIMIT));
+	assert!(reason.is_succeed());
+	assert_eq!(echoed(&output), (origin(), U256::zero(), U256::zero()));
+
+	let args = CallArgs::new(caller(), pending(), GAS_LIMIT).pending_code(pending(), echo_code());
+	let (reason, output) = executor.transact_call_with_args(args);
+	assert!(reason.is_succeed());
+	assert_eq!(echoed(&output), (origin(), U256::zero(), U256::zero()));
+}
+
+#[test]
+fn create_args() {
+	let config = Config::istanbul();
+	let vicinity = vicinity();
+	let backend = MemoryBackend::new(&vicinity, accounts());
+	let mut executor = common::executor(&backend, &config, u64::MAX);
+
+	// STOP
+	let init_code = vec![0x00];
+
+	let legacy = executor.create_address(CreateScheme::Legacy { caller: caller() });
+	let reason = executor.transact_create_with_args(
+		CreateArgs::new(caller(), init_code.clone(), GAS_LIMIT).value(U256::from(3)),
+	);
+	assert!(reason.is_succeed());
+	assert_eq!(executor.state().basic(legacy).balance, U256::from(3));
+
+	let salt = H256::repeat_byte(0x5a);
+	let salted = executor.create_address(CreateScheme::Create2 {
+		caller: caller(),
+		code_hash: config.keccak256(&init_code),
+		salt,
+	});
+	let reason = executor.transact_create_with_args(
+		CreateArgs::new(caller(), init_code, GAS_LIMIT).salt(salt).origin(bundler()),
+	);
+	assert!(reason.is_succeed());
+	assert!(executor.state().exists(salted));
+	assert_eq!(executor.state().basic(salted).nonce, U256::one());
+}
@@ -0,0 +1,127 @@
+//! Watchdog callbacks on call depth and remaining gas.
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+use primitive_types::{H160, U256};
+use evm::{Config, ExitError, ExitReason};
+use evm::backend::{MemoryAccount, MemoryBackend};
+use evm::executor::Watchdog;
+
+mod common;
+use common::{vicinity, caller};
+
+const GAS_LIMIT: u64 = 1_000_000;
+
+fn recursive() -> H160 { H160::repeat_byte(0x0a) }
+fn looping() -> H160 { H160::repeat_byte(0x0b) }
+
+/// Callback triggered on a watchdog.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Callback {
+	Depth(usize),
+	LowGas { gas_left: u64, gas_limit: u64 },
+}
+
+/// Watchdog recording its callbacks, and failing at `abort_depth`.
+struct Recorder {
+	depths: Vec<usize>,
+	abort_depth: Option<usize>,
+	low_gas_percent: Option<u64>,
+	seen: Rc<RefCell<Vec<Callback>>>,
+}
+
+impl Watchdog for Recorder {
+	fn depth_thresholds(&self) -> &[usize] {
+		&self.depths
+	}
+
+	fn low_gas_percent(&self) -> Option<u64> {
+		self.low_gas_percent
+	}
+
+	fn on_depth(&mut self, depth: usize) -> Result<(), ExitError> {
+		self.seen.borrow_mut().push(Callback::Depth(depth));
+		if Some(depth) == self.abort_depth {
+			return Err(ExitError::Other("too deep".into()))
+		}
+		Ok(())
+	}
+
+	fn on_low_gas(&mut self, gas_left: u64, gas_limit: u64) -> Result<(), ExitError> {
+		self.seen.borrow_mut().push(Callback::LowGas { gas_left, gas_limit });
+		Ok(())
+	}
+}
+
+/// Call `target` with `watchdog` installed and return the exit reason.
+fn call(target: H160, watchdog: Recorder) -> ExitReason {
+	// A shallow call stack, so that the recursion stops early.
+	let config = Config { call_stack_limit: 8, ..Config::istanbul() };
+	let vicinity = vicinity();
+	let mut accounts = BTreeMap::new();
+	// PUSH1 0 (5 times); ADDRESS; GAS; CALL; STOP
+	accounts.insert(recursive(), MemoryAccount {
+		code: hex::decode("60006000600060006000305af100").unwrap(),
+		..Default::default()
+	});
+	// JUMPDEST; PUSH1 0; JUMP
+	accounts.insert(looping(), MemoryAccount {
+		code: hex::decode("5b600056").unwrap(),
+		..Default::default()
+	});
+	let backend = MemoryBackend::new(&vicinity, accounts);
+	let mut executor = common::executor(&backend, &config, GAS_LIMIT);
+	executor.set_watchdog(Box::new(watchdog));
+
+	let (reason, _) = executor.transact_call(caller(), target, U256::zero(), Vec::new(), GAS_LIMIT);
+	reason
+}
+
+#[test]
+fn depth_thresholds() {
+	let seen = Rc::new(RefCell::new(Vec::new()));
+	let reason = call(recursive(), Recorder {
+		depths: vec![1, 3],
+		abort_depth: None,
+		low_gas_percent: None,
+		seen: seen.clone(),
+	});
+	assert!(reason.is_succeed());
+	assert_eq!(*seen.borrow(), vec![Callback::Depth(1), Callback::Depth(3)]);
+}
+
+#[test]
+fn failing_depth_callback_aborts_the_frame() {
+	let seen = Rc::new(RefCell::new(Vec::new()));
+	let reason = call(recursive(), Recorder {
+		depths: vec![2, 3],
+		abort_depth: Some(2),
+		low_gas_percent: None,
+		seen: seen.clone(),
+	});
+	// The call at depth 2 fails, and the frames above it carry on.
+	assert!(reason.is_succeed());
+	assert_eq!(*seen.borrow(), vec![Callback::Depth(2)]);
+}
+
+#[test]
+fn low_gas_is_reported_once_per_frame() {
+	let seen = Rc::new(RefCell::new(Vec::new()));
+	let reason = call(looping(), Recorder {
+		depths: Vec::new(),
+		abort_depth: None,
+		low_gas_percent: Some(50),
+		seen: seen.clone(),
+	});
+	assert_eq!(reason, ExitReason::Error(ExitError::OutOfGas));
+
+	let seen = seen.borrow();
+	assert_eq!(seen.len(), 1);
+	let (gas_left, gas_limit) = match seen[0] {
+		Callback::LowGas { gas_left, gas_limit } => (gas_left, gas_limit),
+		callback => panic!("unexpected {:?}", callback),
+	};
+	assert!(gas_left < gas_limit / 2);
+	assert!(gas_left + 20 >= gas_limit / 2);
+}
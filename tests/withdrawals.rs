@@ -0,0 +1,59 @@
+//! EIP-4895 withdrawals credited to a backend.
+
+use std::collections::BTreeMap;
+use primitive_types::{H160, H256, U256};
+use evm::backend::{Backend, MemoryAccount, MemoryBackend, Withdrawal, process_withdrawals};
+
+mod common;
+use common::{vicinity, contract};
+
+fn validator() -> H160 { H160::repeat_byte(0xaa) }
+fn untouched() -> H160 { H160::repeat_byte(0x00) }
+
+fn withdrawal(index: u64, address: H160, amount: u64) -> Withdrawal {
+	Withdrawal { index, validator_index: 7, address, amount }
+}
+
+#[test]
+fn amounts_are_credited_in_wei() {
+	let vicinity = vicinity();
+	let mut accounts = BTreeMap::new();
+	// A contract reverting whenever it is called.
+	accounts.insert(contract(), MemoryAccount {
+		nonce: U256::one(),
+		balance: U256::from(5),
+		storage: vec![(H256::zero(), H256::repeat_byte(1))].into_iter().collect(),
+		code: vec![0x60, 0x00, 0x80, 0xfd],
+	});
+	let mut backend = MemoryBackend::new(&vicinity, accounts);
+
+	process_withdrawals(&mut backend, &[
+		withdrawal(0, validator(), 1),
+		withdrawal(1, contract(), 2),
+		withdrawal(2, validator(), 3),
+		withdrawal(3, untouched(), 0),
+	]);
+
+	let gwei = U256::from(1_000_000_000u64);
+	assert_eq!(backend.basic(validator()).balance, gwei * 4);
+	assert_eq!(backend.basic(validator()).nonce, U256::zero());
+
+	// Credited without running code, which leaves the account as it was.
+	let account = &backend.state()[&contract()];
+	assert_eq!(account.balance, gwei * 2 + 5);
+	assert_eq!(account.nonce, U256::one());
+	assert_eq!(account.code, vec![0x60, 0x00, 0x80, 0xfd]);
+	assert_eq!(account.storage.get(&H256::zero()), Some(&H256::repeat_byte(1)));
+
+	// Zero amounts do not create the account.
+	assert!(!backend.state().contains_key(&untouched()));
+}
+
+#[test]
+fn amount_wei() {
+	assert_eq!(withdrawal(0, validator(), 0).amount_wei(), U256::zero());
+	assert_eq!(
+		withdrawal(0, validator(), u64::MAX).amount_wei(),
+		U256::from(u64::MAX) * U256::from(1_000_000_000u64),
+	);
+}